@@ -0,0 +1,276 @@
+use crate::metadata::normalize_date;
+use crate::types::DiscListItem;
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Which albums a sync, list or dry run covers, evaluated against the fields
+/// of the purchase list. Every given criterion must match; values within one
+/// criterion are alternatives. An empty filter selects every album.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlbumFilter {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ids: Vec<String>,
+    /// Label names, matched case-insensitively as substrings like `status --label`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    /// Tags, matched case-insensitively.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Inclusive; albums without a release date, even after filling it in from
+    /// their details, never match a date bound.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub released_after: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub released_before: Option<NaiveDate>,
+    /// Regular expression searched in the title.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl AlbumFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Only album IDs are given, so the albums can be fetched directly rather
+    /// than picked from the purchase list.
+    pub fn only_ids(&self) -> bool {
+        !self.ids.is_empty()
+            && Self {
+                ids: Vec::new(),
+                ..self.clone()
+            }
+            .is_empty()
+    }
+
+    /// A release date bound is given, so albums the purchase list lists without
+    /// a date need their details before they can match.
+    pub fn has_date_bounds(&self) -> bool {
+        self.released_after.is_some() || self.released_before.is_some()
+    }
+
+    /// Trim the values, drop empty ones and check the title regex.
+    pub fn normalized(mut self) -> Result<Self> {
+        for values in [&mut self.ids, &mut self.labels, &mut self.tags] {
+            *values = values
+                .iter()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .collect();
+        }
+        self.title = self.title.filter(|title| !title.is_empty());
+        if let (Some(after), Some(before)) = (self.released_after, self.released_before) {
+            if after > before {
+                return Err(anyhow!("发行日期范围无效: {after} 晚于 {before}"));
+            }
+        }
+        self.compile()?;
+        Ok(self)
+    }
+
+    pub fn compile(&self) -> Result<AlbumMatcher> {
+        let title = match &self.title {
+            Some(pattern) => Some(
+                Regex::new(pattern).map_err(|e| anyhow!("标题正则表达式无效 {pattern}: {e}"))?,
+            ),
+            None => None,
+        };
+        Ok(AlbumMatcher {
+            filter: self.clone(),
+            title,
+        })
+    }
+
+    /// Short form for job labels and logs, e.g. `label=Foo,after=2023-01-01`.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        for (name, values) in [
+            ("id", &self.ids),
+            ("label", &self.labels),
+            ("tag", &self.tags),
+        ] {
+            parts.extend(values.iter().map(|value| format!("{name}={value}")));
+        }
+        if let Some(after) = self.released_after {
+            parts.push(format!("after={after}"));
+        }
+        if let Some(before) = self.released_before {
+            parts.push(format!("before={before}"));
+        }
+        if let Some(title) = &self.title {
+            parts.push(format!("title={title}"));
+        }
+        parts.join(",")
+    }
+}
+
+/// An [`AlbumFilter`] with its title regex compiled.
+pub struct AlbumMatcher {
+    filter: AlbumFilter,
+    title: Option<Regex>,
+}
+
+impl AlbumMatcher {
+    pub fn filter(&self) -> &AlbumFilter {
+        &self.filter
+    }
+
+    pub fn matches(&self, album: &DiscListItem) -> bool {
+        let filter = &self.filter;
+        if !filter.ids.is_empty() && !filter.ids.contains(&album.id) {
+            return false;
+        }
+        let label = album.label.to_lowercase();
+        if !filter.labels.is_empty()
+            && !filter
+                .labels
+                .iter()
+                .any(|wanted| label.contains(&wanted.to_lowercase()))
+        {
+            return false;
+        }
+        if !filter.tags.is_empty()
+            && !album.tags.iter().any(|tag| {
+                filter
+                    .tags
+                    .iter()
+                    .any(|wanted| wanted.eq_ignore_ascii_case(tag))
+            })
+        {
+            return false;
+        }
+        if filter.has_date_bounds() {
+            let Some(day) = album.release_date.as_deref().and_then(release_day) else {
+                return false;
+            };
+            if filter.released_after.is_some_and(|after| day < after)
+                || filter.released_before.is_some_and(|before| day > before)
+            {
+                return false;
+            }
+        }
+        self.title
+            .as_ref()
+            .map_or(true, |title| title.is_match(&album.title))
+    }
+
+    pub fn select(&self, albums: Vec<DiscListItem>) -> Vec<DiscListItem> {
+        albums
+            .into_iter()
+            .filter(|album| self.matches(album))
+            .collect()
+    }
+}
+
+/// `YYYY-MM-DD` for the CLI date options.
+pub fn parse_day(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("日期格式应为 YYYY-MM-DD: {value}"))
+}
+
+/// The day of a Dizzylab release date in any of its formats.
+fn release_day(date: &str) -> Option<NaiveDate> {
+    let normalized = normalize_date(date);
+    let caps = Regex::new(r"(\d{4})[/-](\d{1,2})[/-](\d{1,2})")
+        .ok()?
+        .captures(&normalized)?;
+    NaiveDate::from_ymd_opt(
+        caps[1].parse().ok()?,
+        caps[2].parse().ok()?,
+        caps[3].parse().ok()?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn album(id: &str, label: &str, tags: &[&str], date: Option<&str>) -> DiscListItem {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": format!("{id} Original Soundtrack"),
+            "label": label,
+            "tags": tags,
+            "release_date": date,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_album_filter() {
+        let albums = vec![
+            album("a", "Foo Records", &["Touhou"], Some("2023年4月1日")),
+            album("b", "foo records", &["Original"], Some("2022-12-31")),
+            album("c", "Bar", &["touhou"], None),
+        ];
+        let select = |filter: AlbumFilter| {
+            let filter = filter.normalized().unwrap();
+            filter
+                .compile()
+                .unwrap()
+                .select(albums.clone())
+                .into_iter()
+                .map(|album| album.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(select(AlbumFilter::default()), ["a", "b", "c"]);
+        let foo = AlbumFilter {
+            labels: vec!["FOO".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(select(foo.clone()), ["a", "b"]);
+        assert_eq!(
+            select(AlbumFilter {
+                tags: vec!["TOUHOU".to_string()],
+                ..foo.clone()
+            }),
+            ["a"]
+        );
+        assert_eq!(
+            select(AlbumFilter {
+                released_after: parse_day("2023-01-01").ok(),
+                ..Default::default()
+            }),
+            ["a"]
+        );
+        assert_eq!(
+            select(AlbumFilter {
+                released_before: parse_day("2023-04-01").ok(),
+                ..Default::default()
+            }),
+            ["a", "b"]
+        );
+        assert_eq!(
+            select(AlbumFilter {
+                ids: vec![" c ".to_string(), "b".to_string()],
+                title: Some("^[bc] ".to_string()),
+                ..Default::default()
+            }),
+            ["b", "c"]
+        );
+
+        assert!(AlbumFilter {
+            title: Some("(".to_string()),
+            ..Default::default()
+        }
+        .normalized()
+        .is_err());
+        let ids = AlbumFilter {
+            ids: vec!["a".to_string()],
+            ..Default::default()
+        };
+        assert!(ids.only_ids());
+        assert!(!foo.only_ids());
+        assert_eq!(
+            AlbumFilter {
+                tags: vec!["x".to_string()],
+                ..foo
+            }
+            .describe(),
+            "label=FOO,tag=x"
+        );
+    }
+}
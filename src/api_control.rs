@@ -1,29 +1,49 @@
+use crate::album_filter::AlbumFilter;
+use crate::api_keys::{self, ApiGrant, ApiScope};
 use crate::client::DizzylabClient;
 use crate::config::{Config, UserConfig};
-use crate::downloader::Downloader;
+use crate::config_env;
+use crate::config_schema;
+use crate::downloader::{Downloader, SyncPlan};
+use crate::jobs::{Enqueued, JobKind, JobQueue, JobTrigger, QueuedJob, RunningJob};
 use crate::local_state;
+use crate::log_files::{self, LogContext, LogEntry};
+use crate::metrics;
+use crate::notify;
+use crate::progress::{self, SyncEvent};
+use crate::shutdown;
+use crate::tls;
 use crate::types::{DiscInfo, DiscListItem, UserInfo};
+use crate::web_auth::{self, WebSessions};
 use anyhow::{anyhow, Result};
 use axum::body::Body;
-use axum::extract::{Path, Query, State};
-use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::extract::{ConnectInfo, Path, Query, State};
+use axum::http::{header, HeaderMap, Method, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
 use axum::response::{IntoResponse, Response};
-use axum::routing::{get, post};
+use axum::routing::{delete, get, post};
 use axum::{Json, Router};
 use chrono::Utc;
 use cron::Schedule;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::convert::Infallible;
 use std::hash::{Hash, Hasher};
 use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
 use std::path::{Path as StdPath, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::sync::{Mutex as TokioMutex, RwLock};
+use tokio::sync::{Mutex as TokioMutex, Notify, RwLock};
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
 use tokio_util::io::ReaderStream;
 use tower_http::services::{ServeDir, ServeFile};
-use tracing::{error, info, Event, Level, Subscriber};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{error, info, info_span, warn, Event, Instrument, Level, Subscriber};
 use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
 
 #[derive(Debug, Clone)]
 pub struct ApiServerOptions {
@@ -31,15 +51,32 @@ pub struct ApiServerOptions {
     pub config: Config,
 }
 
+#[derive(Debug, Clone)]
+pub struct DaemonOptions {
+    pub config_path: String,
+    pub config: Config,
+    pub status_file: Option<PathBuf>,
+}
+
+const CONFIG_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+const STATUS_FILE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+/// Upper bound on a `/sync/plan` request; albums not planned by then are
+/// reported as errors in the plan.
+const PLAN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
 #[derive(Clone)]
 struct ApiState {
     sessions: Arc<RwLock<Vec<AccountSession>>>,
     config: Arc<RwLock<Config>>,
     config_path: String,
-    job: Arc<TokioMutex<JobState>>,
+    jobs: Arc<TokioMutex<JobQueue>>,
+    job_notify: Arc<Notify>,
     schedule: Arc<RwLock<ScheduleState>>,
     last_error: Arc<RwLock<Option<String>>>,
+    /// Why the last hand edit of the config file was rejected, if it was.
+    config_error: Arc<RwLock<Option<String>>>,
     logs: Arc<Mutex<Vec<LogEntry>>>,
+    web_sessions: Arc<Mutex<WebSessions>>,
 }
 
 #[derive(Clone)]
@@ -54,7 +91,27 @@ struct AccountSession {
 #[serde(tag = "state", rename_all = "snake_case")]
 enum JobState {
     Idle,
-    Running { kind: String, started_at: u64 },
+    Running {
+        id: u64,
+        kind: String,
+        started_at: u64,
+    },
+}
+
+impl JobState {
+    fn from_running(job: Option<&RunningJob>) -> Self {
+        match job {
+            Some(job) => JobState::Running {
+                id: job.id,
+                kind: match (&job.kind, job.trigger) {
+                    (JobKind::All, JobTrigger::Scheduled) => "scheduled".to_string(),
+                    (kind, _) => kind.label(),
+                },
+                started_at: job.started_at,
+            },
+            None => JobState::Idle,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -72,22 +129,34 @@ struct StatusResponse {
     ready: bool,
     configured: bool,
     requires_auth: bool,
+    scopes: Vec<ApiScope>,
     user: Option<UserInfo>,
     users: Vec<UserInfo>,
     job: JobState,
+    queue: Vec<QueuedJob>,
     schedule: ScheduleState,
     last_error: Option<String>,
+    config_error: Option<String>,
 }
 
+/// Contents of the daemon's `--status-file`, rewritten every few seconds.
 #[derive(Debug, Serialize)]
-struct MessageResponse {
-    message: String,
+struct DaemonStatus {
+    /// `running`, `stopping` after a signal, or `stopped` on a clean exit.
+    state: &'static str,
+    pid: u32,
+    started_at: u64,
+    updated_at: u64,
+    ready: bool,
+    job: JobState,
+    queue: Vec<QueuedJob>,
+    schedule: ScheduleState,
+    last_error: Option<String>,
+    config_error: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
-struct LogEntry {
-    timestamp: u64,
-    level: &'static str,
+#[derive(Debug, Serialize)]
+struct MessageResponse {
     message: String,
 }
 
@@ -97,11 +166,21 @@ struct LogQuery {
     level: Option<String>,
     start: Option<String>,
     end: Option<String>,
+    /// Number of newest matching entries to skip; pages walk backwards in time.
+    offset: Option<usize>,
+    limit: Option<usize>,
+    job: Option<u64>,
+    account: Option<String>,
+    album: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct LocalFileQuery {
     path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiKeyQuery {
     api_key: Option<String>,
 }
 
@@ -119,6 +198,8 @@ struct AlbumCacheFile {
 }
 
 static WEB_LOGS: OnceLock<Arc<Mutex<Vec<LogEntry>>>> = OnceLock::new();
+/// New log entries for `/api/logs/stream`, sent while the buffer lock is held.
+static LOG_STREAM: OnceLock<tokio::sync::broadcast::Sender<LogEntry>> = OnceLock::new();
 const MAX_LOG_ENTRIES: usize = 1000;
 
 pub fn web_log_layer() -> WebLogLayer {
@@ -129,9 +210,29 @@ pub struct WebLogLayer;
 
 impl<S> Layer<S> for WebLogLayer
 where
-    S: Subscriber,
+    S: Subscriber + for<'a> LookupSpan<'a>,
 {
-    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = LogContextVisitor::default();
+        attrs.record(&mut visitor);
+        if visitor.0 != LogContext::default() {
+            if let Some(span) = ctx.span(id) {
+                span.extensions_mut().insert(visitor.0);
+            }
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let mut visitor = LogContextVisitor(extensions.remove::<LogContext>().unwrap_or_default());
+        values.record(&mut visitor);
+        extensions.insert(visitor.0);
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
         let level = match *event.metadata().level() {
             Level::ERROR => "error",
             Level::WARN => "warn",
@@ -142,8 +243,47 @@ where
         let mut visitor = LogMessageVisitor::default();
         event.record(&mut visitor);
         let message = visitor.finish();
-        if !message.is_empty() {
-            push_log_sync(shared_logs(), level, message);
+        if message.is_empty() {
+            return;
+        }
+
+        // Innermost span wins, so walk from the leaf outwards.
+        let mut context = LogContext::default();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope {
+                if let Some(span_context) = span.extensions().get::<LogContext>() {
+                    context.merge(span_context);
+                }
+            }
+        }
+        push_log_sync(shared_logs(), level, message, context);
+    }
+}
+
+/// Collects the `job_id`, `account` and `album_id` fields of a span.
+#[derive(Default)]
+struct LogContextVisitor(LogContext);
+
+impl tracing::field::Visit for LogContextVisitor {
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        if field.name() == "job_id" {
+            self.0.job_id = Some(value);
+        }
+    }
+
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        match field.name() {
+            "account" => self.0.account = Some(value.to_string()),
+            "album_id" => self.0.album_id = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            "job_id" => self.0.job_id = format!("{value:?}").parse().ok(),
+            "account" | "album_id" => self.record_str(field, &format!("{value:?}")),
+            _ => {}
         }
     }
 }
@@ -179,6 +319,10 @@ fn shared_logs() -> &'static Arc<Mutex<Vec<LogEntry>>> {
     WEB_LOGS.get_or_init(|| Arc::new(Mutex::new(Vec::new())))
 }
 
+fn log_stream() -> &'static tokio::sync::broadcast::Sender<LogEntry> {
+    LOG_STREAM.get_or_init(|| tokio::sync::broadcast::channel(MAX_LOG_ENTRIES).0)
+}
+
 #[derive(Debug, Serialize)]
 struct ConfigResponse {
     config_path: String,
@@ -195,12 +339,18 @@ struct PublicConfig {
     behavior: PublicBehaviorConfig,
     schedule: PublicScheduleConfig,
     api: PublicApiConfig,
+    /// Dotted paths of fields set by `DIZZYSYNC__…` (or `DIZZYSYNC_OUTPUT_DIR`);
+    /// edits to them are overridden by the environment.
+    env_locked: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct PublicUserConfig {
     username: String,
     has_password: bool,
+    /// `file` or `env` when the password is a reference rather than inline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_source: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -238,6 +388,14 @@ struct PublicApiConfig {
     bind: String,
     has_api_key: bool,
     web_root: String,
+    #[serde(default)]
+    keys: Vec<PublicApiKey>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PublicApiKey {
+    name: String,
+    scopes: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -297,6 +455,17 @@ struct BootstrapConfigRequest {
     password: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct WebLoginRequest {
+    password: String,
+}
+
+#[derive(Debug, Serialize)]
+struct WebLoginResponse {
+    message: String,
+    scopes: Vec<ApiScope>,
+}
+
 #[derive(Debug, Deserialize)]
 struct TestLoginRequest {
     username: String,
@@ -311,57 +480,279 @@ struct TestLoginResponse {
     message: String,
 }
 
+/// Body of `/sync` and `/sync/plan`: a single `id`, filter criteria, or both
+/// (`id` then joins `ids`).
 #[derive(Debug, Deserialize)]
 struct SyncRequest {
     id: Option<String>,
+    #[serde(flatten)]
+    filter: AlbumFilter,
+}
+
+#[derive(Debug, Serialize)]
+struct JobsResponse {
+    running: Option<RunningJob>,
+    queued: Vec<QueuedJob>,
+}
+
+#[derive(Debug, Serialize)]
+struct EnqueueResponse {
+    message: String,
+    job: QueuedJob,
+    position: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoveJobRequest {
+    position: usize,
 }
 
 pub async fn run(options: ApiServerOptions) -> Result<()> {
     let mut config = options.config.clone();
     validate_schedule(&config)?;
+    notify::validate_webhooks(&config)?;
+    api_keys::validate_keys(&config.api)?;
+    let hashed = api_keys::hash_plain_keys(&mut config.api);
+    if hashed > 0 {
+        info!("已将 {} 个明文 API key 替换为哈希值并写回配置文件", hashed);
+    }
     ensure_api_key_for_remote_bind(&mut config)?;
     config.save_to_file(&options.config_path)?;
-
-    let state = ApiState {
-        sessions: Arc::new(RwLock::new(Vec::new())),
-        config: Arc::new(RwLock::new(config.clone())),
-        config_path: options.config_path,
-        job: Arc::new(TokioMutex::new(JobState::Idle)),
-        schedule: Arc::new(RwLock::new(schedule_state_from_config(&config))),
-        last_error: Arc::new(RwLock::new(None)),
-        logs: shared_logs().clone(),
+    // Load the certificate before anything starts so a bad pair fails fast.
+    let tls = match tls::tls_paths(&config.api)? {
+        Some((cert, key)) => Some((tls::load(&cert, &key).await?, cert, key)),
+        None => None,
     };
+
+    let state = ApiState::new(&config, options.config_path);
     push_log(&state, "info", "API/Web 控制服务初始化完成").await;
 
     start_login_and_album_cache_refresh(state.clone());
-    start_scheduler(state.clone());
+    start_job_worker(state.clone());
+    let scheduler = start_scheduler(state.clone());
+    start_config_watcher(state.clone());
+    notify::start(state.config.clone());
 
     let bind = config.api.bind.clone();
     let web_root = config.api.web_root.clone();
-    let api = Router::new()
-        .route("/health", get(health))
-        .route("/status", get(status))
+    let read_routes = Router::new()
         .route("/logs", get(get_logs))
-        .route("/config", get(get_config).put(update_config))
-        .route("/config/bootstrap", post(bootstrap_config))
-        .route("/config/test-login", post(test_login))
+        .route("/logs/stream", get(stream_logs))
         .route("/albums", get(list_albums))
         .route("/albums/{id}", get(get_album))
+        .route("/jobs", get(list_jobs))
+        .route("/config/schema", get(get_config_schema))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_read));
+    let media_routes = Router::new()
+        .route("/events", get(stream_events))
         .route("/local-file", get(get_local_file))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_read_media,
+        ));
+    let sync_routes = Router::new()
         .route("/sync", post(start_sync))
+        .route("/sync/plan", post(plan_sync))
         .route("/sync/{id}", post(start_album_sync))
-        .with_state(state);
+        .route("/jobs", post(enqueue_job))
+        .route("/jobs/{id}", delete(remove_job))
+        .route("/jobs/{id}/move", post(move_job))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_sync));
+    let admin_routes = Router::new()
+        .route("/config", get(get_config).put(update_config))
+        .route("/config/bootstrap", post(bootstrap_config))
+        .route("/config/test-login", post(test_login))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin));
+    let api = Router::new()
+        .route("/health", get(health))
+        .route("/status", get(status))
+        .route("/auth/login", post(web_login))
+        .route("/auth/logout", post(web_logout))
+        .merge(read_routes)
+        .merge(media_routes)
+        .merge(sync_routes)
+        .merge(admin_routes)
+        .with_state(state.clone());
 
     let app = Router::new()
+        .route("/metrics", get(get_metrics))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_read))
+        .with_state(state.clone())
         .nest("/api", api)
         .fallback_service(static_service(web_root));
 
     let listener = tokio::net::TcpListener::bind(&bind).await?;
-    info!("API/Web 控制服务已启动: http://{}", bind);
-    axum::serve(listener, app).await?;
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let server = async {
+        match tls {
+            Some((tls_config, cert, key)) => {
+                tls::watch(tls_config.clone(), cert, key);
+                info!("API/Web 控制服务已启动: https://{}", bind);
+                axum_server::from_tcp_rustls(listener.into_std()?, tls_config)?
+                    .serve(service)
+                    .await?;
+            }
+            None => {
+                info!("API/Web 控制服务已启动: http://{}", bind);
+                axum::serve(listener, service).await?;
+            }
+        }
+        Ok::<(), anyhow::Error>(())
+    };
+    // Dropping the server closes the listener and open connections (including
+    // `/api/events` streams), so no new jobs arrive while the running one stops.
+    tokio::select! {
+        result = server => result?,
+        _ = shutdown::signal() => {
+            scheduler.abort();
+            stop_jobs(&state).await;
+            notify::flush().await;
+            info!("API/Web 控制服务已退出");
+        }
+    }
+    Ok(())
+}
+
+impl ApiState {
+    fn new(config: &Config, config_path: String) -> Self {
+        Self {
+            sessions: Arc::new(RwLock::new(Vec::new())),
+            config: Arc::new(RwLock::new(config.clone())),
+            config_path,
+            jobs: Arc::new(TokioMutex::new(JobQueue::default())),
+            job_notify: Arc::new(Notify::new()),
+            schedule: Arc::new(RwLock::new(schedule_state_from_config(config))),
+            last_error: Arc::new(RwLock::new(None)),
+            config_error: Arc::new(RwLock::new(None)),
+            logs: shared_logs().clone(),
+            web_sessions: Arc::new(Mutex::new(WebSessions::default())),
+        }
+    }
+}
+
+/// Run the `serve` scheduler, job queue, config watcher and webhooks without
+/// the HTTP server until SIGTERM or Ctrl+C, then stop like `serve` does.
+pub async fn run_daemon(options: DaemonOptions) -> Result<()> {
+    let config = options.config;
+    validate_schedule(&config)?;
+    notify::validate_webhooks(&config)?;
+    if !config.schedule.enabled {
+        warn!("[schedule] 未启用，守护进程不会自动同步；在配置文件中启用后立即生效");
+    }
+
+    let state = ApiState::new(&config, options.config_path);
+    let started_at = now_unix();
+    start_login_and_album_cache_refresh(state.clone());
+    start_job_worker(state.clone());
+    let scheduler = start_scheduler(state.clone());
+    start_config_watcher(state.clone());
+    notify::start(state.config.clone());
+    info!("守护进程已启动 (PID {})", std::process::id());
+
+    let status_writer = options.status_file.clone().map(|path| {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(STATUS_FILE_INTERVAL);
+            loop {
+                interval.tick().await;
+                write_daemon_status(&state, &path, "running", started_at).await;
+            }
+        })
+    });
+
+    shutdown::signal().await;
+    scheduler.abort();
+    if let Some(writer) = status_writer {
+        writer.abort();
+    }
+    if let Some(path) = &options.status_file {
+        write_daemon_status(&state, path, "stopping", started_at).await;
+    }
+    stop_jobs(&state).await;
+    notify::flush().await;
+
+    if let Some(path) = &options.status_file {
+        write_daemon_status(&state, path, "stopped", started_at).await;
+    }
+    info!("守护进程已退出");
     Ok(())
 }
 
+/// After a shutdown signal: drop queued jobs and give the running one
+/// `shutdown::GRACE_PERIOD` to finish its current files. A job still running
+/// after that is recorded as interrupted; its partial files are deleted when
+/// the runtime drops it.
+async fn stop_jobs(state: &ApiState) {
+    info!("收到退出信号，不再开始新的同步任务");
+    shutdown::request();
+    let running = {
+        let mut jobs = state.jobs.lock().await;
+        for job in jobs.queued() {
+            jobs.remove(job.id);
+        }
+        jobs.running().cloned()
+    };
+    let Some(job) = running else {
+        return;
+    };
+    warn!(
+        "收到退出信号，已清空队列，等待同步任务 #{} 完成进行中的文件（最多 {} 秒，再次发送信号立即退出）",
+        job.id,
+        shutdown::GRACE_PERIOD.as_secs()
+    );
+    let finished = shutdown::drain(async {
+        while state.jobs.lock().await.running().is_some() {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        }
+    })
+    .await;
+    if finished.is_none() {
+        push_job_log(
+            state,
+            job.id,
+            "warn",
+            format!("同步任务 #{} 已中断：退出时仍有文件未下载完成", job.id),
+        );
+        record_job_done(state, &job, Some("服务退出，同步任务已中断".to_string())).await;
+    }
+}
+
+/// Replace the status file in one rename so readers never see half a file.
+async fn write_daemon_status(
+    state: &ApiState,
+    path: &StdPath,
+    phase: &'static str,
+    started_at: u64,
+) {
+    let (job, queue) = {
+        let jobs = state.jobs.lock().await;
+        (JobState::from_running(jobs.running()), jobs.queued())
+    };
+    let status = DaemonStatus {
+        state: phase,
+        pid: std::process::id(),
+        started_at,
+        updated_at: now_unix(),
+        ready: !state.sessions.read().await.is_empty(),
+        job,
+        queue,
+        schedule: state.schedule.read().await.clone(),
+        last_error: state.last_error.read().await.clone(),
+        config_error: state.config_error.read().await.clone(),
+    };
+    let result = serde_json::to_vec_pretty(&status)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| {
+            let temp = PathBuf::from(format!("{}.tmp", path.display()));
+            std::fs::write(&temp, content)?;
+            std::fs::rename(&temp, path)?;
+            Ok(())
+        });
+    if let Err(e) = result {
+        warn!("写入状态文件 {} 失败: {}", path.display(), e);
+    }
+}
+
 fn static_service(web_root: PathBuf) -> ServeDir<ServeFile> {
     let index = web_root.join("index.html");
     ServeDir::new(web_root).fallback(ServeFile::new(index))
@@ -375,7 +766,8 @@ async fn health() -> Json<MessageResponse> {
 
 async fn status(State(state): State<ApiState>, headers: HeaderMap) -> Json<StatusResponse> {
     let config = state.config.read().await.clone();
-    let authenticated = authorize_with_key(&config.api.api_key, header_api_key(&headers)).is_ok();
+    let grant = request_grant(&state, &config, &headers, None);
+    let authenticated = grant.is_some();
     let sessions = state.sessions.read().await.clone();
     let users = sessions
         .iter()
@@ -390,64 +782,160 @@ async fn status(State(state): State<ApiState>, headers: HeaderMap) -> Json<Statu
         }
     };
 
+    let (job, queue) = if authenticated {
+        let jobs = state.jobs.lock().await;
+        (JobState::from_running(jobs.running()), jobs.queued())
+    } else {
+        (JobState::Idle, Vec::new())
+    };
+
     Json(StatusResponse {
         status: "ok",
         ready: !sessions.is_empty(),
         configured: has_credentials(&config),
-        requires_auth: !config.api.api_key.is_empty(),
+        requires_auth: api_keys::requires_auth(&config.api),
+        scopes: grant.map(|grant| grant.scopes).unwrap_or_default(),
         user: authenticated.then(|| users.first().cloned()).flatten(),
         users: if authenticated { users } else { Vec::new() },
-        job: if authenticated {
-            state.job.lock().await.clone()
-        } else {
-            JobState::Idle
-        },
+        job,
+        queue,
         schedule,
         last_error: if authenticated {
             state.last_error.read().await.clone()
         } else {
             None
         },
+        config_error: if authenticated {
+            state.config_error.read().await.clone()
+        } else {
+            None
+        },
     })
 }
 
+async fn get_metrics(State(state): State<ApiState>) -> Result<Response, ApiError> {
+    let (job_running, queue_length) = {
+        let jobs = state.jobs.lock().await;
+        (jobs.running().is_some(), jobs.queued().len())
+    };
+    let next_scheduled_run = state.schedule.read().await.next_run;
+    let config = state.config.read().await.clone();
+    let library_albums =
+        tokio::task::spawn_blocking(move || local_state::library_album_count(&config))
+            .await
+            .unwrap_or_default();
+
+    let body = metrics::render(&metrics::Gauges {
+        job_running,
+        queue_length,
+        next_scheduled_run,
+        library_albums,
+    });
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        body,
+    )
+        .into_response())
+}
+
 async fn get_logs(
     State(state): State<ApiState>,
-    headers: HeaderMap,
     Query(query): Query<LogQuery>,
-) -> Result<Json<Vec<LogEntry>>, ApiError> {
-    authorize(&state, &headers).await?;
-    let logs = state
-        .logs
-        .lock()
-        .unwrap_or_else(|poisoned| poisoned.into_inner());
-    Ok(Json(filter_logs(&logs, &query)))
+) -> Result<Response, ApiError> {
+    let limit = query
+        .limit
+        .unwrap_or(MAX_LOG_ENTRIES)
+        .clamp(1, MAX_LOG_ENTRIES);
+    let offset = query.offset.unwrap_or(0);
+    let keep = log_filter(&query);
+    // `total` is unknown when the file scan stopped once the page was full.
+    let (filtered, total) = if log_files::is_enabled() {
+        let (since, until) = log_query_bounds(&query);
+        let needed = offset.saturating_add(limit);
+        let scan = tokio::task::spawn_blocking(move || {
+            log_files::read_entries(since, until, Some(needed), keep)
+        })
+        .await
+        .map_err(|e| ApiError::internal(format!("读取日志文件失败: {e}")))?
+        .map_err(|e| ApiError::internal(format!("读取日志文件失败: {e}")))?;
+        let total = scan.complete.then_some(scan.entries.len());
+        (scan.entries, total)
+    } else {
+        let logs = state
+            .logs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let filtered: Vec<LogEntry> = logs.iter().filter(|entry| keep(entry)).cloned().collect();
+        let total = filtered.len();
+        (filtered, Some(total))
+    };
+
+    let end = filtered.len().saturating_sub(offset);
+    let start = end.saturating_sub(limit);
+    let page = filtered[start..end].to_vec();
+
+    let mut response = Json(page).into_response();
+    let (name, value) = match total {
+        Some(total) => ("x-total-count", total.to_string()),
+        None => ("x-has-more", "true".to_string()),
+    };
+    if let Ok(value) = value.parse() {
+        response
+            .headers_mut()
+            .insert(header::HeaderName::from_static(name), value);
+    }
+    Ok(response)
+}
+
+/// Narrow the persisted files to read from the date/start/end filters.
+fn log_query_bounds(query: &LogQuery) -> (Option<u64>, Option<u64>) {
+    let mut since = query.start.as_deref().and_then(parse_log_time);
+    let mut until = query.end.as_deref().and_then(parse_log_time);
+    let day = query
+        .date
+        .as_deref()
+        .and_then(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .and_then(|datetime| datetime.and_local_timezone(chrono::Local).earliest())
+        .map(|datetime| datetime.timestamp().max(0) as u64);
+    if let Some(day_start) = day {
+        let day_end = day_start + 24 * 60 * 60;
+        since = Some(since.map_or(day_start, |since| since.max(day_start)));
+        until = Some(until.map_or(day_end, |until| until.min(day_end)));
+    }
+    (since, until)
 }
 
-fn filter_logs(logs: &[LogEntry], query: &LogQuery) -> Vec<LogEntry> {
-    let level = query.level.as_deref().filter(|level| !level.is_empty());
+fn log_filter(query: &LogQuery) -> impl Fn(&LogEntry) -> bool + Send + 'static {
+    let non_empty = |value: &Option<String>| value.clone().filter(|value| !value.is_empty());
+    let level = non_empty(&query.level);
+    let date = query.date.clone();
     let start = query.start.as_deref().and_then(parse_log_time);
     let end = query.end.as_deref().and_then(parse_log_time);
+    let job = query.job;
+    let account = non_empty(&query.account);
+    let album = non_empty(&query.album);
 
-    logs.iter()
-        .filter(|entry| match level {
-            Some(level) => entry.level.eq_ignore_ascii_case(level),
-            None => true,
-        })
-        .filter(|entry| match query.date.as_deref() {
-            Some(date) => log_date(entry.timestamp) == date,
-            None => true,
-        })
-        .filter(|entry| match start {
-            Some(start) => entry.timestamp >= start,
-            None => true,
-        })
-        .filter(|entry| match end {
-            Some(end) => entry.timestamp <= end,
-            None => true,
-        })
-        .cloned()
-        .collect()
+    move |entry| {
+        level
+            .as_deref()
+            .map_or(true, |level| entry.level.eq_ignore_ascii_case(level))
+            && date
+                .as_deref()
+                .map_or(true, |date| log_date(entry.timestamp) == date)
+            && start.map_or(true, |start| entry.timestamp >= start)
+            && end.map_or(true, |end| entry.timestamp <= end)
+            && job.map_or(true, |job| entry.context.job_id == Some(job))
+            && account.as_deref().map_or(true, |account| {
+                entry.context.account.as_deref() == Some(account)
+            })
+            && album.as_deref().map_or(true, |album| {
+                entry.context.album_id.as_deref() == Some(album)
+            })
+    }
 }
 
 fn parse_log_time(value: &str) -> Option<u64> {
@@ -476,11 +964,56 @@ fn log_date(timestamp: u64) -> String {
         .unwrap_or_default()
 }
 
-async fn get_config(
+/// Follow the log over SSE: the newest `limit` matching entries from memory,
+/// then every new one as a `log` event. Subscribing under the buffer lock
+/// makes the two parts meet without gaps or repeats. A subscriber too slow to
+/// keep up gets a `lagged` event with the number of entries it missed.
+async fn stream_logs(
     State(state): State<ApiState>,
-    headers: HeaderMap,
-) -> Result<Json<ConfigResponse>, ApiError> {
-    authorize(&state, &headers).await?;
+    Query(query): Query<LogQuery>,
+) -> Sse<impl Stream<Item = Result<SseEvent, Infallible>>> {
+    let limit = query.limit.unwrap_or(0).min(MAX_LOG_ENTRIES);
+    let keep = log_filter(&query);
+    let (backlog, receiver) = {
+        let logs = state
+            .logs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let matching = logs.iter().filter(|entry| keep(entry)).collect::<Vec<_>>();
+        let backlog = matching[matching.len().saturating_sub(limit)..]
+            .iter()
+            .map(|entry| (*entry).clone())
+            .collect::<Vec<_>>();
+        (backlog, log_stream().subscribe())
+    };
+
+    let log_event = |entry: &LogEntry| {
+        let data = serde_json::to_string(entry).ok()?;
+        Some(Ok(SseEvent::default().event("log").data(data)))
+    };
+    let backlog = tokio_stream::iter(backlog.iter().filter_map(log_event).collect::<Vec<_>>());
+    let live = BroadcastStream::new(receiver).filter_map(move |entry| match entry {
+        Ok(entry) => keep(&entry).then(|| log_event(&entry)).flatten(),
+        Err(tokio_stream::wrappers::errors::BroadcastStreamRecvError::Lagged(missed)) => {
+            Some(Ok(SseEvent::default()
+                .event("lagged")
+                .data(missed.to_string())))
+        }
+    });
+    Sse::new(backlog.chain(live)).keep_alive(KeepAlive::default())
+}
+
+async fn stream_events() -> Sse<impl Stream<Item = Result<SseEvent, Infallible>>> {
+    let stream = BroadcastStream::new(progress::subscribe()).filter_map(|event| {
+        // Slow subscribers skip the events they lagged behind on.
+        let event = event.ok()?;
+        let data = serde_json::to_string(&event).ok()?;
+        Some(Ok(SseEvent::default().event(event.event.name()).data(data)))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn get_config(State(state): State<ApiState>) -> Result<Json<ConfigResponse>, ApiError> {
     let config = state.config.read().await.clone();
     Ok(Json(ConfigResponse {
         config_path: state.config_path.clone(),
@@ -489,19 +1022,23 @@ async fn get_config(
     }))
 }
 
+async fn get_config_schema() -> Json<serde_json::Value> {
+    Json(config_schema::schema().clone())
+}
+
 async fn update_config(
     State(state): State<ApiState>,
-    headers: HeaderMap,
     Json(req): Json<UpdateConfigRequest>,
 ) -> Result<Json<ConfigResponse>, ApiError> {
-    authorize(&state, &headers).await?;
-
     let mut next_config = state.config.read().await.clone();
-    apply_config_update(&mut next_config, req);
-    next_config.apply_env_overrides(true);
+    apply_config_update(&mut next_config, req).map_err(ApiError::bad_request)?;
+    next_config
+        .apply_env_overrides(true)
+        .map_err(ApiError::bad_request)?;
     validate_credentials(&next_config).map_err(ApiError::bad_request)?;
     validate_formats(&next_config).map_err(ApiError::bad_request)?;
     validate_schedule(&next_config).map_err(ApiError::bad_request)?;
+    notify::validate_webhooks(&next_config).map_err(ApiError::bad_request)?;
 
     // Validate all credentials before committing the config to memory or disk.
     let next_sessions = login_accounts(&next_config)
@@ -511,6 +1048,12 @@ async fn update_config(
     next_config.save_to_file(&state.config_path)?;
     *state.config.write().await = next_config.clone();
     *state.sessions.write().await = next_sessions.clone();
+    // Sessions keep the scopes of the key they logged in with.
+    state
+        .web_sessions
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .refresh(&next_config.api);
     *state.schedule.write().await = schedule_state_from_config(&next_config);
     push_log(&state, "info", "配置已验证并保存").await;
     start_album_cache_refresh_for_sessions(state.clone(), next_sessions);
@@ -525,11 +1068,8 @@ async fn update_config(
 
 async fn test_login(
     State(state): State<ApiState>,
-    headers: HeaderMap,
     Json(req): Json<TestLoginRequest>,
 ) -> Result<Json<TestLoginResponse>, ApiError> {
-    authorize(&state, &headers).await?;
-
     let username = req.username.trim().to_string();
     if username.is_empty() {
         return Err(ApiError::bad_request("请设置 Dizzylab username"));
@@ -558,6 +1098,7 @@ async fn test_login(
     let account = UserConfig {
         username: username.clone(),
         password,
+        ..UserConfig::default()
     };
     let account_label = account_label(&account);
     let client = DizzylabClient::new(config.behavior.debug)?;
@@ -603,11 +1144,8 @@ async fn test_login(
 
 async fn bootstrap_config(
     State(state): State<ApiState>,
-    headers: HeaderMap,
     Json(req): Json<BootstrapConfigRequest>,
 ) -> Result<Json<ConfigResponse>, ApiError> {
-    authorize(&state, &headers).await?;
-
     let exists = std::path::Path::new(&state.config_path).exists();
     let force = req.force == Some(true);
     if exists && !force {
@@ -615,7 +1153,7 @@ async fn bootstrap_config(
             "配置文件已存在；如需覆盖请设置 force=true",
         ));
     }
-    if force && state.config.read().await.api.api_key.is_empty() {
+    if force && !api_keys::requires_auth(&state.config.read().await.api) {
         return Err(ApiError::bad_request("未配置 API key 时不允许强制覆盖配置"));
     }
 
@@ -624,7 +1162,9 @@ async fn bootstrap_config(
         api: current.api,
         ..Config::default()
     };
-    config.apply_env_overrides(false);
+    config
+        .apply_env_overrides(false)
+        .map_err(ApiError::bad_request)?;
     let mut account = config.accounts().first().cloned().unwrap_or_default();
     if let Some(username) = req.username {
         account.username = username;
@@ -650,10 +1190,8 @@ async fn bootstrap_config(
 
 async fn list_albums(
     State(state): State<ApiState>,
-    headers: HeaderMap,
     Query(query): Query<AlbumsQuery>,
 ) -> Result<Json<Vec<DiscListItem>>, ApiError> {
-    authorize(&state, &headers).await?;
     let config = state.config.read().await.clone();
     let sessions = ensure_logged_in(&state).await?;
     let cache_key = album_cache_key(&sessions);
@@ -671,7 +1209,7 @@ async fn list_albums(
         }
     }
 
-    let albums = refresh_album_cache(&state, &sessions).await?;
+    let albums = refresh_album_cache(&state.config_path, &sessions).await?;
     let mut annotated = albums;
     local_state::annotate_album_list(&config, &mut annotated);
     push_log(
@@ -685,10 +1223,8 @@ async fn list_albums(
 
 async fn get_album(
     State(state): State<ApiState>,
-    headers: HeaderMap,
     Path(id): Path<String>,
 ) -> Result<Json<DiscInfo>, ApiError> {
-    authorize(&state, &headers).await?;
     if id.trim().is_empty() {
         return Err(ApiError::bad_request("专辑 ID 不能为空"));
     }
@@ -697,6 +1233,7 @@ async fn get_album(
     for session in &sessions {
         match session.client.get_disc_info(&id, &session.token).await {
             Ok(mut album) => {
+                write_disc_cache(&state.config_path, &album).await;
                 let config = state.config.read().await.clone();
                 local_state::annotate_disc_info(&config, &mut album);
                 return Ok(Json(album));
@@ -715,17 +1252,9 @@ async fn get_album(
 
 async fn get_local_file(
     State(state): State<ApiState>,
-    headers: HeaderMap,
     Query(query): Query<LocalFileQuery>,
 ) -> Result<Response, ApiError> {
     let config = state.config.read().await.clone();
-    authorize_with_key(
-        &config.api.api_key,
-        query
-            .api_key
-            .as_deref()
-            .or_else(|| header_api_key(&headers)),
-    )?;
 
     let requested = PathBuf::from(&query.path);
     let canonical_file = requested
@@ -853,15 +1382,17 @@ fn album_cache_key(sessions: &[AccountSession]) -> String {
     format!("{:016x}", hasher.finish())
 }
 
-fn album_cache_path(config_path: &str, cache_key: &str) -> PathBuf {
+fn album_cache_dir(config_path: &str) -> PathBuf {
     let config_path = StdPath::new(config_path);
     let base_dir = config_path
         .parent()
         .filter(|parent| !parent.as_os_str().is_empty())
         .unwrap_or_else(|| StdPath::new("."));
-    base_dir
-        .join(".dizzysync-cache")
-        .join(format!("albums-{cache_key}.json"))
+    base_dir.join(".dizzysync-cache")
+}
+
+fn album_cache_path(config_path: &str, cache_key: &str) -> PathBuf {
+    album_cache_dir(config_path).join(format!("albums-{cache_key}.json"))
 }
 
 async fn read_album_cache(config_path: &str, cache_key: &str) -> Option<Vec<DiscListItem>> {
@@ -894,13 +1425,122 @@ async fn write_album_cache(config_path: &str, cache_key: &str, albums: &[DiscLis
     }
 }
 
+/// Track lists are cached per album next to the list cache so `status` can
+/// check each track offline.
+fn disc_cache_path(config_path: &str, id: &str) -> PathBuf {
+    let name = id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    album_cache_dir(config_path)
+        .join("discs")
+        .join(format!("{name}.json"))
+}
+
+/// The cached details of album `id`, if any command fetched them before.
+pub fn cached_disc_info(config_path: &str, id: &str) -> Option<DiscInfo> {
+    let content = std::fs::read_to_string(disc_cache_path(config_path, id)).ok()?;
+    serde_json::from_str::<DiscInfo>(&content)
+        .ok()
+        .filter(|disc| disc.id == id)
+}
+
+async fn write_disc_cache(config_path: &str, disc: &DiscInfo) {
+    let cache_path = disc_cache_path(config_path, &disc.id);
+    if let Some(parent) = cache_path.parent() {
+        if let Err(e) = tokio::fs::create_dir_all(parent).await {
+            tracing::debug!("无法创建专辑缓存目录: {}", e);
+            return;
+        }
+    }
+    match serde_json::to_vec_pretty(disc) {
+        Ok(bytes) => {
+            if let Err(e) = tokio::fs::write(cache_path, bytes).await {
+                tracing::debug!("无法写入专辑详情缓存: {}", e);
+            }
+        }
+        Err(e) => tracing::debug!("无法序列化专辑详情缓存: {}", e),
+    }
+}
+
+/// Fetch the details of every album in `ids`, `concurrency` at a time, from the
+/// first account that can see it. Results keep the order of `ids` and carry
+/// the index of the session that answered.
+async fn fetch_disc_infos(
+    sessions: &[AccountSession],
+    ids: Vec<String>,
+    concurrency: usize,
+) -> Vec<(String, Result<(usize, DiscInfo)>)> {
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(concurrency.max(1)));
+    let sessions = Arc::new(sessions.to_vec());
+    let mut join_set = tokio::task::JoinSet::new();
+    for (index, id) in ids.into_iter().enumerate() {
+        let semaphore = semaphore.clone();
+        let sessions = sessions.clone();
+        join_set.spawn(async move {
+            let _permit = semaphore.acquire().await;
+            let mut last_error = None;
+            for (session_index, session) in sessions.iter().enumerate() {
+                match session.client.get_disc_info(&id, &session.token).await {
+                    Ok(disc) => return (index, id, Ok((session_index, disc))),
+                    Err(e) => last_error = Some(e),
+                }
+            }
+            let error = last_error
+                .map(|e| anyhow!("获取专辑 {id} 详情失败: {e}"))
+                .unwrap_or_else(|| anyhow!("未配置 Dizzylab 账号"));
+            (index, id, Err(error))
+        });
+    }
+
+    let mut results = Vec::new();
+    while let Some(joined) = join_set.join_next().await {
+        match joined {
+            Ok(result) => results.push(result),
+            Err(e) => tracing::warn!("获取专辑详情的任务异常退出: {}", e),
+        }
+    }
+    results.sort_by_key(|(index, _, _)| *index);
+    results
+        .into_iter()
+        .map(|(_, id, result)| (id, result))
+        .collect()
+}
+
+/// Fill in the release dates the purchase list leaves out, so date filters
+/// can match those albums: from the cached details first, then from the
+/// album page.
+pub async fn fill_release_dates(
+    config_path: &str,
+    client: &DizzylabClient,
+    token: &str,
+    albums: &mut [DiscListItem],
+) {
+    for album in albums
+        .iter_mut()
+        .filter(|album| album.release_date.is_none())
+    {
+        if let Some(detail) = cached_disc_info(config_path, &album.id) {
+            enrich_list_item_from_detail(album, &detail);
+            continue;
+        }
+        match client.get_disc_info(&album.id, token).await {
+            Ok(detail) => {
+                enrich_list_item_from_detail(album, &detail);
+                write_disc_cache(config_path, &detail).await;
+            }
+            Err(e) => tracing::debug!("无法获取专辑 {} 的发行日期: {}", album.id, e),
+        }
+    }
+}
+
 fn start_login_and_album_cache_refresh(state: ApiState) {
     tokio::spawn(async move {
         push_log(&state, "info", "后台登录与专辑缓存刷新已启动").await;
         match ensure_logged_in(&state).await {
             Ok(sessions) => refresh_album_cache_and_log(&state, &sessions).await,
             Err(e) => {
-                error!("API 服务启动时登录失败: {}", e);
+                error!("启动时登录失败: {}", e);
                 *state.last_error.write().await = Some(e.to_string());
                 push_log(&state, "warn", format!("后台登录失败：{e}")).await;
             }
@@ -908,18 +1548,142 @@ fn start_login_and_album_cache_refresh(state: ApiState) {
     });
 }
 
-fn start_album_cache_refresh_for_sessions(state: ApiState, sessions: Vec<AccountSession>) {
+/// Poll the config file and apply hand edits. A change that fails to parse,
+/// validate or log in is reported through the log and `/api/status` while the
+/// running config stays in place.
+fn start_config_watcher(state: ApiState) {
     tokio::spawn(async move {
-        push_log(&state, "info", "后台专辑缓存刷新已启动").await;
-        refresh_album_cache_and_log(&state, &sessions).await;
+        let mut last_stamp = config_file_stamp(&state.config_path);
+        let mut interval = tokio::time::interval(CONFIG_WATCH_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            let stamp = config_file_stamp(&state.config_path);
+            if stamp == last_stamp || stamp.is_none() {
+                continue;
+            }
+            last_stamp = stamp;
+
+            match reload_config_from_disk(&state).await {
+                Ok(_) => *state.config_error.write().await = None,
+                Err(e) => {
+                    let message = format!("配置文件修改未生效，继续使用当前配置：{e}");
+                    error!("{}", message);
+                    push_log(&state, "error", message).await;
+                    *state.config_error.write().await = Some(e.to_string());
+                }
+            }
+        }
     });
 }
 
-async fn refresh_album_cache_and_log(state: &ApiState, sessions: &[AccountSession]) {
-    match refresh_album_cache(state, sessions).await {
-        Ok(albums) => {
-            *state.last_error.write().await = None;
-            push_log(
+fn config_file_stamp(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Load, validate and swap in the config file. Returns `false` when the file
+/// matches the running config, e.g. after the server saved it itself.
+async fn reload_config_from_disk(state: &ApiState) -> Result<bool> {
+    let mut next_config = Config::load_from_file(&state.config_path)?;
+    next_config.apply_env_overrides(true)?;
+    validate_credentials(&next_config)?;
+    validate_formats(&next_config)?;
+    validate_schedule(&next_config)?;
+    notify::validate_webhooks(&next_config)?;
+    api_keys::validate_keys(&next_config.api)?;
+    if api_keys::hash_plain_keys(&mut next_config.api) > 0 {
+        next_config.save_to_file(&state.config_path)?;
+    }
+
+    let current = state.config.read().await.clone();
+    let credentials = |config: &Config| {
+        config
+            .accounts()
+            .into_iter()
+            .map(|account| (account.username, account.password))
+            .collect::<Vec<_>>()
+    };
+    let credentials_changed = credentials(&current) != credentials(&next_config);
+    if !credentials_changed && toml::to_string(&current)? == toml::to_string(&next_config)? {
+        return Ok(false);
+    }
+
+    let next_sessions = if credentials_changed {
+        let current_sessions = state.sessions.read().await.clone();
+        Some(relogin_changed_accounts(&next_config, &current_sessions).await?)
+    } else {
+        None
+    };
+
+    if current.api.bind != next_config.api.bind
+        || current.api.tls_cert != next_config.api.tls_cert
+        || current.api.tls_key != next_config.api.tls_key
+        || current.api.web_root != next_config.api.web_root
+    {
+        warn!("[api] 的 bind、tls_cert、tls_key、web_root 修改需重启服务后生效");
+    }
+
+    {
+        let mut config = state.config.write().await;
+        let mut sessions = state.sessions.write().await;
+        *config = next_config.clone();
+        if let Some(next_sessions) = &next_sessions {
+            *sessions = next_sessions.clone();
+        }
+    }
+    if current.schedule.enabled != next_config.schedule.enabled
+        || current.schedule.cron != next_config.schedule.cron
+    {
+        *state.schedule.write().await = schedule_state_from_config(&next_config);
+    }
+    state
+        .web_sessions
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .refresh(&next_config.api);
+    push_log(state, "info", "已从磁盘重新加载配置文件").await;
+    if let Some(next_sessions) = next_sessions {
+        start_album_cache_refresh_for_sessions(state.clone(), next_sessions);
+    }
+    Ok(true)
+}
+
+/// Sessions for `config`'s accounts, reusing the existing login of every
+/// account whose username and password did not change.
+async fn relogin_changed_accounts(
+    config: &Config,
+    current: &[AccountSession],
+) -> Result<Vec<AccountSession>> {
+    let mut sessions = Vec::new();
+    for account in config.accounts() {
+        let existing = current.iter().find(|session| {
+            session.account.username == account.username
+                && session.account.password == account.password
+        });
+        match existing {
+            Some(session) => sessions.push(session.clone()),
+            None => {
+                info!("账号 {} 的凭据已变更，重新登录", account_label(&account));
+                sessions.push(login_account(config, account).await?);
+            }
+        }
+    }
+    Ok(sessions)
+}
+
+fn start_album_cache_refresh_for_sessions(state: ApiState, sessions: Vec<AccountSession>) {
+    tokio::spawn(async move {
+        push_log(&state, "info", "后台专辑缓存刷新已启动").await;
+        refresh_album_cache_and_log(&state, &sessions).await;
+    });
+}
+
+async fn refresh_album_cache_and_log(state: &ApiState, sessions: &[AccountSession]) {
+    match refresh_album_cache(&state.config_path, sessions).await {
+        Ok(albums) => {
+            *state.last_error.write().await = None;
+            push_log(
                 state,
                 "info",
                 format!("后台已刷新 {} 张已购专辑缓存", albums.len()),
@@ -935,7 +1699,7 @@ async fn refresh_album_cache_and_log(state: &ApiState, sessions: &[AccountSessio
 }
 
 async fn refresh_album_cache(
-    state: &ApiState,
+    config_path: &str,
     sessions: &[AccountSession],
 ) -> Result<Vec<DiscListItem>> {
     let cache_key = album_cache_key(sessions);
@@ -948,7 +1712,10 @@ async fn refresh_album_cache(
                     .get_disc_info(&album.id, &session.token)
                     .await
                 {
-                    Ok(detail) => enrich_list_item_from_detail(&mut album, &detail),
+                    Ok(detail) => {
+                        enrich_list_item_from_detail(&mut album, &detail);
+                        write_disc_cache(config_path, &detail).await;
+                    }
                     Err(e) => {
                         tracing::debug!("无法用专辑详情补全列表缓存 {} 的元数据: {}", album.id, e)
                     }
@@ -958,10 +1725,54 @@ async fn refresh_album_cache(
         }
     }
     let albums = albums_by_id.into_values().collect::<Vec<_>>();
-    write_album_cache(&state.config_path, &cache_key, &albums).await;
+    write_album_cache(config_path, &cache_key, &albums).await;
+    Ok(albums)
+}
+
+/// Log in every account, refresh the album list cache the API server uses and
+/// then the cached details of every album. Albums whose details fail keep their old cache.
+pub async fn fetch_album_details(config_path: &str, config: &Config) -> Result<Vec<DiscListItem>> {
+    let sessions = login_accounts(config).await?;
+    let albums = refresh_album_cache(config_path, &sessions).await?;
+    let ids = albums.iter().map(|album| album.id.clone()).collect();
+    let mut failed = 0usize;
+    for (id, result) in
+        fetch_disc_infos(&sessions, ids, config.behavior.max_concurrent_albums).await
+    {
+        match result {
+            Ok((_, disc)) => write_disc_cache(config_path, &disc).await,
+            Err(e) => {
+                failed += 1;
+                tracing::warn!("无法更新专辑 {} 的曲目列表: {}", id, e);
+            }
+        }
+    }
+    if failed > 0 {
+        tracing::warn!("{} 张专辑的曲目列表获取失败，将使用旧的缓存", failed);
+    }
     Ok(albums)
 }
 
+/// The most recently written album cache next to `config_path`, with its
+/// write time. The cache key needs logged-in sessions, so offline readers
+/// take the newest file.
+pub fn latest_album_cache(config_path: &str) -> Option<(u64, Vec<DiscListItem>)> {
+    std::fs::read_dir(album_cache_dir(config_path))
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("albums-") && name.ends_with(".json")
+        })
+        .filter_map(|entry| {
+            let content = std::fs::read_to_string(entry.path()).ok()?;
+            serde_json::from_str::<AlbumCacheFile>(&content).ok()
+        })
+        .max_by_key(|cache| cache.updated_at)
+        .map(|cache| (cache.updated_at, cache.albums))
+}
+
 fn enrich_list_item_from_detail(album: &mut DiscListItem, detail: &crate::types::DiscInfo) {
     if album.release_date.is_none() {
         album.release_date = detail.release_date.clone();
@@ -973,135 +1784,506 @@ fn enrich_list_item_from_detail(album: &mut DiscListItem, detail: &crate::types:
 
 async fn start_sync(
     State(state): State<ApiState>,
-    headers: HeaderMap,
     body: Option<Json<SyncRequest>>,
-) -> Result<(StatusCode, Json<MessageResponse>), ApiError> {
-    authorize(&state, &headers).await?;
-    let album_id = body.and_then(|Json(req)| req.id);
-    start_job(state, album_id).await
+) -> Result<(StatusCode, Json<EnqueueResponse>), ApiError> {
+    let kind = filter_job_kind(request_filter(body)?);
+    Ok(enqueue(&state, kind, JobTrigger::Manual).await)
 }
 
 async fn start_album_sync(
     State(state): State<ApiState>,
-    headers: HeaderMap,
     Path(id): Path<String>,
-) -> Result<(StatusCode, Json<MessageResponse>), ApiError> {
-    authorize(&state, &headers).await?;
-    start_job(state, Some(id)).await
+) -> Result<(StatusCode, Json<EnqueueResponse>), ApiError> {
+    let kind = album_job_kind(id)?;
+    Ok(enqueue(&state, kind, JobTrigger::Manual).await)
 }
 
-async fn start_job(
-    state: ApiState,
-    album_id: Option<String>,
-) -> Result<(StatusCode, Json<MessageResponse>), ApiError> {
-    {
-        let mut job = state.job.lock().await;
-        if matches!(*job, JobState::Running { .. }) {
-            return Err(ApiError::conflict("已有同步任务正在运行"));
-        }
-        *job = JobState::Running {
-            kind: album_id
-                .as_ref()
-                .map(|id| format!("album:{id}"))
-                .unwrap_or_else(|| "all".to_string()),
-            started_at: now_unix(),
-        };
+/// Dry run of a sync: what would be downloaded, re-tagged or skipped, with
+/// download sizes from HEAD requests. Runs inline rather than as a job.
+async fn plan_sync(
+    State(state): State<ApiState>,
+    body: Option<Json<SyncRequest>>,
+) -> Result<Json<SyncPlan>, ApiError> {
+    let filter = request_filter(body)?;
+    let matcher = filter
+        .compile()
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    let sessions = ensure_logged_in(&state).await?;
+    if sessions.is_empty() {
+        return Err(ApiError::bad_request("未配置 Dizzylab 账号"));
     }
+    let config = state.config.read().await.clone();
 
-    *state.last_error.write().await = None;
-    push_log(
-        &state,
-        "info",
-        album_id
-            .as_ref()
-            .map(|id| format!("同步任务已启动：专辑 {id}"))
-            .unwrap_or_else(|| "同步任务已启动：全部专辑".to_string()),
-    )
-    .await;
-    let job_state = state.job.clone();
-    let last_error = state.last_error.clone();
-    let logs = state.logs.clone();
-
-    tokio::spawn(async move {
-        let job_handle = tokio::spawn(async move { run_sync_job(state, album_id).await });
-        match job_handle.await {
-            Ok(Ok(())) => {
-                push_log_raw(&logs, "info", "同步任务已完成").await;
+    // Albums are planned by the first account that lists them; requested IDs
+    // by the first account that can open them.
+    let deadline = tokio::time::Instant::now() + PLAN_TIMEOUT;
+    let mut plan = SyncPlan::default();
+    let mut candidates: Vec<(String, Vec<usize>)> = Vec::new();
+    if filter.only_ids() {
+        let all = (0..sessions.len()).collect::<Vec<_>>();
+        candidates.extend(filter.ids.iter().map(|id| (id.clone(), all.clone())));
+    } else {
+        let mut seen = HashSet::new();
+        let mut visible = HashSet::new();
+        for (index, session) in sessions.iter().enumerate() {
+            let listed =
+                tokio::time::timeout_at(deadline, session.client.get_my_discs(&session.token))
+                    .await
+                    .unwrap_or_else(|_| Err(anyhow!("超过 {} 秒", PLAN_TIMEOUT.as_secs())));
+            let mut albums = match listed {
+                Ok(albums) => albums,
+                Err(e) => {
+                    let label = account_label(&session.account);
+                    plan.fail(None, format!("获取账号 {label} 的专辑列表失败: {e}"));
+                    continue;
+                }
+            };
+            visible.extend(albums.iter().map(|album| album.id.clone()));
+            if filter.has_date_bounds() {
+                let filled = fill_release_dates(
+                    &state.config_path,
+                    &session.client,
+                    &session.token,
+                    &mut albums,
+                );
+                if tokio::time::timeout_at(deadline, filled).await.is_err() {
+                    plan.fail(
+                        None,
+                        format!("补全发行日期超过 {} 秒", PLAN_TIMEOUT.as_secs()),
+                    );
+                }
             }
-            Ok(Err(e)) => {
-                error!("API 触发的同步任务失败: {}", e);
-                push_log_raw(&logs, "error", format!("同步任务失败：{e}")).await;
-                *last_error.write().await = Some(e.to_string());
+            for album in matcher.select(albums) {
+                if seen.insert(album.id.clone()) {
+                    candidates.push((album.id, vec![index]));
+                }
             }
-            Err(e) => {
-                error!("API 触发的同步任务异常: {}", e);
-                push_log_raw(&logs, "error", format!("同步任务异常：{e}")).await;
-                *last_error.write().await = Some(e.to_string());
+        }
+        for id in filter.ids.iter().filter(|id| !visible.contains(*id)) {
+            plan.fail(Some(id), format!("所有账号均未找到或无法访问专辑 {id}"));
+        }
+    }
+
+    let planners = Arc::new(
+        sessions
+            .iter()
+            .map(|session| {
+                let downloader = Downloader::new(
+                    session.client.clone(),
+                    config.clone(),
+                    session.token.clone(),
+                );
+                (account_label(&session.account), session.clone(), downloader)
+            })
+            .collect::<Vec<_>>(),
+    );
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(
+        config.behavior.max_concurrent_albums.max(1),
+    ));
+    let mut join_set = tokio::task::JoinSet::new();
+    for (order, (id, candidates)) in candidates.into_iter().enumerate() {
+        let planners = planners.clone();
+        let semaphore = semaphore.clone();
+        join_set.spawn(async move {
+            let planned = tokio::time::timeout_at(deadline, async {
+                let _permit = semaphore.acquire().await;
+                let mut last_error = None;
+                for index in candidates {
+                    let (label, session, downloader) = &planners[index];
+                    match session.client.get_disc_info(&id, &session.token).await {
+                        Ok(disc_info) => return Ok(downloader.plan_album(label, &disc_info).await),
+                        Err(e) => last_error = Some(e),
+                    }
+                }
+                Err(last_error
+                    .map(|e| format!("获取专辑 {id} 详情失败: {e}"))
+                    .unwrap_or_else(|| format!("未找到或无法访问专辑 {id}")))
+            })
+            .await
+            .unwrap_or_else(|_| {
+                Err(format!(
+                    "计算同步计划超过 {} 秒，未计算专辑 {id}",
+                    PLAN_TIMEOUT.as_secs()
+                ))
+            });
+            (order, id, planned)
+        });
+    }
+
+    let mut planned = Vec::new();
+    while let Some(joined) = join_set.join_next().await {
+        match joined {
+            Ok(result) => planned.push(result),
+            Err(e) => tracing::warn!("计算同步计划的任务异常退出: {}", e),
+        }
+    }
+    planned.sort_by_key(|(order, _, _)| *order);
+    for (_, id, result) in planned {
+        match result {
+            Ok(album) => plan.push(album),
+            Err(e) => plan.fail(Some(&id), e),
+        }
+    }
+    Ok(Json(plan))
+}
+
+/// The albums a `/sync` or `/sync/plan` body selects.
+fn request_filter(body: Option<Json<SyncRequest>>) -> Result<AlbumFilter, ApiError> {
+    let Some(Json(req)) = body else {
+        return Ok(AlbumFilter::default());
+    };
+    let mut filter = req.filter;
+    if let Some(id) = req.id {
+        if id.trim().is_empty() {
+            return Err(ApiError::bad_request("专辑 ID 不能为空"));
+        }
+        filter.ids.push(id);
+    }
+    filter
+        .normalized()
+        .map_err(|e| ApiError::bad_request(e.to_string()))
+}
+
+/// A filter selecting nothing in particular is a full sync, and a single ID
+/// stays an album job so it deduplicates with `/sync/{id}`.
+fn filter_job_kind(filter: AlbumFilter) -> JobKind {
+    match filter.ids.as_slice() {
+        _ if filter.is_empty() => JobKind::All,
+        [id] if filter.only_ids() => JobKind::Album { id: id.clone() },
+        _ => JobKind::Filter(filter),
+    }
+}
+
+fn album_job_kind(id: String) -> Result<JobKind, ApiError> {
+    let id = id.trim().to_string();
+    if id.is_empty() {
+        return Err(ApiError::bad_request("专辑 ID 不能为空"));
+    }
+    Ok(JobKind::Album { id })
+}
+
+async fn list_jobs(State(state): State<ApiState>) -> Result<Json<JobsResponse>, ApiError> {
+    let jobs = state.jobs.lock().await;
+    Ok(Json(JobsResponse {
+        running: jobs.running().cloned(),
+        queued: jobs.queued(),
+    }))
+}
+
+async fn enqueue_job(
+    State(state): State<ApiState>,
+    Json(kind): Json<JobKind>,
+) -> Result<(StatusCode, Json<EnqueueResponse>), ApiError> {
+    let kind = match kind {
+        JobKind::Album { id } => album_job_kind(id)?,
+        JobKind::Account { username } => {
+            let username = username.trim().to_string();
+            let config = state.config.read().await;
+            if !config
+                .accounts()
+                .iter()
+                .any(|account| account.username == username)
+            {
+                return Err(ApiError::not_found(format!("未配置账号 {username}")));
             }
+            JobKind::Account { username }
+        }
+        JobKind::All => JobKind::All,
+        JobKind::Filter(filter) => filter_job_kind(
+            filter
+                .normalized()
+                .map_err(|e| ApiError::bad_request(e.to_string()))?,
+        ),
+    };
+    Ok(enqueue(&state, kind, JobTrigger::Manual).await)
+}
+
+async fn remove_job(
+    State(state): State<ApiState>,
+    Path(id): Path<u64>,
+) -> Result<Json<MessageResponse>, ApiError> {
+    let removed = state.jobs.lock().await.remove(id);
+    match removed {
+        Some(job) => {
+            push_log(
+                &state,
+                "info",
+                format!("已从同步队列移除任务 #{}：{}", job.id, job.kind.label()),
+            )
+            .await;
+            Ok(Json(MessageResponse {
+                message: "任务已从队列移除".to_string(),
+            }))
+        }
+        None => Err(ApiError::not_found(format!("队列中没有任务 #{id}"))),
+    }
+}
+
+async fn move_job(
+    State(state): State<ApiState>,
+    Path(id): Path<u64>,
+    Json(req): Json<MoveJobRequest>,
+) -> Result<Json<JobsResponse>, ApiError> {
+    let mut jobs = state.jobs.lock().await;
+    if jobs.move_to(id, req.position).is_none() {
+        return Err(ApiError::not_found(format!("队列中没有任务 #{id}")));
+    }
+    Ok(Json(JobsResponse {
+        running: jobs.running().cloned(),
+        queued: jobs.queued(),
+    }))
+}
+
+async fn enqueue(
+    state: &ApiState,
+    kind: JobKind,
+    trigger: JobTrigger,
+) -> (StatusCode, Json<EnqueueResponse>) {
+    let enqueued = state.jobs.lock().await.enqueue(kind, trigger, now_unix());
+    match enqueued {
+        Enqueued::Added { job, position } => {
+            push_log(
+                state,
+                "info",
+                format!(
+                    "同步任务 #{} 已加入队列：{}（位置 {}）",
+                    job.id,
+                    job.kind.label(),
+                    position + 1
+                ),
+            )
+            .await;
+            state.job_notify.notify_one();
+            (
+                StatusCode::ACCEPTED,
+                Json(EnqueueResponse {
+                    message: "同步任务已加入队列".to_string(),
+                    job,
+                    position,
+                }),
+            )
+        }
+        Enqueued::Duplicate { job, position } => (
+            StatusCode::OK,
+            Json(EnqueueResponse {
+                message: "相同的同步任务已在队列中".to_string(),
+                job,
+                position,
+            }),
+        ),
+    }
+}
+
+fn start_job_worker(state: ApiState) {
+    tokio::spawn(async move {
+        loop {
+            let next = if shutdown::requested() {
+                None
+            } else {
+                state.jobs.lock().await.start_next(now_unix())
+            };
+            let Some(job) = next else {
+                state.job_notify.notified().await;
+                continue;
+            };
+            run_queued_job(&state, job).await;
+            state.jobs.lock().await.finish();
         }
-        *job_state.lock().await = JobState::Idle;
     });
+}
 
-    Ok((
-        StatusCode::ACCEPTED,
-        Json(MessageResponse {
-            message: "同步任务已启动".to_string(),
-        }),
-    ))
+async fn run_queued_job(state: &ApiState, job: RunningJob) {
+    *state.last_error.write().await = None;
+    if job.trigger == JobTrigger::Scheduled {
+        state.schedule.write().await.last_run = Some(job.started_at);
+    }
+    push_job_log(
+        state,
+        job.id,
+        "info",
+        format!("同步任务 #{} 已启动：{}", job.id, job.kind.label()),
+    );
+    progress::emit(SyncEvent::JobStarted {
+        job_id: job.id,
+        kind: job.kind.label(),
+    });
+
+    let run_state = state.clone();
+    let kind = job.kind.clone();
+    let span = info_span!("sync_job", job_id = job.id);
+    let job_handle = tokio::spawn(run_sync_job(run_state, kind).instrument(span.clone()));
+    let failure = match job_handle.await {
+        Ok(Ok(())) => {
+            push_job_log(
+                state,
+                job.id,
+                "info",
+                format!("同步任务 #{} 已完成", job.id),
+            );
+            None
+        }
+        Ok(Err(e)) if shutdown::requested() => {
+            span.in_scope(|| warn!("同步任务 #{} 已中断: {}", job.id, e));
+            push_job_log(
+                state,
+                job.id,
+                "warn",
+                format!("同步任务 #{} 已中断：{e}", job.id),
+            );
+            Some(e.to_string())
+        }
+        Ok(Err(e)) => {
+            span.in_scope(|| error!("同步任务 #{} 失败: {}", job.id, e));
+            push_job_log(
+                state,
+                job.id,
+                "error",
+                format!("同步任务 #{} 失败：{e}", job.id),
+            );
+            Some(e.to_string())
+        }
+        Err(e) => {
+            span.in_scope(|| error!("同步任务 #{} 异常: {}", job.id, e));
+            push_job_log(
+                state,
+                job.id,
+                "error",
+                format!("同步任务 #{} 异常：{e}", job.id),
+            );
+            Some(format!("同步任务异常: {e}"))
+        }
+    };
+
+    record_job_done(state, &job, failure).await;
 }
 
-async fn run_sync_job(state: ApiState, album_id: Option<String>) -> Result<()> {
-    let sessions = ensure_logged_in(&state).await?;
+/// Publish the end of a job. A job that fails after a shutdown signal is
+/// recorded as interrupted rather than failed.
+async fn record_job_done(state: &ApiState, job: &RunningJob, failure: Option<String>) {
+    progress::emit(SyncEvent::JobDone {
+        job_id: job.id,
+        success: failure.is_none(),
+        interrupted: failure.is_some() && shutdown::requested(),
+        error: failure.clone(),
+    });
+    if let Some(message) = &failure {
+        *state.last_error.write().await = Some(message.clone());
+    }
+    if job.trigger == JobTrigger::Scheduled {
+        state.schedule.write().await.last_error = failure;
+    }
+}
+
+async fn run_sync_job(state: ApiState, kind: JobKind) -> Result<()> {
+    let mut sessions = ensure_logged_in(&state).await?;
     let config = state.config.read().await.clone();
     let mut failures = Vec::new();
-    let mut album_found = false;
+    let mut found = HashSet::new();
+
+    if let JobKind::Account { username } = &kind {
+        sessions.retain(|session| &session.account.username == username);
+        if sessions.is_empty() {
+            return Err(anyhow!("未找到已登录的账号 {username}"));
+        }
+    }
 
     for session in sessions {
         let account_label = account_label(&session.account);
-        let downloader = Downloader::new(
-            session.client.clone(),
-            config.clone(),
-            session.token.clone(),
-        );
+        let span = info_span!("account", account = %account_label);
+        if let Err(e) = sync_account(&session, &state.config_path, &config, &kind, &mut found)
+            .instrument(span)
+            .await
+        {
+            failures.push(format!("{account_label}: {e}"));
+        }
+    }
 
-        if let Some(album_id) = &album_id {
+    let requested = match &kind {
+        JobKind::Album { id } => std::slice::from_ref(id),
+        JobKind::Filter(filter) => filter.ids.as_slice(),
+        JobKind::All | JobKind::Account { .. } => &[],
+    };
+    for id in requested.iter().filter(|id| !found.contains(*id)) {
+        failures.push(format!("所有账号均未找到或无法访问专辑 {id}"));
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(failures.join("; ")))
+    }
+}
+
+/// Run one account's share of a job, adding the IDs of the albums it could
+/// see to `found`, whether or not the filter selected them.
+async fn sync_account(
+    session: &AccountSession,
+    config_path: &str,
+    config: &Config,
+    kind: &JobKind,
+    found: &mut HashSet<String>,
+) -> Result<()> {
+    let account_label = account_label(&session.account);
+    let downloader = Downloader::new(
+        session.client.clone(),
+        config.clone(),
+        session.token.clone(),
+    );
+    let filter = match kind {
+        JobKind::Album { id } => AlbumFilter {
+            ids: vec![id.clone()],
+            ..Default::default()
+        },
+        JobKind::Filter(filter) => filter.clone(),
+        JobKind::All | JobKind::Account { .. } => AlbumFilter::default(),
+    };
+
+    if filter.only_ids() {
+        let mut failures = Vec::new();
+        for album_id in &filter.ids {
             match session.client.get_disc_info(album_id, &session.token).await {
                 Ok(disc_info) => {
-                    album_found = true;
                     info!("账号 {} 开始同步专辑 {}", account_label, album_id);
+                    found.insert(album_id.clone());
                     if let Err(e) = downloader.download_album(&disc_info).await {
-                        failures.push(format!("{account_label}: {e}"));
+                        failures.push(e.to_string());
                     }
                 }
-                Err(e) => {
-                    info!(
-                        "账号 {} 未找到或无法访问专辑 {}: {}",
-                        account_label, album_id, e
-                    );
-                }
-            }
-        } else {
-            match session.client.get_my_discs(&session.token).await {
-                Ok(albums) => {
-                    info!("账号 {} 开始同步 {} 个专辑", account_label, albums.len());
-                    if let Err(e) = downloader.sync_all_albums(albums).await {
-                        failures.push(format!("{account_label}: {e}"));
-                    }
-                }
-                Err(e) => failures.push(format!("{account_label}: {e}")),
+                Err(e) => info!(
+                    "账号 {} 未找到或无法访问专辑 {}: {}",
+                    account_label, album_id, e
+                ),
             }
         }
+        return if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(failures.join("; ")))
+        };
     }
 
-    if album_id.is_some() && !album_found {
-        failures.push("所有账号均未找到或无法访问指定专辑".to_string());
+    let mut albums = session.client.get_my_discs(&session.token).await?;
+    found.extend(albums.iter().map(|album| album.id.clone()));
+    if filter.has_date_bounds() {
+        fill_release_dates(config_path, &session.client, &session.token, &mut albums).await;
     }
-
-    if failures.is_empty() {
-        Ok(())
+    let albums = if filter.is_empty() {
+        info!("账号 {} 开始同步 {} 个专辑", account_label, albums.len());
+        albums
     } else {
-        Err(anyhow!(failures.join("; ")))
-    }
+        let total = albums.len();
+        let albums = filter.compile()?.select(albums);
+        info!(
+            "账号 {} 的 {} 个专辑中有 {} 个匹配筛选条件 {}",
+            account_label,
+            total,
+            albums.len(),
+            filter.describe()
+        );
+        albums
+    };
+    downloader.sync_all_albums(albums).await?;
+    Ok(())
 }
 
 async fn ensure_logged_in(state: &ApiState) -> Result<Vec<AccountSession>> {
@@ -1122,22 +2304,32 @@ async fn login_accounts(config: &Config) -> Result<Vec<AccountSession>> {
 
     let mut sessions = Vec::new();
     for account in config.accounts() {
-        let client = DizzylabClient::new(config.behavior.debug)?;
-        let token = client
-            .login(&account.username, &account.password)
-            .await
-            .map_err(|e| anyhow!("{}: {e}", account_label(&account)))?;
-        let user = client.get_my_info(&token).await.ok();
-        sessions.push(AccountSession {
-            account,
-            client,
-            token,
-            user,
-        });
+        sessions.push(login_account(config, account).await?);
     }
     Ok(sessions)
 }
 
+async fn login_account(config: &Config, account: UserConfig) -> Result<AccountSession> {
+    let client = DizzylabClient::new(config.behavior.debug)?;
+    let token = match client.login(&account.username, &account.password).await {
+        Ok(token) => token,
+        Err(e) => {
+            progress::emit(SyncEvent::LoginFailed {
+                account: account_label(&account),
+                error: e.to_string(),
+            });
+            return Err(anyhow!("{}: {e}", account_label(&account)));
+        }
+    };
+    let user = client.get_my_info(&token).await.ok();
+    Ok(AccountSession {
+        account,
+        client,
+        token,
+        user,
+    })
+}
+
 fn account_label(account: &UserConfig) -> String {
     if account.username.trim().is_empty() {
         "<empty>".to_string()
@@ -1146,9 +2338,202 @@ fn account_label(account: &UserConfig) -> String {
     }
 }
 
-async fn authorize(state: &ApiState, headers: &HeaderMap) -> Result<(), ApiError> {
-    let expected = state.config.read().await.api.api_key.clone();
-    authorize_with_key(&expected, header_api_key(headers))
+async fn require_read(
+    State(state): State<ApiState>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, ApiError> {
+    require_scope(&state, ApiScope::Read, false, request, next).await
+}
+
+/// Read access for EventSource and `<img>`/`<audio>` requests, which cannot
+/// send custom headers and may pass the key as `?api_key=` instead.
+async fn require_read_media(
+    State(state): State<ApiState>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, ApiError> {
+    require_scope(&state, ApiScope::Read, true, request, next).await
+}
+
+async fn require_sync(
+    State(state): State<ApiState>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, ApiError> {
+    require_scope(&state, ApiScope::Sync, false, request, next).await
+}
+
+async fn require_admin(
+    State(state): State<ApiState>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, ApiError> {
+    require_scope(&state, ApiScope::Admin, false, request, next).await
+}
+
+async fn require_scope(
+    state: &ApiState,
+    scope: ApiScope,
+    allow_query_key: bool,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, ApiError> {
+    // Keys in URLs end up in access logs and browser history, so only the
+    // routes that cannot send headers accept `?api_key=`.
+    let query_key = (allow_query_key && request.method() == Method::GET)
+        .then(|| Query::<ApiKeyQuery>::try_from_uri(request.uri()).ok())
+        .flatten()
+        .and_then(|Query(query)| query.api_key);
+    // Wrong keys count towards the same per-IP lockout as Web UI logins.
+    let key_ip = (query_key.is_some() || header_api_key(request.headers()).is_some())
+        .then(|| request.extensions().get::<ConnectInfo<SocketAddr>>())
+        .flatten()
+        .map(|ConnectInfo(peer)| peer.ip());
+    let now = now_unix();
+    if let Some(ip) = key_ip {
+        let locked = state
+            .web_sessions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .locked_for(ip, now);
+        if let Some(retry_after) = locked {
+            return Ok(locked_out(retry_after));
+        }
+    }
+    let grant = {
+        let config = state.config.read().await;
+        request_grant(state, &config, request.headers(), query_key.as_deref())
+    };
+    match grant {
+        None => {
+            if let Some(ip) = key_ip {
+                let lockout = state
+                    .web_sessions
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .record_failure(ip, now);
+                match lockout {
+                    Some(lockout) => {
+                        warn!("API key 认证失败次数过多，已锁定 {} {} 秒", ip, lockout)
+                    }
+                    None => warn!("API key 认证失败: {}", ip),
+                }
+            }
+            Err(ApiError::unauthorized("无效或缺失 API key"))
+        }
+        Some(grant) if !grant.allows(scope) => Err(ApiError::forbidden(format!(
+            "API key \"{}\" 没有 {} 权限",
+            grant.name,
+            scope.as_str()
+        ))),
+        Some(_) => Ok(next.run(request).await),
+    }
+}
+
+/// Resolve the caller from an API key (header, then `query_key`) or, failing
+/// that, a Web UI session cookie.
+fn request_grant(
+    state: &ApiState,
+    config: &Config,
+    headers: &HeaderMap,
+    query_key: Option<&str>,
+) -> Option<ApiGrant> {
+    if let Some(key) = header_api_key(headers).or(query_key) {
+        return api_keys::resolve(&config.api, Some(key));
+    }
+    if !api_keys::requires_auth(&config.api) {
+        return api_keys::resolve(&config.api, None);
+    }
+    let token = headers
+        .get(header::COOKIE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(web_auth::session_token)?;
+    state
+        .web_sessions
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(token, now_unix())
+}
+
+async fn web_login(
+    State(state): State<ApiState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Json(req): Json<WebLoginRequest>,
+) -> Result<Response, ApiError> {
+    let api = state.config.read().await.api.clone();
+    let ip = peer.ip();
+    let now = now_unix();
+    let mut sessions = state
+        .web_sessions
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(retry_after) = sessions.locked_for(ip, now) {
+        return Ok(locked_out(retry_after));
+    }
+
+    let key = req.password.trim();
+    let Some(grant) = api_keys::resolve(&api, Some(key)) else {
+        match sessions.record_failure(ip, now) {
+            Some(lockout) => warn!("Web UI 登录失败次数过多，已锁定 {} {} 秒", ip, lockout),
+            None => warn!("Web UI 登录失败: {}", ip),
+        }
+        return Err(ApiError::unauthorized("密码错误"));
+    };
+    sessions.record_success(ip);
+
+    let ttl_secs = api.session_ttl_hours.max(1) * 60 * 60;
+    let secure = matches!(tls::tls_paths(&api), Ok(Some(_)));
+    let token = sessions.create(grant.clone(), key, ttl_secs, now)?;
+    drop(sessions);
+    info!("Web UI 登录成功: {} ({})", ip, grant.name);
+
+    let mut response = Json(WebLoginResponse {
+        message: "登录成功".to_string(),
+        scopes: grant.scopes,
+    })
+    .into_response();
+    if let Ok(cookie) = web_auth::session_cookie(&token, ttl_secs, secure).parse() {
+        response.headers_mut().insert(header::SET_COOKIE, cookie);
+    }
+    Ok(response)
+}
+
+async fn web_logout(State(state): State<ApiState>, headers: HeaderMap) -> Response {
+    let secure = matches!(tls::tls_paths(&state.config.read().await.api), Ok(Some(_)));
+    if let Some(token) = headers
+        .get(header::COOKIE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(web_auth::session_token)
+    {
+        state
+            .web_sessions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(token);
+    }
+    let mut response = Json(MessageResponse {
+        message: "已退出登录".to_string(),
+    })
+    .into_response();
+    if let Ok(cookie) = web_auth::expired_session_cookie(secure).parse() {
+        response.headers_mut().insert(header::SET_COOKIE, cookie);
+    }
+    response
+}
+
+/// 429 for an IP locked out after too many failed logins or key guesses.
+fn locked_out(retry_after: u64) -> Response {
+    let message = format!(
+        "登录失败次数过多，请 {} 分钟后再试",
+        (retry_after + 59) / 60
+    );
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after.to_string())],
+        Json(MessageResponse { message }),
+    )
+        .into_response()
 }
 
 fn header_api_key(headers: &HeaderMap) -> Option<&str> {
@@ -1163,46 +2548,32 @@ fn header_api_key(headers: &HeaderMap) -> Option<&str> {
         })
 }
 
-fn authorize_with_key(expected: &str, provided: Option<&str>) -> Result<(), ApiError> {
-    if expected.is_empty() || provided == Some(expected) {
-        Ok(())
-    } else {
-        Err(ApiError::unauthorized("无效或缺失 API key"))
-    }
-}
-
-fn apply_config_update(config: &mut Config, req: UpdateConfigRequest) {
+fn apply_config_update(config: &mut Config, req: UpdateConfigRequest) -> Result<()> {
     if let Some(users) = req.users {
         let existing = config.accounts();
         let next_users = users
             .into_iter()
             .map(|user| {
                 let username = user.username.unwrap_or_default();
-                let password = user
-                    .password
-                    .filter(|password| !password.is_empty())
-                    .or_else(|| {
-                        existing
-                            .iter()
-                            .find(|account| account.username == username)
-                            .map(|account| account.password.clone())
-                    })
+                // Keep the stored account, including any password_file/password_env
+                // reference, unless a new password was typed in.
+                let mut account = existing
+                    .iter()
+                    .find(|account| account.username == username)
+                    .cloned()
                     .unwrap_or_default();
-
-                UserConfig { username, password }
+                account.username = username;
+                set_typed_password(&mut account, user.password)?;
+                Ok(account)
             })
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>>>()?;
         config.set_accounts(next_users);
     } else if let Some(user) = req.user {
         let mut next = config.accounts().first().cloned().unwrap_or_default();
         if let Some(username) = user.username {
             next.username = username;
         }
-        if let Some(password) = user.password {
-            if !password.is_empty() {
-                next.password = password;
-            }
-        }
+        set_typed_password(&mut next, user.password)?;
         config.set_accounts(vec![next]);
     }
 
@@ -1259,6 +2630,21 @@ fn apply_config_update(config: &mut Config, req: UpdateConfigRequest) {
             config.api.api_key = api_key;
         }
     }
+    Ok(())
+}
+
+fn set_typed_password(account: &mut UserConfig, password: Option<String>) -> Result<()> {
+    let Some(password) = password.filter(|password| !password.is_empty()) else {
+        return Ok(());
+    };
+    if account.password_source().is_some() {
+        return Err(anyhow!(
+            "账号 {} 的密码来自 password_file/password_env，请直接修改对应的文件或环境变量",
+            account.username
+        ));
+    }
+    account.password = password;
+    Ok(())
 }
 
 pub fn validate_credentials(config: &Config) -> Result<()> {
@@ -1294,7 +2680,7 @@ pub fn validate_formats(config: &Config) -> Result<()> {
         return Err(anyhow!("formats 至少需要包含一种下载格式"));
     }
 
-    let mut seen = std::collections::HashSet::new();
+    let mut seen = HashSet::new();
     for format in &config.download.formats {
         match format.as_str() {
             "128" | "320" | "FLAC" | "gift" => {}
@@ -1326,14 +2712,18 @@ impl PublicConfig {
             .accounts()
             .into_iter()
             .map(|account| PublicUserConfig {
-                username: account.username,
                 has_password: !account.password.is_empty(),
+                password_source: account.password_source().map(str::to_string),
+                username: account.username,
             })
             .collect::<Vec<_>>();
+        let env_locked = config_env::locked_fields();
+        let locked = |field: &str| env_locked.iter().any(|locked| locked == field);
         Self {
             user: users.first().cloned().unwrap_or(PublicUserConfig {
                 username: String::new(),
                 has_password: false,
+                password_source: None,
             }),
             users,
             download: PublicDownloadConfig {
@@ -1342,13 +2732,13 @@ impl PublicConfig {
             paths: PublicPathsConfig {
                 output_dir: config.paths.output_dir.display().to_string(),
                 directory_template: config.paths.directory_template.clone(),
-                output_dir_locked: std::env::var("DIZZYSYNC_OUTPUT_DIR").is_ok(),
+                output_dir_locked: locked("paths.output_dir"),
             },
             behavior: PublicBehaviorConfig {
                 skip_existing: config.behavior.skip_existing,
                 single_threaded: config.behavior.single_threaded,
                 max_concurrent_albums: config.behavior.max_concurrent_albums.max(1),
-                max_concurrent_albums_locked: false,
+                max_concurrent_albums_locked: locked("behavior.max_concurrent_albums"),
                 generate_readme: config.behavior.generate_readme,
                 generate_nfo: config.behavior.generate_nfo,
                 debug: config.behavior.debug,
@@ -1362,12 +2752,22 @@ impl PublicConfig {
                 bind: config.api.bind.clone(),
                 has_api_key: !config.api.api_key.is_empty(),
                 web_root: config.api.web_root.display().to_string(),
+                keys: config
+                    .api
+                    .keys
+                    .iter()
+                    .map(|key| PublicApiKey {
+                        name: key.name.clone(),
+                        scopes: key.scopes.clone(),
+                    })
+                    .collect(),
             },
+            env_locked,
         }
     }
 }
 
-fn start_scheduler(state: ApiState) {
+fn start_scheduler(state: ApiState) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
         interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
@@ -1423,63 +2823,26 @@ fn start_scheduler(state: ApiState) {
                 schedule_state.next_run = next_after_fire;
             }
 
-            let mut job = state.job.lock().await;
-            if matches!(*job, JobState::Running { .. }) {
-                info!("自动同步已到触发时间，但已有同步任务正在运行，跳过本次触发");
-                continue;
-            }
-            *job = JobState::Running {
-                kind: "scheduled".to_string(),
-                started_at: now_unix(),
-            };
-            drop(job);
-
-            let run_state = state.clone();
-            let job_state = state.job.clone();
-            let schedule_state = state.schedule.clone();
-            let last_error = state.last_error.clone();
-            tokio::spawn(async move {
-                let started_at = now_unix();
-                let current = schedule_state.read().await.clone();
-                *schedule_state.write().await = ScheduleState {
-                    last_run: Some(started_at),
-                    ..current
-                };
-
-                let job_handle = tokio::spawn(async move { run_sync_job(run_state, None).await });
-                match job_handle.await {
-                    Ok(Ok(())) => {
-                        let current = schedule_state.read().await.clone();
-                        *schedule_state.write().await = ScheduleState {
-                            last_error: None,
-                            ..current
-                        };
-                    }
-                    Ok(Err(e)) => {
-                        let message = e.to_string();
-                        error!("自动同步任务失败: {}", message);
-                        *last_error.write().await = Some(message.clone());
-                        let current = schedule_state.read().await.clone();
-                        *schedule_state.write().await = ScheduleState {
-                            last_error: Some(message),
-                            ..current
-                        };
-                    }
-                    Err(e) => {
-                        let message = format!("自动同步任务异常: {e}");
-                        error!("{}", message);
-                        *last_error.write().await = Some(message.clone());
-                        let current = schedule_state.read().await.clone();
-                        *schedule_state.write().await = ScheduleState {
-                            last_error: Some(message),
-                            ..current
-                        };
-                    }
+            let enqueued =
+                state
+                    .jobs
+                    .lock()
+                    .await
+                    .enqueue(JobKind::All, JobTrigger::Scheduled, now_unix());
+            match enqueued {
+                Enqueued::Added { job, .. } => {
+                    info!("自动同步已到触发时间，任务 #{} 已加入同步队列", job.id);
+                    state.job_notify.notify_one();
                 }
-                *job_state.lock().await = JobState::Idle;
-            });
+                Enqueued::Duplicate { job, .. } => {
+                    info!(
+                        "自动同步已到触发时间，但全量同步任务 #{} 已在队列中，跳过本次触发",
+                        job.id
+                    );
+                }
+            }
         }
-    });
+    })
 }
 
 fn schedule_state_from_config(config: &Config) -> ScheduleState {
@@ -1502,11 +2865,11 @@ fn schedule_state_from_config(config: &Config) -> ScheduleState {
 }
 
 fn ensure_api_key_for_remote_bind(config: &mut Config) -> Result<()> {
-    if !config.api.api_key.is_empty() || is_loopback_bind(&config.api.bind) {
+    if api_keys::requires_auth(&config.api) || is_loopback_bind(&config.api.bind) {
         return Ok(());
     }
 
-    config.api.api_key = generate_api_key()?;
+    config.api.api_key = api_keys::random_token()?;
     info!("API/Web 监听非本地地址且未配置 Web UI 密码，已自动生成并写入配置文件");
     Ok(())
 }
@@ -1534,20 +2897,6 @@ fn is_loopback_bind(bind: &str) -> bool {
         .unwrap_or(false)
 }
 
-fn generate_api_key() -> Result<String> {
-    let mut bytes = [0_u8; 32];
-    std::fs::File::open("/dev/urandom")
-        .and_then(|mut file| std::io::Read::read_exact(&mut file, &mut bytes))
-        .map_err(|e| anyhow!("无法从系统随机源读取安全随机数: {e}"))?;
-
-    let mut key = String::with_capacity(bytes.len() * 2);
-    for byte in bytes {
-        use std::fmt::Write as _;
-        let _ = write!(key, "{byte:02x}");
-    }
-    Ok(key)
-}
-
 fn now_unix() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -1559,12 +2908,17 @@ async fn push_log(state: &ApiState, level: &'static str, message: impl Into<Stri
     push_log_raw(&state.logs, level, message).await;
 }
 
+/// Log on behalf of a job from outside its span (start/finish bookkeeping).
+fn push_job_log(state: &ApiState, job_id: u64, level: &'static str, message: impl Into<String>) {
+    push_log_sync(&state.logs, level, message, LogContext::job(job_id));
+}
+
 async fn push_log_raw(
     logs: &Arc<Mutex<Vec<LogEntry>>>,
     level: &'static str,
     message: impl Into<String>,
 ) {
-    push_log_sync(logs, level, message);
+    push_log_sync(logs, level, message, LogContext::default());
 }
 
 fn redact_sensitive(message: &str) -> String {
@@ -1591,13 +2945,18 @@ fn push_log_sync(
     logs: &Arc<Mutex<Vec<LogEntry>>>,
     level: &'static str,
     message: impl Into<String>,
+    context: LogContext,
 ) {
-    let mut logs = logs.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
-    logs.push(LogEntry {
+    let entry = LogEntry {
         timestamp: now_unix(),
         level,
         message: redact_sensitive(&message.into()),
-    });
+        context,
+    };
+    log_files::append(&entry);
+    let mut logs = logs.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let _ = log_stream().send(entry.clone());
+    logs.push(entry);
     if logs.len() > MAX_LOG_ENTRIES {
         let excess = logs.len() - MAX_LOG_ENTRIES;
         logs.drain(0..excess);
@@ -1617,6 +2976,13 @@ impl ApiError {
         }
     }
 
+    fn forbidden(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            message: redact_sensitive(&message.into()),
+        }
+    }
+
     fn conflict(message: impl Into<String>) -> Self {
         Self {
             status: StatusCode::CONFLICT,
@@ -0,0 +1,199 @@
+use crate::config::ApiConfig;
+use anyhow::{anyhow, Result};
+use aws_lc_rs::rand::{SecureRandom, SystemRandom};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+const HASH_PREFIX: &str = "sha256:";
+
+/// What an API key may do. `admin` implies every other scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiScope {
+    Read,
+    Sync,
+    Admin,
+}
+
+impl ApiScope {
+    const ALL: [ApiScope; 3] = [ApiScope::Read, ApiScope::Sync, ApiScope::Admin];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiScope::Read => "read",
+            ApiScope::Sync => "sync",
+            ApiScope::Admin => "admin",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == value.trim())
+    }
+}
+
+/// The identity and scopes resolved from a request's API key.
+#[derive(Debug, Clone)]
+pub struct ApiGrant {
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+}
+
+impl ApiGrant {
+    fn full(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            scopes: ApiScope::ALL.to_vec(),
+        }
+    }
+
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes.contains(&scope) || self.scopes.contains(&ApiScope::Admin)
+    }
+}
+
+pub fn hash_key(key: &str) -> String {
+    format!("{HASH_PREFIX}{}", to_hex(&Sha256::digest(key.as_bytes())))
+}
+
+/// 32 random bytes from the system RNG, hex encoded.
+pub fn random_token() -> Result<String> {
+    let mut bytes = [0_u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| anyhow!("无法生成安全随机数"))?;
+    Ok(to_hex(&bytes))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        use std::fmt::Write as _;
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+/// Replace plaintext `key` entries with their hash. Returns how many were hashed.
+pub fn hash_plain_keys(api: &mut ApiConfig) -> usize {
+    let mut hashed = 0;
+    for entry in &mut api.keys {
+        if !entry.key.is_empty() {
+            entry.key_hash = hash_key(&entry.key);
+            entry.key.clear();
+            hashed += 1;
+        }
+    }
+    hashed
+}
+
+pub fn requires_auth(api: &ApiConfig) -> bool {
+    !api.api_key.is_empty() || !api.keys.is_empty()
+}
+
+/// Resolve the provided key. Without any configured key every request gets full
+/// access; the legacy `api_key` is an unnamed admin key.
+pub fn resolve(api: &ApiConfig, provided: Option<&str>) -> Option<ApiGrant> {
+    let hash = provided.filter(|key| !key.is_empty()).map(hash_key);
+    resolve_hash(api, hash.as_deref())
+}
+
+/// [`resolve`] from the hash of the provided key, so that a grant can be
+/// re-evaluated after the keys change without keeping the key itself.
+pub fn resolve_hash(api: &ApiConfig, hash: Option<&str>) -> Option<ApiGrant> {
+    if !requires_auth(api) {
+        return Some(ApiGrant::full(""));
+    }
+    let hash = hash?;
+    if !api.api_key.is_empty() && hash_key(&api.api_key) == hash {
+        return Some(ApiGrant::full("api_key"));
+    }
+
+    api.keys
+        .iter()
+        .find(|entry| {
+            entry.key_hash.eq_ignore_ascii_case(hash)
+                || (!entry.key.is_empty() && hash_key(&entry.key) == hash)
+        })
+        .map(|entry| ApiGrant {
+            name: entry.name.clone(),
+            scopes: entry
+                .scopes
+                .iter()
+                .filter_map(|scope| ApiScope::parse(scope))
+                .collect(),
+        })
+}
+
+pub fn validate_keys(api: &ApiConfig) -> Result<()> {
+    let mut names = HashSet::new();
+    for entry in &api.keys {
+        let name = entry.name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("[[api.keys]] 的 name 不能为空"));
+        }
+        if !names.insert(name) {
+            return Err(anyhow!("[[api.keys]] 中存在重复的名称 \"{}\"", name));
+        }
+        if entry.key.is_empty() && !is_valid_hash(&entry.key_hash) {
+            return Err(anyhow!(
+                "API key \"{}\" 需要设置 key 或 sha256:<64 位十六进制> 格式的 key_hash",
+                name
+            ));
+        }
+        if entry.scopes.is_empty() {
+            return Err(anyhow!("API key \"{}\" 至少需要一个 scope", name));
+        }
+        for scope in &entry.scopes {
+            if ApiScope::parse(scope).is_none() {
+                return Err(anyhow!(
+                    "API key \"{}\" 包含不支持的 scope \"{}\"；可选值为 read、sync、admin",
+                    name,
+                    scope
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_valid_hash(value: &str) -> bool {
+    value
+        .strip_prefix(HASH_PREFIX)
+        .is_some_and(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ApiKeyConfig;
+
+    #[test]
+    fn test_resolve_hashed_scoped_keys() {
+        let mut api = ApiConfig {
+            keys: vec![ApiKeyConfig {
+                name: "player".to_string(),
+                key: "family-secret".to_string(),
+                key_hash: String::new(),
+                scopes: vec!["read".to_string()],
+            }],
+            ..ApiConfig::default()
+        };
+        assert_eq!(hash_plain_keys(&mut api), 1);
+        assert!(api.keys[0].key.is_empty());
+        assert!(validate_keys(&api).is_ok());
+
+        let grant = resolve(&api, Some("family-secret")).expect("key should resolve");
+        assert_eq!(grant.name, "player");
+        assert!(grant.allows(ApiScope::Read));
+        assert!(!grant.allows(ApiScope::Sync));
+        assert!(!grant.allows(ApiScope::Admin));
+        assert!(resolve(&api, Some("wrong")).is_none());
+        assert!(resolve(&api, None).is_none());
+
+        api.api_key = "legacy".to_string();
+        assert!(resolve(&api, Some("legacy")).is_some_and(|grant| grant.allows(ApiScope::Admin)));
+    }
+}
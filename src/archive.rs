@@ -1,3 +1,5 @@
+use crate::partial::PartialFile;
+use crate::shutdown;
 use anyhow::{anyhow, Result};
 use encoding_rs::GBK;
 use filetime::{set_file_times, FileTime};
@@ -45,13 +47,19 @@ pub fn detect_archive_format_from_path(path: &Path) -> ArchiveFormat {
     detect_archive_format(&buf[..n])
 }
 
-/// Extract a ZIP archive from a file path on disk.
-pub fn extract_zip_from_path(zip_path: &Path, format: &str, album_dir: &Path) -> Result<()> {
+/// Extract a ZIP archive from a file path on disk. Returns the paths written.
+pub fn extract_zip_from_path(
+    zip_path: &Path,
+    format: &str,
+    album_dir: &Path,
+) -> Result<Vec<PathBuf>> {
     let file = File::open(zip_path)?;
     let reader = BufReader::new(file);
     let mut archive = ZipArchive::new(reader)?;
+    let mut extracted = Vec::new();
 
     for i in 0..archive.len() {
+        check_abandoned(zip_path)?;
         let mut entry = archive.by_index(i)?;
 
         let file_name_raw = entry.name_raw().to_vec();
@@ -84,35 +92,45 @@ pub fn extract_zip_from_path(zip_path: &Path, format: &str, album_dir: &Path) ->
         }
 
         let zip_last_modified = entry.last_modified();
-        let mut output_file = File::create(&output_path)?;
+        let partial = PartialFile::new(&output_path);
+        let mut output_file = File::create(partial.path())?;
         std::io::copy(&mut entry, &mut output_file)?;
         drop(output_file);
+        partial.commit()?;
 
         if let Some(dt) = zip_last_modified {
             if let Err(e) = set_file_timestamps(&output_path, dt) {
                 warn!("设置文件时间戳失败 {}: {}", output_path.display(), e);
             }
         }
+        extracted.push(output_path);
     }
 
-    Ok(())
+    Ok(extracted)
 }
 
-/// Extract a RAR archive from a file path on disk.
-pub fn extract_rar_from_path(rar_path: &Path, format: &str, album_dir: &Path) -> Result<()> {
+/// Extract a RAR archive from a file path on disk. Returns the paths written.
+pub fn extract_rar_from_path(
+    rar_path: &Path,
+    format: &str,
+    album_dir: &Path,
+) -> Result<Vec<PathBuf>> {
     let archive = Archive::new(rar_path);
     let archive = archive.open_for_processing()?;
-    process_rar_archive(archive, format, album_dir)
+    process_rar_archive(rar_path, archive, format, album_dir)
 }
 
 fn process_rar_archive(
+    rar_path: &Path,
     archive: unrar::OpenArchive<unrar::Process, unrar::CursorBeforeHeader>,
     format: &str,
     album_dir: &Path,
-) -> Result<()> {
+) -> Result<Vec<PathBuf>> {
     let mut next_archive = Some(archive);
+    let mut extracted = Vec::new();
 
     while let Some(archive) = next_archive.take() {
+        check_abandoned(rar_path)?;
         match archive.read_header()? {
             Some(header_archive) => {
                 let entry = header_archive.entry();
@@ -144,13 +162,82 @@ fn process_rar_archive(
                 }
 
                 let (data, archive) = header_archive.read()?;
-                fs::write(&output_path, data)?;
+                let partial = PartialFile::new(&output_path);
+                fs::write(partial.path(), data)?;
+                partial.commit()?;
+                extracted.push(output_path);
                 next_archive = Some(archive);
             }
             None => break,
         }
     }
 
+    Ok(extracted)
+}
+
+/// Extract an archive of `format` into `album_dir` through a staging
+/// directory next to the files, moving the entries into place only after the
+/// last one. A failed or abandoned extraction removes the staged entries, so
+/// `skip_existing` never takes part of an archive for a finished format.
+/// Returns the final paths, or `None` for a payload that is not an archive.
+pub fn extract_staged(
+    archive_path: &Path,
+    format: &str,
+    album_dir: &Path,
+) -> Result<Option<Vec<PathBuf>>> {
+    let extract = match detect_archive_format_from_path(archive_path) {
+        ArchiveFormat::Zip => extract_zip_from_path,
+        ArchiveFormat::Rar => extract_rar_from_path,
+        ArchiveFormat::Unknown => return Ok(None),
+    };
+    let staging = album_dir.join(format!(".dizzysync-extract-{}", format.to_lowercase()));
+    // Left behind when a previous run was killed mid-extraction.
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    let result = extract(archive_path, format, &staging).and_then(|extracted| {
+        check_abandoned(archive_path)?;
+        move_into(&staging, album_dir)?;
+        Ok(Some(
+            extracted
+                .into_iter()
+                .filter_map(|path| Some(album_dir.join(path.strip_prefix(&staging).ok()?)))
+                .collect(),
+        ))
+    });
+    if let Err(e) = fs::remove_dir_all(&staging) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("删除解压临时目录失败 {}: {}", staging.display(), e);
+        }
+    }
+    result
+}
+
+/// Move the contents of `src` into `dest`, merging directories that already
+/// exist and replacing files.
+fn move_into(src: &Path, dest: &Path) -> Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dest.join(entry.file_name());
+        if entry.file_type()?.is_dir() && target.is_dir() {
+            move_into(&entry.path(), &target)?;
+        } else {
+            if target.is_file() {
+                fs::remove_file(&target)?;
+            }
+            fs::rename(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Stop between entries once shutdown gave up on in-flight files; the entry
+/// being written is left as a `.part` file and removed.
+fn check_abandoned(archive_path: &Path) -> Result<()> {
+    if shutdown::abandoned() {
+        return Err(anyhow!("退出时放弃解压 {}", archive_path.display()));
+    }
     Ok(())
 }
 
@@ -0,0 +1,1433 @@
+use crate::album_filter::{self, AlbumFilter, AlbumMatcher};
+use crate::api_control;
+use crate::client::DizzylabClient;
+use crate::config::Config;
+use crate::config_schema;
+use crate::downloader::{AlbumPlan, Downloader, FormatPlan, PlanAction, SyncPlan};
+use crate::exit_status::{self, Failure};
+use crate::local_state;
+use crate::log_files;
+use crate::metrics;
+use crate::shutdown;
+use crate::terminal_progress::SyncProgress;
+use crate::types::{DiscInfo, DiscListItem};
+use anyhow::{anyhow, Result};
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+/// Behaviour overrides shared by every command that reads the album library.
+const OVERRIDE_ARGS: [&str; 6] = [
+    "metadata-only",
+    "skip-existing",
+    "single-threaded",
+    "generate-readme",
+    "generate-nfo",
+    "output-dir",
+];
+
+pub fn command() -> Command {
+    Command::new("DizzySync")
+        .version("0.1.0")
+        .author("去离子水")
+        .about("Dizzylab自动同步器")
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .value_name("FILE")
+                .help("配置文件路径")
+                .default_value("config.toml")
+                .global(true),
+        )
+        .arg(
+            Arg::new("debug")
+                .long("debug")
+                .help("启用调试模式，打印所有HTTP响应")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .args(legacy_args())
+        .args(override_args().map(|arg| arg.hide(true)))
+        .args(serve_args().map(|arg| arg.hide(true)))
+        .args(filter_args().map(|arg| arg.hide(true)))
+        .arg(output_arg().hide(true))
+        .subcommand(
+            Command::new("sync")
+                .about("同步全部专辑，或用 --id、--label 等条件只同步部分专辑")
+                .args(filter_args())
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help(
+                            "不下载，预览将下载、补写标签或跳过的专辑、格式与曲目，并估算下载大小",
+                        )
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(output_arg().requires("dry-run"))
+                .arg(
+                    Arg::new("no-progress")
+                        .long("no-progress")
+                        .help("在终端中也不显示进度条，按行输出日志")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("fail-on")
+                        .long("fail-on")
+                        .value_name("LIST")
+                        .help("哪些结果以非零退出码结束，逗号分隔；未列出的只记录日志并以 0 退出 [默认: config,auth,partial,total]")
+                        .hide_default_value(true)
+                        .value_delimiter(',')
+                        .value_parser(FAIL_ON_VALUES)
+                        .default_values(DEFAULT_FAIL_ON),
+                )
+                .args(override_args()),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("列出账号下的专辑，不下载")
+                .args(filter_args())
+                .arg(output_arg())
+                .args(override_args()),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("启动HTTP API与Web控制服务")
+                .args(serve_args())
+                .args(override_args()),
+        )
+        .subcommand(
+            Command::new("daemon")
+                .about("不启动 HTTP 服务，在前台按 [schedule] 定时同步，收到 SIGTERM 后退出")
+                .arg(
+                    Arg::new("status-file")
+                        .long("status-file")
+                        .value_name("FILE")
+                        .help("定期写入运行状态的 JSON 文件，供外部监控读取")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .args(override_args()),
+        )
+        .subcommand(remote_command())
+        .subcommand(Command::new("init").about("创建默认配置文件"))
+        .subcommand(
+            Command::new("status")
+                .about("按专辑列表缓存离线检查本地曲库完整度")
+                .arg(
+                    Arg::new("refresh")
+                        .long("refresh")
+                        .help("登录 Dizzylab 重新获取专辑列表并更新缓存")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("incomplete")
+                        .long("incomplete")
+                        .help("只显示不完整的专辑")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("label")
+                        .long("label")
+                        .value_name("LABEL")
+                        .help("只显示厂牌名包含该文本的专辑（不区分大小写）")
+                        .value_parser(clap::value_parser!(String)),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("登录并检查本地文件是否完整，不下载")
+                .args(filter_args())
+                .args(override_args()),
+        )
+        .subcommand(
+            Command::new("retag")
+                .about("按 Dizzylab 元数据重写本地音频文件的标签，不下载")
+                .args(filter_args())
+                .args(override_args()),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("配置文件维护")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("check")
+                        .about("校验配置文件（字段、类型、下载格式与 cron 表达式）"),
+                )
+                .subcommand(
+                    Command::new("encrypt").about("使用主密钥加密配置文件中的账号密码与 API key"),
+                ),
+        )
+}
+
+/// `remote`: talk to a running `serve` instance instead of Dizzylab.
+fn remote_command() -> Command {
+    Command::new("remote")
+        .about("通过 HTTP API 控制正在运行的 serve 服务")
+        .subcommand_required(true)
+        .arg(
+            Arg::new("url")
+                .long("url")
+                .value_name("URL")
+                .help("服务地址，例如 http://nas.local:8787 [默认: DIZZYSYNC_URL 或配置文件 [api] bind]")
+                .value_parser(clap::value_parser!(String))
+                .global(true),
+        )
+        .arg(
+            Arg::new("api-key")
+                .long("api-key")
+                .value_name("KEY")
+                .help("API key [默认: 配置文件 [api] api_key 或 DIZZYSYNC_API_KEY]")
+                .value_parser(clap::value_parser!(String))
+                .global(true),
+        )
+        .arg(
+            Arg::new("insecure")
+                .long("insecure")
+                .help("HTTPS 时不校验服务器证书（自签名证书）")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .subcommand(
+            Command::new("status")
+                .about("显示服务登录状态、当前任务、队列与自动同步")
+                .arg(output_arg()),
+        )
+        .subcommand(
+            Command::new("sync")
+                .about("将同步任务加入服务的队列")
+                .arg(
+                    Arg::new("id")
+                        .value_name("ALBUM_ID")
+                        .help("只同步该专辑；省略时同步全部专辑")
+                        .value_parser(clap::value_parser!(String)),
+                )
+                .arg(
+                    Arg::new("account")
+                        .long("account")
+                        .value_name("USERNAME")
+                        .help("只同步该账号的专辑")
+                        .conflicts_with("id")
+                        .value_parser(clap::value_parser!(String)),
+                ),
+        )
+        .subcommand(
+            Command::new("logs")
+                .about("显示服务日志")
+                .arg(
+                    Arg::new("follow")
+                        .short('f')
+                        .long("follow")
+                        .help("持续输出新日志，直到按下 Ctrl+C")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .short('n')
+                        .long("limit")
+                        .value_name("N")
+                        .help("先显示最近的 N 条 [默认: 100]")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("level")
+                        .long("level")
+                        .value_name("LEVEL")
+                        .help("只显示该级别的日志（error、warn、info、debug）")
+                        .value_parser(clap::value_parser!(String)),
+                )
+                .arg(
+                    Arg::new("job")
+                        .long("job")
+                        .value_name("JOB_ID")
+                        .help("只显示该同步任务的日志")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("account")
+                        .long("account")
+                        .value_name("USERNAME")
+                        .help("只显示该账号的日志")
+                        .value_parser(clap::value_parser!(String)),
+                )
+                .arg(
+                    Arg::new("album")
+                        .long("album")
+                        .value_name("ALBUM_ID")
+                        .help("只显示该专辑的日志")
+                        .value_parser(clap::value_parser!(String)),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            Command::new("albums")
+                .about("列出服务缓存的专辑及本地完整度")
+                .arg(
+                    Arg::new("refresh")
+                        .long("refresh")
+                        .help("让服务重新从 Dizzylab 获取专辑列表")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("incomplete")
+                        .long("incomplete")
+                        .help("只显示不完整的专辑")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(output_arg()),
+        )
+}
+
+/// Values of `sync --fail-on`, the names of [`Failure`] categories.
+const FAIL_ON_VALUES: [&str; 5] = ["config", "auth", "partial", "total", "nothing-to-do"];
+const DEFAULT_FAIL_ON: [&str; 4] = ["config", "auth", "partial", "total"];
+
+/// Pre-subcommand flags. They still work but log a pointer to the subcommand
+/// that replaces them.
+fn legacy_args() -> [Arg; 4] {
+    [
+        Arg::new("init")
+            .long("init")
+            .help("已弃用，请使用 init 子命令")
+            .action(ArgAction::SetTrue)
+            .hide(true),
+        Arg::new("check-config")
+            .long("check-config")
+            .help("已弃用，请使用 config check 子命令")
+            .action(ArgAction::SetTrue)
+            .hide(true),
+        Arg::new("dry-run")
+            .long("dry-run")
+            .help("已弃用，请使用 sync --dry-run")
+            .action(ArgAction::SetTrue)
+            .hide(true),
+        Arg::new("api-server")
+            .long("api-server")
+            .help("已弃用，请使用 serve 子命令")
+            .action(ArgAction::SetTrue)
+            .hide(true),
+    ]
+}
+
+/// Album selection shared by the commands that walk the purchase list.
+const FILTER_ARGS: [&str; 7] = [
+    "id",
+    "ids-file",
+    "label",
+    "tag",
+    "released-after",
+    "released-before",
+    "title",
+];
+
+fn filter_args() -> [Arg; 7] {
+    [
+        Arg::new("id")
+            .long("id")
+            .value_name("ALBUM_ID")
+            .help("仅处理指定ID的专辑（例如：dts），可重复或用逗号分隔")
+            .action(ArgAction::Append)
+            .value_delimiter(',')
+            .value_parser(clap::value_parser!(String)),
+        Arg::new("ids-file")
+            .long("ids-file")
+            .value_name("FILE")
+            .help("从文件读取专辑ID，每行一个，忽略空行与 # 注释；- 表示标准输入")
+            .value_parser(clap::value_parser!(PathBuf)),
+        Arg::new("label")
+            .long("label")
+            .value_name("LABEL")
+            .help("只处理厂牌名包含该文本的专辑（不区分大小写），可重复")
+            .action(ArgAction::Append)
+            .value_parser(clap::value_parser!(String)),
+        Arg::new("tag")
+            .long("tag")
+            .value_name("TAG")
+            .help("只处理带有该标签的专辑（不区分大小写），可重复")
+            .action(ArgAction::Append)
+            .value_parser(clap::value_parser!(String)),
+        Arg::new("released-after")
+            .long("released-after")
+            .value_name("YYYY-MM-DD")
+            .help("只处理在该日期当天或之后发行的专辑")
+            .value_parser(album_filter::parse_day),
+        Arg::new("released-before")
+            .long("released-before")
+            .value_name("YYYY-MM-DD")
+            .help("只处理在该日期当天或之前发行的专辑")
+            .value_parser(album_filter::parse_day),
+        Arg::new("title")
+            .long("title")
+            .value_name("REGEX")
+            .help("只处理标题匹配该正则表达式的专辑")
+            .value_parser(|value: &str| {
+                regex::Regex::new(value)
+                    .map(|_| value.to_string())
+                    .map_err(|e| e.to_string())
+            }),
+    ]
+}
+
+fn override_args() -> [Arg; 6] {
+    [
+        Arg::new("metadata-only")
+            .long("metadata-only")
+            .help("仅下载元数据（专辑信息、封面、README、NFO），不下载音频文件")
+            .action(ArgAction::SetTrue),
+        bool_arg("skip-existing", "跳过已存在的目录 [默认: true]"),
+        bool_arg("single-threaded", "单线程模式 [默认: true]"),
+        bool_arg("generate-readme", "生成README.md文件 [默认: true]"),
+        bool_arg("generate-nfo", "生成NFO文件 [默认: true]"),
+        Arg::new("output-dir")
+            .long("output-dir")
+            .short('o')
+            .value_name("DIR")
+            .help("指定输出目录")
+            .value_parser(clap::value_parser!(String)),
+    ]
+}
+
+fn bool_arg(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name)
+        .long(name)
+        .value_name("[BOOL]")
+        .help(help)
+        .num_args(0..=1)
+        .default_missing_value("true")
+        .value_parser(clap::value_parser!(bool))
+}
+
+fn serve_args() -> [Arg; 3] {
+    [
+        Arg::new("api-bind")
+            .long("bind")
+            .alias("api-bind")
+            .value_name("ADDR")
+            .help("API/Web服务监听地址，例如 0.0.0.0:8787")
+            .value_parser(clap::value_parser!(String)),
+        Arg::new("api-key")
+            .long("api-key")
+            .value_name("KEY")
+            .help("API访问密钥；设置后请求需携带 X-API-Key 或 Bearer Token")
+            .value_parser(clap::value_parser!(String)),
+        Arg::new("web-root")
+            .long("web-root")
+            .value_name("DIR")
+            .help("静态Web前端目录")
+            .value_parser(clap::value_parser!(String)),
+    ]
+}
+
+/// Run the old flat-flag interface by mapping it onto the subcommands.
+pub async fn run_legacy(config_path: &str, matches: &ArgMatches) -> Result<()> {
+    if matches.get_flag("init") {
+        warn!("--init 已弃用，请使用 `dizzysync init`");
+        return Config::create_default_config(config_path);
+    }
+    if matches.get_flag("check-config") {
+        warn!("--check-config 已弃用，请使用 `dizzysync config check`");
+        return check_config(config_path);
+    }
+    if matches.get_flag("api-server") {
+        warn!("--api-server 已弃用，请使用 `dizzysync serve`");
+        return serve(config_path, matches).await;
+    }
+    if matches.get_flag("dry-run") {
+        warn!("--dry-run 已弃用，请使用 `dizzysync sync --dry-run`");
+        return plan(config_path, matches).await;
+    }
+    let used_flags = OVERRIDE_ARGS
+        .iter()
+        .chain(FILTER_ARGS.iter())
+        .chain(["api-bind", "api-key", "web-root"].iter())
+        .any(|id| matches.value_source(id) == Some(ValueSource::CommandLine));
+    if used_flags {
+        warn!("顶层同步参数已弃用，请使用 `dizzysync sync [参数]`");
+    }
+    sync(config_path, matches).await
+}
+
+pub fn check_config(config_path: &str) -> Result<()> {
+    let content = std::fs::read_to_string(config_path)
+        .map_err(|e| Failure::Config.error(format!("无法读取配置文件 {config_path}: {e}")))?;
+    let issues = config_schema::check(&content);
+    if issues.is_empty() {
+        info!("配置文件校验通过: {}", config_path);
+        return Ok(());
+    }
+    for issue in &issues {
+        error!("{}: {}", config_path, issue);
+    }
+    Err(Failure::Config.error(format!(
+        "配置文件 {} 存在 {} 个问题",
+        config_path,
+        issues.len()
+    )))
+}
+
+/// Read an existing config file; a missing or broken file is a config failure.
+fn read_config(config_path: &str) -> Result<Config> {
+    if !Path::new(config_path).exists() {
+        return Err(Failure::Config.error(format!(
+            "配置文件不存在: {config_path}，请运行 'dizzysync init' 创建默认配置文件"
+        )));
+    }
+    Config::load_from_file(config_path)
+        .map_err(|e| Failure::Config.error(format!("无法加载配置文件 {config_path}: {e:#}")))
+}
+
+/// Load the config for a command that talks to Dizzylab and apply the command
+/// line overrides, then the environment, in the same order as `serve`.
+fn load_config(config_path: &str, matches: &ArgMatches) -> Result<Config> {
+    let mut config = read_config(config_path)?;
+    apply_overrides(&mut config, matches);
+    apply_env(&mut config)?;
+    configure_logging(&config, config_path);
+    Ok(config)
+}
+
+/// Apply the `DIZZYSYNC_*` environment; a malformed override is a config failure.
+fn apply_env(config: &mut Config) -> Result<()> {
+    config
+        .apply_env_overrides(true)
+        .map_err(|e| Failure::Config.error(format!("环境变量配置无效: {e:#}")))
+}
+
+fn configure_logging(config: &Config, config_path: &str) {
+    if let Err(e) = log_files::configure(&config.logging, config_path) {
+        error!("无法启用日志文件: {}", e);
+    }
+}
+
+fn apply_overrides(config: &mut Config, matches: &ArgMatches) {
+    if matches.get_flag("debug") {
+        config.behavior.debug = true;
+    }
+
+    if matches.get_flag("metadata-only") {
+        config.behavior.metadata_only = true;
+        info!("启用仅元数据模式：只下载专辑信息，不下载音频文件");
+    }
+
+    if let Some(skip_existing) = matches.get_one::<bool>("skip-existing") {
+        config.behavior.skip_existing = *skip_existing;
+        info!("设置跳过已存在目录: {}", skip_existing);
+    }
+
+    if let Some(single_threaded) = matches.get_one::<bool>("single-threaded") {
+        config.behavior.single_threaded = *single_threaded;
+        info!("设置单线程模式: {}", single_threaded);
+    }
+
+    if let Some(generate_readme) = matches.get_one::<bool>("generate-readme") {
+        config.behavior.generate_readme = *generate_readme;
+        info!("设置README.md生成: {}", generate_readme);
+    }
+
+    if let Some(generate_nfo) = matches.get_one::<bool>("generate-nfo") {
+        config.behavior.generate_nfo = *generate_nfo;
+        info!("设置NFO文件生成: {}", generate_nfo);
+    }
+
+    if let Some(output_dir) = matches.get_one::<String>("output-dir") {
+        if std::env::var("DIZZYSYNC_OUTPUT_DIR").is_ok() {
+            info!("已设置 DIZZYSYNC_OUTPUT_DIR，忽略 --output-dir 参数");
+        } else {
+            config.paths.output_dir = PathBuf::from(output_dir);
+            info!("设置输出目录: {}", output_dir);
+        }
+    }
+}
+
+pub async fn serve(config_path: &str, matches: &ArgMatches) -> Result<()> {
+    let mut config = Config::load_or_bootstrap(config_path)
+        .map_err(|e| Failure::Config.error(format!("无法加载配置文件 {config_path}: {e:#}")))?;
+    configure_logging(&config, config_path);
+    apply_overrides(&mut config, matches);
+
+    if let Some(api_bind) = matches.get_one::<String>("api-bind") {
+        config.api.bind = api_bind.clone();
+        info!("设置API/Web监听地址: {}", api_bind);
+    }
+
+    if let Some(api_key) = matches.get_one::<String>("api-key") {
+        config.api.api_key = api_key.clone();
+        info!("已设置API访问密钥");
+    }
+
+    if let Some(web_root) = matches.get_one::<String>("web-root") {
+        config.api.web_root = PathBuf::from(web_root);
+        info!("设置Web前端目录: {}", web_root);
+    }
+
+    config.apply_env_overrides(true)?;
+    config.save_to_file(config_path)?;
+    api_control::run(api_control::ApiServerOptions {
+        config_path: config_path.to_string(),
+        config,
+    })
+    .await
+}
+
+pub async fn daemon(config_path: &str, matches: &ArgMatches) -> Result<()> {
+    let config = load_config(config_path, matches)?;
+
+    api_control::run_daemon(api_control::DaemonOptions {
+        config_path: config_path.to_string(),
+        config,
+        status_file: matches.get_one::<PathBuf>("status-file").cloned(),
+    })
+    .await
+}
+
+/// Library completeness from the album list and track lists cached by the API
+/// server, or a fresh fetch with `--refresh`. Albums with a cached track list
+/// are checked track by track; without `--refresh` nothing touches the network.
+pub async fn status(config_path: &str, matches: &ArgMatches) -> Result<()> {
+    let mut config = read_config(config_path)?;
+    apply_env(&mut config)?;
+    configure_logging(&config, config_path);
+
+    let mut albums = if matches.get_flag("refresh") {
+        ready_to_login(&config)?;
+        let albums = api_control::fetch_album_details(config_path, &config).await?;
+        info!("已从 Dizzylab 获取 {} 张已购专辑及其曲目列表", albums.len());
+        albums
+    } else {
+        let Some((updated_at, albums)) = api_control::latest_album_cache(config_path) else {
+            return Err(anyhow!(
+                "没有找到专辑列表缓存，请使用 `dizzysync status --refresh` 从 Dizzylab 获取"
+            ));
+        };
+        let updated_at = chrono::DateTime::from_timestamp(updated_at as i64, 0)
+            .map(|time| {
+                time.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        info!(
+            "使用 {} 缓存的专辑列表（{} 张），--refresh 可重新获取",
+            updated_at,
+            albums.len()
+        );
+        albums
+    };
+    let mut without_details = 0usize;
+    local_state::annotate_album_list_with_details(&config, &mut albums, |id| {
+        let disc = api_control::cached_disc_info(config_path, id);
+        without_details += usize::from(disc.is_none());
+        disc
+    });
+    if without_details > 0 {
+        warn!(
+            "{} 张专辑没有缓存的曲目列表，只能按文件数量估算，--refresh 可获取",
+            without_details
+        );
+    }
+
+    let label = matches
+        .get_one::<String>("label")
+        .map(|label| label.to_lowercase());
+    let incomplete_only = matches.get_flag("incomplete");
+    albums.retain(|album| {
+        let complete = album.local.as_ref().is_some_and(|local| local.complete);
+        !(incomplete_only && complete)
+            && label
+                .as_ref()
+                .map_or(true, |label| album.label.to_lowercase().contains(label))
+    });
+
+    let format = output_format(matches);
+    if format != OutputFormat::Text {
+        let mut output = AlbumOutput::new(format);
+        for album in &albums {
+            output.push(None, album)?;
+        }
+        return output.finish();
+    }
+
+    let mut complete = 0usize;
+    for album in &albums {
+        let Some(local) = &album.local else {
+            continue;
+        };
+        // With a track list, a track missing any configured format counts as missing.
+        let missing = if local.missing_tracks.is_empty() {
+            local
+                .expected_tracks
+                .saturating_sub(local.downloaded_tracks)
+        } else {
+            local.missing_tracks.len()
+        };
+        let present = if local.expected_tracks > 0 {
+            local.expected_tracks.saturating_sub(missing)
+        } else {
+            local.downloaded_tracks
+        };
+        let expected = match local.expected_tracks {
+            0 => "?".to_string(),
+            count => count.to_string(),
+        };
+        if local.complete {
+            complete += 1;
+            println!(
+                "完整    {} - {} ({})  {}/{} 首",
+                album.title, album.label, album.id, present, expected
+            );
+            continue;
+        }
+        let mut problems = Vec::new();
+        if !local.directory_exists {
+            problems.push("未下载".to_string());
+        } else {
+            if !local.missing_formats.is_empty() {
+                problems.push(format!("缺少格式 [{}]", local.missing_formats.join(", ")));
+            }
+            if local.expected_tracks == 0 {
+                problems.push("曲目数未知".to_string());
+            } else if missing > 0 {
+                problems.push(format!("缺少 {} 首", missing));
+            }
+        }
+        if local.gift_missing {
+            problems.push("缺少特典".to_string());
+        }
+        println!(
+            "不完整  {} - {} ({})  {}/{} 首  {}",
+            album.title,
+            album.label,
+            album.id,
+            present,
+            expected,
+            problems.join(", ")
+        );
+        for track in &local.missing_tracks {
+            println!("        {}", track);
+        }
+    }
+    println!(
+        "共 {} 张专辑：{} 张完整，{} 张不完整（输出目录 {}）",
+        albums.len(),
+        complete,
+        albums.len() - complete,
+        config.paths.output_dir.display()
+    );
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Ndjson,
+}
+
+fn output_arg() -> Arg {
+    Arg::new("output")
+        .long("output")
+        .value_name("FORMAT")
+        .help("专辑列表的输出格式：text、json（单个数组）或 ndjson（每行一个专辑）")
+        .value_parser(["text", "json", "ndjson"])
+        .default_value("text")
+}
+
+pub fn output_format(matches: &ArgMatches) -> OutputFormat {
+    match matches.get_one::<String>("output").map(String::as_str) {
+        Some("json") => OutputFormat::Json,
+        Some("ndjson") => OutputFormat::Ndjson,
+        _ => OutputFormat::Text,
+    }
+}
+
+/// Whether stdout carries JSON, in which case logs must go to stderr.
+pub fn writes_machine_output(matches: &ArgMatches) -> bool {
+    let matches = match matches.subcommand() {
+        Some(("list" | "sync" | "status", sub)) => sub,
+        Some(("remote", remote)) => match remote.subcommand() {
+            Some(("status" | "logs" | "albums", sub)) => sub,
+            _ => return false,
+        },
+        Some(_) => return false,
+        None => matches,
+    };
+    output_format(matches) != OutputFormat::Text
+}
+
+/// Whether `sync` may replace its log lines with progress bars; only when
+/// stdout turns out to be a terminal.
+pub fn wants_progress(matches: &ArgMatches) -> bool {
+    match matches.subcommand() {
+        Some(("sync", sub)) => !sub.get_flag("dry-run") && !sub.get_flag("no-progress"),
+        _ => false,
+    }
+}
+
+/// One listed album: the album's own fields plus the account that owns it.
+/// `local` carries the computed directory and its completeness.
+#[derive(Serialize)]
+struct ListedAlbum<'a, T: Serialize> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account: Option<&'a str>,
+    #[serde(flatten)]
+    album: &'a T,
+}
+
+/// Collects albums for `--output json`, or prints them as they arrive for ndjson.
+struct AlbumOutput {
+    format: OutputFormat,
+    albums: Vec<serde_json::Value>,
+}
+
+impl AlbumOutput {
+    fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            albums: Vec::new(),
+        }
+    }
+
+    fn push<T: Serialize>(&mut self, account: Option<&str>, album: &T) -> Result<()> {
+        let value = serde_json::to_value(ListedAlbum { account, album })?;
+        match self.format {
+            OutputFormat::Ndjson => println!("{}", serde_json::to_string(&value)?),
+            _ => self.albums.push(value),
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        if self.format == OutputFormat::Json {
+            println!("{}", serde_json::to_string_pretty(&self.albums)?);
+        }
+        Ok(())
+    }
+}
+
+struct Session {
+    label: String,
+    client: DizzylabClient,
+    token: String,
+}
+
+/// Log in every configured account, recording the ones that fail. Fails when
+/// none of them could log in.
+async fn login_sessions(config: &Config, failures: &mut Vec<String>) -> Result<Vec<Session>> {
+    let mut sessions = Vec::new();
+    for account in config.accounts() {
+        let label = if account.username.trim().is_empty() {
+            "<empty>".to_string()
+        } else {
+            account.username.clone()
+        };
+        info!("账号 {} 登录中", label);
+        let client = DizzylabClient::new(config.behavior.debug)?;
+        let token = match client.login(&account.username, &account.password).await {
+            Ok(t) => t,
+            Err(e) => {
+                error!("账号 {} 登录失败: {}", label, e);
+                failures.push(format!("{label}: {e}"));
+                continue;
+            }
+        };
+
+        if let Ok(user_info) = client.get_my_info(&token).await {
+            info!(
+                "账号 {} 已登录为: {} (UID: {})",
+                label, user_info.username, user_info.uid
+            );
+        }
+        sessions.push(Session {
+            label,
+            client,
+            token,
+        });
+    }
+    if sessions.is_empty() && !failures.is_empty() {
+        return Err(Failure::Auth.error(failures.join("; ")));
+    }
+    Ok(sessions)
+}
+
+/// Validate the loaded config before logging in.
+fn ready_to_login(config: &Config) -> Result<()> {
+    api_control::validate_credentials(config).map_err(|e| Failure::Config.error(e.to_string()))?;
+    api_control::validate_formats(config)
+        .map_err(|e| Failure::Config.error(format!("{e}，请在配置文件中只保留其中一个")))
+}
+
+async fn requested_album(session: &Session, album_id: &str) -> Option<DiscInfo> {
+    info!("账号 {} 获取指定专辑: {}", session.label, album_id);
+    match session.client.get_disc_info(album_id, &session.token).await {
+        Ok(disc_info) => Some(disc_info),
+        Err(e) => {
+            info!(
+                "账号 {} 未找到或无法访问专辑 {}: {}",
+                session.label, album_id, e
+            );
+            None
+        }
+    }
+}
+
+async fn account_albums(session: &Session, failures: &mut Vec<String>) -> Vec<DiscListItem> {
+    match session.client.get_my_discs(&session.token).await {
+        Ok(albums) => {
+            if albums.is_empty() {
+                info!("账号 {} 没有找到任何专辑", session.label);
+            } else {
+                info!("账号 {} 找到 {} 个专辑", session.label, albums.len());
+            }
+            albums
+        }
+        Err(e) => {
+            failures.push(format!("{}: {e}", session.label));
+            Vec::new()
+        }
+    }
+}
+
+/// The album filter options of a command, with the album IDs that some
+/// account could see, matched or not.
+struct Selection {
+    config_path: String,
+    matcher: AlbumMatcher,
+    found: HashSet<String>,
+}
+
+impl Selection {
+    fn new(config_path: &str, matches: &ArgMatches) -> Result<Self> {
+        let matcher = album_filter(matches)?.compile()?;
+        if !matcher.filter().is_empty() {
+            info!("专辑筛选条件: {}", matcher.filter().describe());
+        }
+        Ok(Self {
+            config_path: config_path.to_string(),
+            matcher,
+            found: HashSet::new(),
+        })
+    }
+
+    /// Albums requested by ID alone are fetched directly, so albums missing
+    /// from the purchase list can still be reached. `None` for other filters.
+    async fn requested_albums(&mut self, session: &Session) -> Option<Vec<DiscInfo>> {
+        if !self.matcher.filter().only_ids() {
+            return None;
+        }
+        let mut discs = Vec::new();
+        for album_id in &self.matcher.filter().ids {
+            if let Some(disc_info) = requested_album(session, album_id).await {
+                self.found.insert(album_id.clone());
+                discs.push(disc_info);
+            }
+        }
+        Some(discs)
+    }
+
+    /// The albums of the account's purchase list that match the filter.
+    async fn albums(&mut self, session: &Session, failures: &mut Vec<String>) -> Vec<DiscListItem> {
+        let mut albums = account_albums(session, failures).await;
+        self.found
+            .extend(albums.iter().map(|album| album.id.clone()));
+        if self.matcher.filter().is_empty() || albums.is_empty() {
+            return albums;
+        }
+        if self.matcher.filter().has_date_bounds() {
+            api_control::fill_release_dates(
+                &self.config_path,
+                &session.client,
+                &session.token,
+                &mut albums,
+            )
+            .await;
+        }
+        let total = albums.len();
+        let albums = self.matcher.select(albums);
+        info!(
+            "账号 {} 的 {} 个专辑中有 {} 个匹配筛选条件",
+            session.label,
+            total,
+            albums.len()
+        );
+        albums
+    }
+
+    /// Full album details of the selected albums.
+    async fn discs(&mut self, session: &Session, failures: &mut Vec<String>) -> Vec<DiscInfo> {
+        if let Some(discs) = self.requested_albums(session).await {
+            return discs;
+        }
+        let mut discs = Vec::new();
+        for album in self.albums(session, failures).await {
+            match session
+                .client
+                .get_disc_info(&album.id, &session.token)
+                .await
+            {
+                Ok(disc_info) => discs.push(disc_info),
+                Err(e) => failures.push(format!(
+                    "{}: 获取专辑 {} 详情失败: {e}",
+                    session.label, album.id
+                )),
+            }
+        }
+        discs
+    }
+
+    /// Requested IDs that no account could access count as failures.
+    fn report_missing(&self, failures: &mut Vec<String>) {
+        for album_id in &self.matcher.filter().ids {
+            if !self.found.contains(album_id) {
+                failures.push(format!("所有账号均未找到或无法访问专辑 {album_id}"));
+            }
+        }
+    }
+}
+
+/// The album filter given on the command line, including `--ids-file`.
+fn album_filter(matches: &ArgMatches) -> Result<AlbumFilter> {
+    let values = |id: &str| -> Vec<String> {
+        match matches.try_get_many::<String>(id) {
+            Ok(Some(values)) => values.cloned().collect(),
+            _ => Vec::new(),
+        }
+    };
+    let day = |id: &str| matches.try_get_one(id).ok().flatten().copied();
+    let mut ids = values("id");
+    if let Ok(Some(path)) = matches.try_get_one::<PathBuf>("ids-file") {
+        ids.extend(read_ids_file(path)?);
+    }
+    AlbumFilter {
+        ids,
+        labels: values("label"),
+        tags: values("tag"),
+        released_after: day("released-after"),
+        released_before: day("released-before"),
+        title: matches
+            .try_get_one::<String>("title")
+            .ok()
+            .flatten()
+            .cloned(),
+    }
+    .normalized()
+    .map_err(|e| Failure::Config.error(e.to_string()))
+}
+
+/// One album ID per line; blank lines and `#` comments are skipped.
+fn read_ids_file(path: &Path) -> Result<Vec<String>> {
+    let content = if path == Path::new("-") {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| anyhow!("无法从标准输入读取专辑ID: {e}"))?;
+        content
+    } else {
+        std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("无法读取专辑ID文件 {}: {e}", path.display()))?
+    };
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+fn finish(selection: &Selection, mut failures: Vec<String>) -> Result<()> {
+    selection.report_missing(&mut failures);
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(failures.join("; ")))
+    }
+}
+
+/// Categorize the end of a sync for its exit code: nothing failed but nothing
+/// was downloaded either, some albums failed, or nothing could be synced.
+/// `album_errors` of the `failures` are per-account summaries of failed
+/// albums, which are counted one by one instead. Albums with failed tracks or
+/// formats are failures; albums that were already complete are successes.
+fn finish_sync(
+    selection: &Selection,
+    mut failures: Vec<String>,
+    album_errors: usize,
+) -> Result<()> {
+    selection.report_missing(&mut failures);
+    let other_failures = failures.len().saturating_sub(album_errors) as u64;
+    let failure = exit_status::classify_sync(
+        metrics::albums_failed() + other_failures,
+        metrics::albums_synced(),
+        metrics::downloaded_bytes(),
+    );
+    match failure {
+        None => Ok(()),
+        Some(Failure::NothingToDo) => Err(Failure::NothingToDo.error("没有需要下载的新文件")),
+        Some(failure) => Err(failure.error(failures.join("; "))),
+    }
+}
+
+/// `sync --fail-on`: categorized failures that are not listed are only
+/// logged, and the command exits with 0.
+fn apply_fail_on(matches: &ArgMatches, result: Result<()>) -> Result<()> {
+    let Err(e) = result else {
+        return Ok(());
+    };
+    let Some(failure) = exit_status::failure(&e) else {
+        return Err(e);
+    };
+    // Legacy top-level syncs have no `--fail-on` and use the default list.
+    let listed = match matches.try_get_many::<String>("fail-on") {
+        Ok(Some(mut values)) => values.any(|value| value == failure.name()),
+        _ => DEFAULT_FAIL_ON.contains(&failure.name()),
+    };
+    if listed {
+        return Err(e);
+    }
+    match failure {
+        Failure::NothingToDo => info!("{}", e),
+        _ => warn!("{}（--fail-on 未包含 {}，退出码为 0）", e, failure.name()),
+    }
+    Ok(())
+}
+
+pub async fn sync(config_path: &str, matches: &ArgMatches) -> Result<()> {
+    let result = run_sync(config_path, matches).await;
+    apply_fail_on(matches, result)
+}
+
+async fn run_sync(config_path: &str, matches: &ArgMatches) -> Result<()> {
+    let config = load_config(config_path, matches)?;
+    ready_to_login(&config)?;
+
+    let mut selection = Selection::new(config_path, matches)?;
+    let progress = SyncProgress::start();
+    let work = async {
+        let mut failures = Vec::new();
+        // Failures that the album counters already cover.
+        let mut album_errors = 0usize;
+        for session in login_sessions(&config, &mut failures).await? {
+            let downloader = Downloader::new(
+                session.client.clone(),
+                config.clone(),
+                session.token.clone(),
+            );
+            if let Some(discs) = selection.requested_albums(&session).await {
+                if let Some(progress) = &progress {
+                    progress.add_albums(discs.len());
+                }
+                for disc_info in discs {
+                    if let Err(e) = downloader.download_album(&disc_info).await {
+                        failures.push(format!("{}: {e}", session.label));
+                        album_errors += 1;
+                    }
+                }
+                continue;
+            }
+
+            let albums = selection.albums(&session, &mut failures).await;
+            if albums.is_empty() {
+                continue;
+            }
+            if let Some(progress) = &progress {
+                progress.add_albums(albums.len());
+            }
+            if let Err(e) = downloader.sync_all_albums(albums).await {
+                failures.push(format!("{}: {e}", session.label));
+                album_errors += 1;
+            }
+        }
+        Ok::<_, anyhow::Error>((failures, album_errors))
+    };
+    let outcome = shutdown::interruptible(work).await;
+    if let Some(progress) = progress {
+        progress.finish().await;
+    }
+    let Some(outcome) = outcome else {
+        return Err(anyhow!(
+            "同步已中断，未完成的文件已删除，下次同步时重新下载"
+        ));
+    };
+    let (failures, album_errors) = outcome?;
+    finish_sync(&selection, failures, album_errors)
+}
+
+/// `sync --dry-run`: what a sync would download, re-tag or skip, with sizes
+/// from HEAD requests. Nothing is written.
+pub async fn plan(config_path: &str, matches: &ArgMatches) -> Result<()> {
+    let config = load_config(config_path, matches)?;
+    ready_to_login(&config)?;
+
+    let mut selection = Selection::new(config_path, matches)?;
+    let format = output_format(matches);
+    let mut plan = SyncPlan::default();
+    let mut failures = Vec::new();
+    let mut seen = HashSet::new();
+    for session in login_sessions(&config, &mut failures).await? {
+        let downloader = Downloader::new(
+            session.client.clone(),
+            config.clone(),
+            session.token.clone(),
+        );
+        for disc_info in selection.discs(&session, &mut failures).await {
+            // Albums owned by several accounts are only synced once.
+            if !seen.insert(disc_info.id.clone()) {
+                continue;
+            }
+            info!("计算同步计划: {} - {}", disc_info.title, disc_info.label);
+            let album = downloader.plan_album(&session.label, &disc_info).await;
+            match format {
+                OutputFormat::Text => print_album_plan(&album),
+                OutputFormat::Ndjson => println!("{}", serde_json::to_string(&album)?),
+                OutputFormat::Json => {}
+            }
+            plan.push(album);
+        }
+    }
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&plan)?),
+        OutputFormat::Ndjson => {}
+        OutputFormat::Text => {
+            let unknown = if plan.unknown_sizes > 0 {
+                format!("，另有 {} 个文件大小未知", plan.unknown_sizes)
+            } else {
+                String::new()
+            };
+            println!(
+                "同步计划：下载 {} 张专辑（{} 个文件，约 {}{}），{} 张只补写标签（{} 个文件），{} 张跳过",
+                plan.download_albums,
+                plan.download_files,
+                format_bytes(plan.bytes),
+                unknown,
+                plan.retag_albums,
+                plan.retag_files,
+                plan.skip_albums
+            );
+        }
+    }
+    finish(&selection, failures)
+}
+
+fn print_album_plan(album: &AlbumPlan) {
+    let size = if album.action == PlanAction::Download {
+        format!("  约 {}", format_bytes(album.bytes))
+    } else {
+        String::new()
+    };
+    println!(
+        "[{}] {}  {} - {} ({}){}",
+        album.account,
+        action_label(album.action),
+        album.title,
+        album.label,
+        album.id,
+        size
+    );
+    if album.action == PlanAction::Skip {
+        return;
+    }
+    for format in &album.formats {
+        println!(
+            "        {:<5} {}",
+            format.format,
+            format_plan_summary(format)
+        );
+    }
+}
+
+fn format_plan_summary(format: &FormatPlan) -> String {
+    if let Some(reason) = &format.reason {
+        let reason = match reason.as_str() {
+            "exists" => "本地已存在",
+            "unavailable" => "没有特典",
+            "metadata_only" => "仅元数据模式",
+            "no_tracks" => "没有曲目信息",
+            other => other,
+        };
+        return format!("跳过（{}）", reason);
+    }
+    let count = |action| {
+        format
+            .files
+            .iter()
+            .filter(|file| file.action == action)
+            .count()
+    };
+    let mut parts = Vec::new();
+    let downloads = count(PlanAction::Download);
+    if downloads > 0 {
+        let bytes = format.files.iter().filter_map(|file| file.bytes).sum();
+        parts.push(format!(
+            "下载 {} 个文件（约 {}）",
+            downloads,
+            format_bytes(bytes)
+        ));
+    }
+    let retags = count(PlanAction::Retag);
+    if retags > 0 {
+        parts.push(format!("补写标签 {} 个", retags));
+    }
+    let skips = count(PlanAction::Skip);
+    if skips > 0 {
+        parts.push(format!("跳过 {} 个", skips));
+    }
+    parts.join("，")
+}
+
+fn action_label(action: PlanAction) -> &'static str {
+    match action {
+        PlanAction::Download => "下载",
+        PlanAction::Retag => "补标签",
+        PlanAction::Skip => "跳过",
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+pub async fn list(config_path: &str, matches: &ArgMatches) -> Result<()> {
+    let config = load_config(config_path, matches)?;
+    ready_to_login(&config)?;
+
+    let mut selection = Selection::new(config_path, matches)?;
+    let mut output = AlbumOutput::new(output_format(matches));
+    let mut failures = Vec::new();
+    for session in login_sessions(&config, &mut failures).await? {
+        if let Some(discs) = selection.requested_albums(&session).await {
+            for (index, mut disc_info) in discs.into_iter().enumerate() {
+                if output.format == OutputFormat::Text {
+                    println!(
+                        "[{}] {:3}. {} - {} ({})",
+                        session.label,
+                        index + 1,
+                        disc_info.title,
+                        disc_info.label,
+                        disc_info.id
+                    );
+                } else {
+                    local_state::annotate_disc_info(&config, &mut disc_info);
+                    output.push(Some(&session.label), &disc_info)?;
+                }
+            }
+            continue;
+        }
+
+        let mut albums = selection.albums(&session, &mut failures).await;
+        if albums.is_empty() {
+            continue;
+        }
+        if output.format != OutputFormat::Text {
+            local_state::annotate_album_list(&config, &mut albums);
+            for album in &albums {
+                output.push(Some(&session.label), album)?;
+            }
+            continue;
+        }
+        info!("=== 账号 {} 专辑列表 ===", session.label);
+        for (index, album) in albums.iter().enumerate() {
+            println!(
+                "[{}] {:3}. {} - {} ({})",
+                session.label,
+                index + 1,
+                album.title,
+                album.label,
+                album.id
+            );
+        }
+    }
+    output.finish()?;
+    finish(&selection, failures)
+}
+
+/// Compare the local files of each album against its Dizzylab track list.
+/// Incomplete albums make the command fail.
+pub async fn verify(config_path: &str, matches: &ArgMatches) -> Result<()> {
+    let config = load_config(config_path, matches)?;
+    ready_to_login(&config)?;
+
+    let mut selection = Selection::new(config_path, matches)?;
+    let mut failures = Vec::new();
+    let mut checked = 0usize;
+    let mut incomplete = 0usize;
+    for session in login_sessions(&config, &mut failures).await? {
+        for mut disc_info in selection.discs(&session, &mut failures).await {
+            checked += 1;
+            local_state::annotate_disc_info(&config, &mut disc_info);
+            let Some(local) = &disc_info.local else {
+                continue;
+            };
+            if local.complete {
+                continue;
+            }
+            incomplete += 1;
+            println!(
+                "[{}] {} ({}) 不完整: {}/{} 首, 缺少格式 [{}]{}",
+                session.label,
+                disc_info.title,
+                disc_info.id,
+                local.complete_tracks,
+                local.expected_tracks,
+                local.missing_formats.join(", "),
+                if local.gift_missing {
+                    ", 缺少特典"
+                } else {
+                    ""
+                }
+            );
+            for track in &local.missing_tracks {
+                println!("    {}", track);
+            }
+        }
+    }
+
+    info!("已检查 {} 个专辑，{} 个不完整", checked, incomplete);
+    if incomplete > 0 {
+        failures.push(format!("{} 个专辑本地文件不完整", incomplete));
+    }
+    finish(&selection, failures)
+}
+
+/// Rewrite tags of files already on disk; nothing is downloaded.
+pub async fn retag(config_path: &str, matches: &ArgMatches) -> Result<()> {
+    let mut config = load_config(config_path, matches)?;
+    ready_to_login(&config)?;
+    // Tag FLAC files even when they already carry a Dizzylab tag.
+    config.behavior.skip_existing = false;
+
+    let mut selection = Selection::new(config_path, matches)?;
+    let mut failures = Vec::new();
+    let mut tagged = 0usize;
+    for session in login_sessions(&config, &mut failures).await? {
+        let downloader = Downloader::new(
+            session.client.clone(),
+            config.clone(),
+            session.token.clone(),
+        );
+        for disc_info in selection.discs(&session, &mut failures).await {
+            let count = downloader.retag_album(&disc_info);
+            if count > 0 {
+                info!("已重写 {} 个MP3文件的标签 - {}", count, disc_info.title);
+            }
+            tagged += count;
+        }
+    }
+
+    info!("标签重写完成，共 {} 个MP3文件", tagged);
+    finish(&selection, failures)
+}
@@ -37,7 +37,7 @@ impl DizzylabClient {
         info!("获取用户信息...");
 
         let url = format!("https://www.dizzylab.net/apis/getmyinfo/?token={token}");
-        let response = self.client.get(&url).send().await?;
+        let response = self.send("getmyinfo", self.client.get(&url)).await?;
         let text = self.log_response_text(response, "getmyinfo").await?;
 
         let parsed: MyInfoResponse = serde_json::from_str(&text)?;
@@ -74,7 +74,7 @@ impl DizzylabClient {
                 offset + PAGE_SIZE
             );
 
-            let response = self.client.get(&url).send().await?;
+            let response = self.send("getmydisc", self.client.get(&url)).await?;
             let text = self
                 .log_response_text(response, &format!("getmydisc offset={offset}"))
                 .await?;
@@ -100,7 +100,7 @@ impl DizzylabClient {
 
         let url =
             format!("https://www.dizzylab.net/apis/getthisdicsinfo/?discid={discid}&token={token}");
-        let response = self.client.get(&url).send().await?;
+        let response = self.send("getthisdicsinfo", self.client.get(&url)).await?;
         let text = self
             .log_response_text(response, &format!("getthisdicsinfo {discid}"))
             .await?;
@@ -124,7 +124,9 @@ impl DizzylabClient {
             discid, trackid, packtype
         );
 
-        let response = self.client.get(&url).send().await?;
+        let response = self
+            .send("gettrackdownloadurl", self.client.get(&url))
+            .await?;
         let status = response.status();
         let text = self
             .log_response_text(response, &format!("gettrackdownloadurl {trackid}"))
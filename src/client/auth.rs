@@ -1,4 +1,5 @@
 use super::DizzylabClient;
+use crate::metrics;
 use anyhow::{anyhow, Result};
 use tracing::{debug, info};
 
@@ -6,11 +7,21 @@ impl DizzylabClient {
     /// Login via both web session (for gift/web downloads) and API token.
     /// Returns the API token for use in all JSON API calls.
     pub async fn login(&self, username: &str, password: &str) -> Result<String> {
+        let result = self.login_session(username, password).await;
+        if result.is_err() {
+            metrics::login_failed();
+        }
+        result
+    }
+
+    async fn login_session(&self, username: &str, password: &str) -> Result<String> {
         info!("登录中...");
 
         // Step 1: GET login page to obtain csrftoken cookie
         let login_page_url = "https://www.dizzylab.net/albums/login/";
-        let response = self.client.get(login_page_url).send().await?;
+        let response = self
+            .send("login_page", self.client.get(login_page_url))
+            .await?;
         let login_page_status = response.status();
 
         if self.debug {
@@ -31,13 +42,12 @@ impl DizzylabClient {
             ("password", password),
         ];
 
-        let web_login_resp = self
+        let request = self
             .client
             .post(login_page_url)
             .header("Referer", login_page_url)
-            .form(&form_params)
-            .send()
-            .await?;
+            .form(&form_params);
+        let web_login_resp = self.send("web_login", request).await?;
 
         let web_login_status = web_login_resp.status();
         if self.debug {
@@ -55,14 +65,13 @@ impl DizzylabClient {
             "password": password,
         });
 
-        let api_resp = self
+        let request = self
             .client
             .post("https://www.dizzylab.net/apis/auth/login/")
             .header("Accept", "application/json")
             .header("Content-Type", "application/json")
-            .json(&api_login_body)
-            .send()
-            .await?;
+            .json(&api_login_body);
+        let api_resp = self.send("api_login", request).await?;
 
         let api_status = api_resp.status();
         let api_resp_text = api_resp.text().await?;
@@ -2,8 +2,11 @@ mod api;
 mod auth;
 mod web;
 
+use crate::metrics;
+use crate::partial::PartialFile;
+use crate::progress::FileProgress;
 use anyhow::Result;
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, Response};
 use tracing::debug;
 
 /// Metadata extracted from cover HTTP response headers.
@@ -30,16 +33,33 @@ impl DizzylabClient {
         Ok(Self { client, debug })
     }
 
-    /// Stream a CDN URL directly to a file on disk. Returns the `Last-Modified` header value.
+    /// Send a request, counting it under `endpoint` for `/metrics`.
+    pub(super) async fn send(
+        &self,
+        endpoint: &'static str,
+        request: RequestBuilder,
+    ) -> reqwest::Result<Response> {
+        let result = request.send().await;
+        let status = result
+            .as_ref()
+            .ok()
+            .map(|response| response.status().as_u16());
+        metrics::http_request(endpoint, status);
+        result
+    }
+
+    /// Stream a CDN URL for album `album_id` to a file on disk, via a `.part`
+    /// file that is renamed once complete. Returns the `Last-Modified` header value.
     pub async fn stream_to_file(
         &self,
         url: &str,
+        album_id: &str,
         dest: &std::path::Path,
     ) -> Result<Option<String>> {
         use tokio::io::AsyncWriteExt;
 
         debug!("下载: {}", redact_url_for_log(url));
-        let mut response = self.client.get(url).send().await?;
+        let mut response = self.send("cdn_download", self.client.get(url)).await?;
 
         if !response.status().is_success() {
             return Err(anyhow::anyhow!("下载失败，状态码: {}", response.status()));
@@ -51,16 +71,24 @@ impl DizzylabClient {
             .and_then(|v| v.to_str().ok())
             .map(|s| s.to_string());
 
-        let mut file = tokio::fs::File::create(dest).await?;
+        let partial = PartialFile::new(dest);
+        let mut progress = FileProgress::new(album_id, dest, response.content_length());
+        let mut file = tokio::fs::File::create(partial.path()).await?;
         while let Some(chunk) = response.chunk().await? {
             file.write_all(&chunk).await?;
+            progress.advance(chunk.len());
+            metrics::bytes_downloaded(chunk.len() as u64);
         }
         file.flush().await?;
+        drop(file);
+        partial.commit()?;
+        progress.finish();
 
         Ok(last_modified)
     }
 
-    /// Stream a web session download (with Referer header) directly to a file on disk.
+    /// Stream a web session download (with Referer header) to a file on disk,
+    /// via a `.part` file like `stream_to_file`.
     pub async fn stream_file_to_path(
         &self,
         url: &str,
@@ -69,15 +97,11 @@ impl DizzylabClient {
     ) -> Result<()> {
         use tokio::io::AsyncWriteExt;
 
-        let mut response = self
-            .client
-            .get(url)
-            .header(
-                "Referer",
-                &format!("https://www.dizzylab.net/d/{album_id}/"),
-            )
-            .send()
-            .await?;
+        let request = self.client.get(url).header(
+            "Referer",
+            &format!("https://www.dizzylab.net/d/{album_id}/"),
+        );
+        let mut response = self.send("web_download", request).await?;
 
         if self.debug {
             debug!("下载响应状态码: {} ({})", response.status(), album_id);
@@ -87,7 +111,7 @@ impl DizzylabClient {
             if let Some(location) = response.headers().get("location") {
                 let redirect_url = location.to_str()?.to_string();
                 debug!("重定向到: {}", redact_url_for_log(&redirect_url));
-                self.stream_to_file(&redirect_url, dest).await?;
+                self.stream_to_file(&redirect_url, album_id, dest).await?;
                 return Ok(());
             }
         }
@@ -96,18 +120,59 @@ impl DizzylabClient {
             return Err(anyhow::anyhow!("下载失败，状态码: {}", response.status()));
         }
 
-        let mut file = tokio::fs::File::create(dest).await?;
+        let partial = PartialFile::new(dest);
+        let mut progress = FileProgress::new(album_id, dest, response.content_length());
+        let mut file = tokio::fs::File::create(partial.path()).await?;
         while let Some(chunk) = response.chunk().await? {
             file.write_all(&chunk).await?;
+            progress.advance(chunk.len());
+            metrics::bytes_downloaded(chunk.len() as u64);
         }
         file.flush().await?;
+        drop(file);
+        partial.commit()?;
+        progress.finish();
 
         Ok(())
     }
 
+    /// HEAD request for the size of a download, following one redirect like
+    /// `stream_file_to_path`. Returns `None` when no `Content-Length` is sent.
+    pub async fn head_size(&self, url: &str, album_id: &str) -> Result<Option<u64>> {
+        let request = self.client.head(url).header(
+            "Referer",
+            &format!("https://www.dizzylab.net/d/{album_id}/"),
+        );
+        let mut response = self.send("download_head", request).await?;
+
+        if response.status().is_redirection() {
+            if let Some(location) = response.headers().get("location") {
+                let redirect_url = location.to_str()?.to_string();
+                debug!("重定向到: {}", redact_url_for_log(&redirect_url));
+                response = self
+                    .send("download_head", self.client.head(&redirect_url))
+                    .await?;
+            }
+        }
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "HEAD 请求失败，状态码: {}",
+                response.status()
+            ));
+        }
+
+        // `content_length()` reports the (empty) HEAD body, so read the header.
+        Ok(response
+            .headers()
+            .get("content-length")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok()))
+    }
+
     /// HEAD request to get cover metadata without downloading the body.
     pub async fn head_cover(&self, cover_url: &str, album_id: &str) -> Result<CoverMeta> {
-        let response = self.client.head(cover_url).send().await?;
+        let response = self.send("cover_head", self.client.head(cover_url)).await?;
 
         let last_modified = response
             .headers()
@@ -144,7 +209,7 @@ impl DizzylabClient {
             return Err(anyhow!("封面URL为空"));
         }
 
-        let response = self.client.get(cover_url).send().await?;
+        let response = self.send("cover", self.client.get(cover_url)).await?;
 
         if self.debug {
             debug!("封面下载状态码: {} ({})", response.status(), album_id);
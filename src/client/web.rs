@@ -9,7 +9,7 @@ impl DizzylabClient {
     /// Returns an empty map if the album has no gift content.
     pub async fn get_gift_download_link(&self, album_id: &str) -> Result<HashMap<String, String>> {
         let album_url = format!("https://www.dizzylab.net/d/{album_id}/");
-        let response = self.client.get(&album_url).send().await?;
+        let response = self.send("album_page", self.client.get(&album_url)).await?;
 
         let html = self
             .log_response_text(response, &format!("gift key {album_id}"))
@@ -47,7 +47,7 @@ impl DizzylabClient {
         format: &str,
     ) -> Result<String> {
         let album_url = format!("https://www.dizzylab.net/d/{album_id}/");
-        let response = self.client.get(&album_url).send().await?;
+        let response = self.send("album_page", self.client.get(&album_url)).await?;
         let html = self
             .log_response_text(response, &format!("web format link {album_id} {format}"))
             .await?;
@@ -1,11 +1,20 @@
-use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use crate::config_env;
+use crate::config_migrate::{self, CURRENT_CONFIG_VERSION};
+use crate::secrets::{self, MasterKey};
+use anyhow::{anyhow, Result};
+use schemars::JsonSchema;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use tracing::info;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct Config {
+    /// Schema version; older files are upgraded by `config_migrate` on load.
     #[serde(default)]
-    pub user: UserConfig,
+    pub config_version: u32,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub users: Vec<UserConfig>,
     pub download: DownloadConfig,
@@ -15,26 +24,105 @@ pub struct Config {
     pub schedule: ScheduleConfig,
     #[serde(default)]
     pub api: ApiConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// A Dizzylab account. The password is either inline or read at load time from
+/// `password_file` (e.g. a Docker secret) or the `password_env` variable; the
+/// resolved value of a reference is never written back to the file.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct UserConfig {
     pub username: String,
+    #[serde(default)]
     pub password: String,
+    #[serde(default)]
+    pub password_file: Option<PathBuf>,
+    #[serde(default)]
+    pub password_env: Option<String>,
+}
+
+impl UserConfig {
+    /// Where the password comes from when it is not stored inline.
+    pub fn password_source(&self) -> Option<&'static str> {
+        if self.password_file.is_some() {
+            Some("file")
+        } else if self.password_env.is_some() {
+            Some("env")
+        } else {
+            None
+        }
+    }
+
+    pub fn resolve_password(&mut self) -> Result<()> {
+        match (&self.password_file, &self.password_env) {
+            (Some(_), Some(_)) => Err(anyhow!(
+                "账号 {} 不能同时设置 password_file 和 password_env",
+                self.username
+            )),
+            (Some(path), None) => {
+                let password = std::fs::read_to_string(path).map_err(|e| {
+                    anyhow!(
+                        "无法读取账号 {} 的 password_file {}: {}",
+                        self.username,
+                        path.display(),
+                        e
+                    )
+                })?;
+                self.password = password.trim_end_matches(['\r', '\n']).to_string();
+                Ok(())
+            }
+            (None, Some(var)) => {
+                self.password = std::env::var(var).map_err(|_| {
+                    anyhow!(
+                        "账号 {} 的 password_env 环境变量 {} 未设置",
+                        self.username,
+                        var
+                    )
+                })?;
+                Ok(())
+            }
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl Serialize for UserConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("UserConfig", 2)?;
+        state.serialize_field("username", &self.username)?;
+        match (&self.password_file, &self.password_env) {
+            (Some(path), _) => state.serialize_field("password_file", path)?,
+            (None, Some(var)) => state.serialize_field("password_env", var)?,
+            (None, None) => state.serialize_field("password", &self.password)?,
+        }
+        state.end()
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct DownloadConfig {
+    #[schemars(extend(
+        "items" = { "type": "string", "enum": ["128", "320", "FLAC", "gift"] },
+        "minItems" = 1,
+        "uniqueItems" = true,
+    ))]
     pub formats: Vec<String>, // "128", "320", "FLAC", "gift"
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct PathsConfig {
     pub output_dir: PathBuf,
     pub directory_template: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct BehaviorConfig {
     pub skip_existing: bool,
     pub single_threaded: bool,
@@ -50,7 +138,8 @@ pub struct BehaviorConfig {
     pub metadata_only: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct ScheduleConfig {
     #[serde(default = "default_false")]
     pub enabled: bool,
@@ -67,7 +156,8 @@ impl Default for ScheduleConfig {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct ApiConfig {
     #[serde(default = "default_api_bind")]
     pub bind: String,
@@ -75,6 +165,32 @@ pub struct ApiConfig {
     pub api_key: String,
     #[serde(default = "default_web_root")]
     pub web_root: PathBuf,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<ApiKeyConfig>,
+    /// Lifetime of a Web UI login session cookie.
+    #[serde(default = "default_session_ttl_hours")]
+    pub session_ttl_hours: u64,
+    /// PEM certificate chain and private key; when both are set the server
+    /// speaks HTTPS only.
+    #[serde(default, skip_serializing_if = "path_is_empty")]
+    pub tls_cert: PathBuf,
+    #[serde(default, skip_serializing_if = "path_is_empty")]
+    pub tls_key: PathBuf,
+}
+
+/// A named API key limited to `scopes` (read, sync, admin). Only the hash is kept:
+/// a plaintext `key` is hashed into `key_hash` when the API server starts.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub key: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub key_hash: String,
+    #[serde(default)]
+    #[schemars(extend("items" = { "type": "string", "enum": ["read", "sync", "admin"] }))]
+    pub scopes: Vec<String>,
 }
 
 impl Default for ApiConfig {
@@ -83,10 +199,75 @@ impl Default for ApiConfig {
             bind: default_api_bind(),
             api_key: String::new(),
             web_root: default_web_root(),
+            keys: Vec::new(),
+            session_ttl_hours: default_session_ttl_hours(),
+            tls_cert: PathBuf::new(),
+            tls_key: PathBuf::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct LoggingConfig {
+    #[serde(default = "default_true")]
+    pub file_enabled: bool,
+    /// Empty means `.dizzysync-logs` next to the config file.
+    #[serde(default)]
+    pub dir: PathBuf,
+    #[serde(default = "default_log_rotation")]
+    #[schemars(extend("enum" = ["daily", "size"]))]
+    pub rotation: String, // "daily", "size"
+    #[serde(default = "default_log_max_size_mb")]
+    pub max_size_mb: u64,
+    #[serde(default = "default_log_retention_days")]
+    pub retention_days: u64,
+    /// Keep at most this many log files; 0 means no limit.
+    #[serde(default)]
+    pub max_files: u64,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            file_enabled: true,
+            dir: PathBuf::new(),
+            rotation: default_log_rotation(),
+            max_size_mb: default_log_max_size_mb(),
+            retention_days: default_log_retention_days(),
+            max_files: 0,
         }
     }
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct NotifyConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Empty means every event: album_downloaded, job_failed, job_finished, login_failed.
+    #[serde(default)]
+    #[schemars(extend("items" = {
+        "type": "string",
+        "enum": ["album_downloaded", "job_failed", "job_finished", "login_failed"],
+    }))]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default = "default_webhook_retries")]
+    pub retries: u32,
+}
+
+fn default_webhook_retries() -> u32 {
+    3
+}
+
 fn default_one() -> usize {
     1
 }
@@ -111,13 +292,32 @@ fn default_web_root() -> PathBuf {
     PathBuf::from("./web/dist")
 }
 
+fn default_session_ttl_hours() -> u64 {
+    24 * 7
+}
+
+// serde hands `skip_serializing_if` a `&PathBuf`.
+#[allow(clippy::ptr_arg)]
+fn path_is_empty(path: &PathBuf) -> bool {
+    path.as_os_str().is_empty()
+}
+
+fn default_log_rotation() -> String {
+    "daily".to_string()
+}
+
+fn default_log_max_size_mb() -> u64 {
+    10
+}
+
+fn default_log_retention_days() -> u64 {
+    14
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
-            user: UserConfig {
-                username: String::new(),
-                password: String::new(),
-            },
+            config_version: CURRENT_CONFIG_VERSION,
             users: Vec::new(),
             download: DownloadConfig {
                 formats: vec!["320".to_string(), "FLAC".to_string()],
@@ -137,6 +337,8 @@ impl Default for Config {
             },
             schedule: ScheduleConfig::default(),
             api: ApiConfig::default(),
+            logging: LoggingConfig::default(),
+            notify: NotifyConfig::default(),
         }
     }
 }
@@ -144,7 +346,31 @@ impl Default for Config {
 impl Config {
     pub fn load_from_file(path: &str) -> Result<Self> {
         let content = std::fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
+        let mut table: toml::Table = toml::from_str(&content)?;
+        let changes = config_migrate::migrate(&mut table)?;
+        let mut config: Config = if changes.is_empty() {
+            // Parse the text again so type errors keep their line numbers.
+            toml::from_str(&content)?
+        } else {
+            table.try_into()?
+        };
+        config.decrypt_secrets()?;
+        for user in &mut config.users {
+            user.resolve_password()?;
+        }
+
+        if !changes.is_empty() {
+            let backup = format!("{path}.bak");
+            std::fs::write(&backup, &content)?;
+            info!(
+                "配置文件已升级到版本 {}，原文件备份为 {}",
+                CURRENT_CONFIG_VERSION, backup
+            );
+            for change in &changes {
+                info!("配置迁移 {}", change);
+            }
+            config.save_to_file(path)?;
+        }
         Ok(config)
     }
 
@@ -154,20 +380,93 @@ impl Config {
                 std::fs::create_dir_all(parent)?;
             }
         }
-        let content = toml::to_string_pretty(self)?;
-        std::fs::write(path, content)?;
+        let mut table = match MasterKey::from_env()? {
+            Some(key) => {
+                let mut encrypted = self.clone();
+                for value in encrypted.secret_values() {
+                    if !value.is_empty() && !secrets::is_encrypted(value) {
+                        *value = key.encrypt(value)?;
+                    }
+                }
+                toml::Table::try_from(&encrypted)?
+            }
+            None => toml::Table::try_from(self)?,
+        };
+        let locked = config_env::locked_fields();
+        if !locked.is_empty() {
+            let on_disk = std::fs::read_to_string(path)
+                .ok()
+                .and_then(|content| toml::from_str::<toml::Table>(&content).ok());
+            let defaults = toml::Table::try_from(Config::default())?;
+            config_env::restore_locked(&mut table, on_disk.as_ref(), &defaults, &locked);
+        }
+        std::fs::write(path, toml::to_string_pretty(&table)?)?;
+        Ok(())
+    }
+
+    /// Inline account passwords and the API key: the values encrypted at rest
+    /// when a master key is configured.
+    fn secret_values(&mut self) -> impl Iterator<Item = &mut String> {
+        self.users
+            .iter_mut()
+            .filter(|user| user.password_source().is_none())
+            .map(|user| &mut user.password)
+            .chain(std::iter::once(&mut self.api.api_key))
+    }
+
+    fn decrypt_secrets(&mut self) -> Result<()> {
+        let mut key = None;
+        for value in self.secret_values() {
+            if !secrets::is_encrypted(value) {
+                continue;
+            }
+            if key.is_none() {
+                key = MasterKey::from_env()?;
+            }
+            let Some(key) = &key else {
+                return Err(anyhow!(
+                    "配置文件包含加密的凭据，请通过 {} 或 {} 提供主密钥",
+                    secrets::MASTER_KEY_ENV,
+                    secrets::MASTER_KEY_FILE_ENV
+                ));
+            };
+            *value = key.decrypt(value)?;
+        }
         Ok(())
     }
 
+    /// Rewrite the file at `path` with its credentials encrypted. Returns how many
+    /// values were encrypted.
+    pub fn encrypt_file(path: &str) -> Result<usize> {
+        if MasterKey::from_env()?.is_none() {
+            return Err(anyhow!(
+                "未提供主密钥，请设置 {} 或 {}",
+                secrets::MASTER_KEY_ENV,
+                secrets::MASTER_KEY_FILE_ENV
+            ));
+        }
+        let mut config = Self::load_from_file(path)?;
+        let count = config
+            .secret_values()
+            .filter(|value| !value.is_empty())
+            .count();
+        config.save_to_file(path)?;
+        Ok(count)
+    }
+
     pub fn create_default_config(path: &str) -> Result<()> {
-        let default_config = Config::default();
+        let mut default_config = Config::default();
+        default_config.set_accounts(vec![UserConfig::default()]);
         default_config.save_to_file(path)?;
         println!("已创建默认配置文件: {path}");
         println!("请编辑配置文件，设置你的用户名和密码");
         Ok(())
     }
 
-    pub fn apply_env_overrides(&mut self, fill_empty_only: bool) {
+    /// Apply the deployment's environment: the single-purpose `DIZZYSYNC_*`
+    /// variables (only filling empty values when `fill_empty_only`) and then the
+    /// generic `DIZZYSYNC__SECTION__FIELD` overrides, which always win.
+    pub fn apply_env_overrides(&mut self, fill_empty_only: bool) -> Result<()> {
         apply_string_env(
             &mut self.api.api_key,
             "DIZZYSYNC_WEB_PASSWORD",
@@ -175,16 +474,17 @@ impl Config {
         );
         apply_string_env(&mut self.api.api_key, "DIZZYSYNC_API_KEY", fill_empty_only);
 
-        apply_string_env(
-            &mut self.user.username,
-            "DIZZYSYNC_USERNAME",
-            fill_empty_only,
-        );
-        apply_string_env(
-            &mut self.user.password,
-            "DIZZYSYNC_PASSWORD",
-            fill_empty_only,
-        );
+        if std::env::var_os("DIZZYSYNC_USERNAME").is_some()
+            || std::env::var_os("DIZZYSYNC_PASSWORD").is_some()
+        {
+            // The single-account variables describe the first account.
+            if self.users.is_empty() {
+                self.users.push(UserConfig::default());
+            }
+            let account = &mut self.users[0];
+            apply_string_env(&mut account.username, "DIZZYSYNC_USERNAME", fill_empty_only);
+            apply_string_env(&mut account.password, "DIZZYSYNC_PASSWORD", fill_empty_only);
+        }
 
         if let Ok(output_dir) = std::env::var("DIZZYSYNC_OUTPUT_DIR") {
             // The deployment-provided output directory is authoritative so the Web UI
@@ -192,35 +492,28 @@ impl Config {
             self.paths.output_dir = PathBuf::from(output_dir);
         }
 
+        config_env::apply(self)?;
         self.behavior.max_concurrent_albums = self.behavior.max_concurrent_albums.max(1);
+        Ok(())
     }
 
     pub fn accounts(&self) -> Vec<UserConfig> {
-        if self.users.is_empty() {
-            if self.user.username.trim().is_empty() && self.user.password.trim().is_empty() {
-                Vec::new()
-            } else {
-                vec![self.user.clone()]
-            }
-        } else {
-            self.users.clone()
-        }
+        self.users.clone()
     }
 
     pub fn set_accounts(&mut self, users: Vec<UserConfig>) {
         self.users = users;
-        self.user = self.users.first().cloned().unwrap_or_default();
     }
 
     pub fn load_or_bootstrap(path: &str) -> Result<Self> {
         if std::path::Path::new(path).exists() {
             let mut config = Self::load_from_file(path)?;
-            config.apply_env_overrides(true);
+            config.apply_env_overrides(true)?;
             return Ok(config);
         }
 
         let mut config = Self::default();
-        config.apply_env_overrides(false);
+        config.apply_env_overrides(false)?;
         if std::env::var("DIZZYSYNC_OUTPUT_DIR").is_err() {
             config.paths.output_dir = PathBuf::from("/data");
         }
@@ -237,3 +530,43 @@ fn apply_string_env(value: &mut String, key: &str, fill_empty_only: bool) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_references_are_not_saved() {
+        let secret = std::env::temp_dir().join(format!("dizzysync-secret-{}", std::process::id()));
+        std::fs::write(&secret, "from-file\n").unwrap();
+        std::env::set_var("DIZZYSYNC_TEST_ACCOUNT_PASSWORD", "from-env");
+
+        let mut config = Config::default();
+        config.set_accounts(vec![
+            UserConfig {
+                username: "file".to_string(),
+                password_file: Some(secret.clone()),
+                ..UserConfig::default()
+            },
+            UserConfig {
+                username: "env".to_string(),
+                password_env: Some("DIZZYSYNC_TEST_ACCOUNT_PASSWORD".to_string()),
+                ..UserConfig::default()
+            },
+        ]);
+        let path = secret.with_extension("toml");
+        config.save_to_file(path.to_str().unwrap()).unwrap();
+
+        let loaded = Config::load_from_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.users[0].password, "from-file");
+        assert_eq!(loaded.users[1].password, "from-env");
+
+        loaded.save_to_file(path.to_str().unwrap()).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains("from-file") && !saved.contains("from-env"));
+        assert!(saved.contains("password_env = \"DIZZYSYNC_TEST_ACCOUNT_PASSWORD\""));
+
+        let _ = std::fs::remove_file(&secret);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::config::{Config, UserConfig};
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use toml::{Table, Value};
+
+const PREFIX: &str = "DIZZYSYNC__";
+const SECTIONS: [&str; 8] = [
+    "download", "paths", "behavior", "schedule", "api", "logging", "notify", "users",
+];
+
+/// One `DIZZYSYNC__SECTION__FIELD=value` variable. Path segments are lowercased
+/// config keys; numeric segments index into arrays such as `[[users]]`.
+struct EnvOverride {
+    name: String,
+    path: Vec<String>,
+    value: String,
+}
+
+fn overrides() -> Vec<EnvOverride> {
+    let mut overrides = std::env::vars()
+        .filter_map(|(name, value)| {
+            let path = name
+                .strip_prefix(PREFIX)?
+                .split("__")
+                .map(str::to_ascii_lowercase)
+                .collect::<Vec<_>>();
+            (!path.iter().any(String::is_empty)).then_some(EnvOverride { name, path, value })
+        })
+        .collect::<Vec<_>>();
+    overrides.sort_by(|a, b| a.name.cmp(&b.name));
+    overrides
+}
+
+/// Dotted paths (`behavior.max_concurrent_albums`, `users.0.password`) of every
+/// field set through the environment. The Web UI shows these as read-only.
+pub fn locked_fields() -> Vec<String> {
+    let mut fields = overrides()
+        .into_iter()
+        .map(|o| o.path.join("."))
+        .collect::<Vec<_>>();
+    if std::env::var_os("DIZZYSYNC_OUTPUT_DIR").is_some() {
+        fields.push("paths.output_dir".to_string());
+    }
+    fields.sort();
+    fields.dedup();
+    fields
+}
+
+/// Apply every `DIZZYSYNC__…` variable to `config`. Values are parsed to the
+/// type of the field they replace; arrays take comma-separated items.
+pub fn apply(config: &mut Config) -> Result<()> {
+    let overrides = overrides();
+    if let Some(unknown) = overrides
+        .iter()
+        .find(|o| !SECTIONS.contains(&o.path[0].as_str()))
+    {
+        return Err(anyhow!("环境变量 {} 指向未知的配置段", unknown.name));
+    }
+
+    for section in SECTIONS {
+        let matching = overrides
+            .iter()
+            .filter(|o| o.path[0] == section)
+            .collect::<Vec<_>>();
+        if matching.is_empty() {
+            continue;
+        }
+        match section {
+            "download" => apply_section(&mut config.download, &matching)?,
+            "paths" => apply_section(&mut config.paths, &matching)?,
+            "behavior" => apply_section(&mut config.behavior, &matching)?,
+            "schedule" => apply_section(&mut config.schedule, &matching)?,
+            "api" => apply_section(&mut config.api, &matching)?,
+            "logging" => apply_section(&mut config.logging, &matching)?,
+            "notify" => apply_section(&mut config.notify, &matching)?,
+            _ => apply_users(&mut config.users, &matching)?,
+        }
+    }
+    Ok(())
+}
+
+fn apply_section<T: Serialize + DeserializeOwned>(
+    target: &mut T,
+    overrides: &[&EnvOverride],
+) -> Result<()> {
+    let mut value = Value::try_from(&*target)?;
+    for o in overrides {
+        set_path(&mut value, &o.path[1..], &o.value)
+            .map_err(|e| anyhow!("环境变量 {} 无效: {}", o.name, e))?;
+    }
+    let section = &overrides[0].path[0];
+    *target = value
+        .try_into()
+        .map_err(|e| anyhow!("环境变量覆盖后的 [{}] 无效: {}", section, e))?;
+    Ok(())
+}
+
+/// Accounts serialize without passwords that come from a reference, so carry
+/// those over and resolve references introduced by the environment.
+fn apply_users(users: &mut Vec<UserConfig>, overrides: &[&EnvOverride]) -> Result<()> {
+    let previous = users.clone();
+    apply_section(users, overrides)?;
+    for (index, user) in users.iter_mut().enumerate() {
+        if user.password_source().is_none() || !user.password.is_empty() {
+            continue;
+        }
+        match previous.get(index) {
+            Some(old) if old.username == user.username && !old.password.is_empty() => {
+                user.password = old.password.clone();
+            }
+            _ => user.resolve_password()?,
+        }
+    }
+    Ok(())
+}
+
+fn set_path(value: &mut Value, path: &[String], raw: &str) -> Result<()> {
+    let Some((key, rest)) = path.split_first() else {
+        return Err(anyhow!("缺少字段名"));
+    };
+    let child = match value {
+        Value::Table(table) => {
+            if rest.is_empty() {
+                let parsed = parse_like(table.get(key), raw)?;
+                table.insert(key.clone(), parsed);
+                return Ok(());
+            }
+            let is_index = rest[0].parse::<usize>().is_ok();
+            table.entry(key.clone()).or_insert_with(|| {
+                if is_index {
+                    Value::Array(Vec::new())
+                } else {
+                    Value::Table(Table::new())
+                }
+            })
+        }
+        Value::Array(items) => {
+            let index = key
+                .parse::<usize>()
+                .map_err(|_| anyhow!("{} 需要数组下标", key))?;
+            if index == items.len() {
+                items.push(Value::Table(Table::new()));
+            }
+            let len = items.len();
+            let item = items
+                .get_mut(index)
+                .ok_or_else(|| anyhow!("数组下标 {} 超出范围（当前 {} 项）", index, len))?;
+            if rest.is_empty() {
+                *item = parse_like(Some(item), raw)?;
+                return Ok(());
+            }
+            item
+        }
+        _ => return Err(anyhow!("{} 不是配置段", key)),
+    };
+    set_path(child, rest, raw)
+}
+
+fn parse_like(existing: Option<&Value>, raw: &str) -> Result<Value> {
+    let raw = raw.trim();
+    Ok(match existing {
+        Some(Value::Boolean(_)) => Value::Boolean(
+            raw.parse()
+                .map_err(|_| anyhow!("需要 true 或 false，实际为 \"{}\"", raw))?,
+        ),
+        Some(Value::Integer(_)) => Value::Integer(
+            raw.parse()
+                .map_err(|_| anyhow!("需要整数，实际为 \"{}\"", raw))?,
+        ),
+        Some(Value::Float(_)) => Value::Float(
+            raw.parse()
+                .map_err(|_| anyhow!("需要数字，实际为 \"{}\"", raw))?,
+        ),
+        Some(Value::Array(items)) => {
+            let element = items.first();
+            Value::Array(
+                raw.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| parse_like(element, item))
+                    .collect::<Result<_>>()?,
+            )
+        }
+        _ => Value::String(raw.to_string()),
+    })
+}
+
+/// Put the file's own values back for `locked` paths so saving a config never
+/// writes environment-provided values into the TOML. Fields the file never had
+/// fall back to `defaults`; anything missing from both, such as an account that
+/// only exists in the environment, is dropped.
+pub fn restore_locked(
+    table: &mut Table,
+    on_disk: Option<&Table>,
+    defaults: &Table,
+    locked: &[String],
+) {
+    let mut removed: Vec<Vec<&str>> = Vec::new();
+    for field in locked {
+        let path = field.split('.').collect::<Vec<_>>();
+        if removed.iter().any(|prefix| path.starts_with(prefix)) {
+            continue;
+        }
+        let original = on_disk
+            .and_then(|disk| get_path(disk, &path))
+            .or_else(|| get_path(defaults, &path));
+        match original {
+            Some(value) => {
+                let value = value.clone();
+                if let Some(slot) = get_path_mut(table, &path) {
+                    *slot = value;
+                }
+            }
+            None => {
+                let known = |len: usize| {
+                    let prefix = &path[..len];
+                    on_disk.and_then(|disk| get_path(disk, prefix)).is_some()
+                        || get_path(defaults, prefix).is_some()
+                };
+                let len = (1..=path.len())
+                    .find(|&len| !known(len))
+                    .unwrap_or(path.len());
+                remove_path(table, &path[..len]);
+                removed.push(path[..len].to_vec());
+            }
+        }
+    }
+}
+
+fn get_path<'a>(table: &'a Table, path: &[&str]) -> Option<&'a Value> {
+    let (first, rest) = path.split_first()?;
+    rest.iter()
+        .try_fold(table.get(*first)?, |value, key| match value {
+            Value::Table(table) => table.get(*key),
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+fn get_path_mut<'a>(table: &'a mut Table, path: &[&str]) -> Option<&'a mut Value> {
+    let (first, rest) = path.split_first()?;
+    rest.iter()
+        .try_fold(table.get_mut(*first)?, |value, key| match value {
+            Value::Table(table) => table.get_mut(*key),
+            Value::Array(items) => items.get_mut(key.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+fn remove_path(table: &mut Table, path: &[&str]) {
+    let Some((last, parent)) = path.split_last() else {
+        return;
+    };
+    if parent.is_empty() {
+        table.remove(*last);
+        return;
+    }
+    match get_path_mut(table, parent) {
+        Some(Value::Table(parent)) => {
+            parent.remove(*last);
+        }
+        Some(Value::Array(items)) => {
+            if let Ok(index) = last.parse::<usize>() {
+                if index < items.len() {
+                    items.remove(index);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_path_parses_like_existing_value() {
+        let mut value = Value::try_from(Config::default()).unwrap();
+        let path = |p: &str| p.split('.').map(str::to_string).collect::<Vec<_>>();
+
+        set_path(&mut value, &path("behavior.max_concurrent_albums"), "4").unwrap();
+        set_path(&mut value, &path("behavior.skip_existing"), "false").unwrap();
+        set_path(&mut value, &path("download.formats"), "FLAC, gift").unwrap();
+        set_path(&mut value, &path("users.0.username"), "alice").unwrap();
+        assert!(set_path(&mut value, &path("behavior.max_concurrent_albums"), "many").is_err());
+        assert!(set_path(&mut value, &path("users.5.username"), "bob").is_err());
+
+        let config: Config = value.try_into().unwrap();
+        assert_eq!(config.behavior.max_concurrent_albums, 4);
+        assert!(!config.behavior.skip_existing);
+        assert_eq!(config.download.formats, vec!["FLAC", "gift"]);
+        assert_eq!(config.users[0].username, "alice");
+    }
+
+    #[test]
+    fn test_restore_locked_keeps_file_values() {
+        let mut saved: Table = toml::from_str(
+            "[[users]]\nusername = \"env\"\npassword = \"pw\"\n\n\
+             [api]\nbind = \"0.0.0.0:1\"\napi_key = \"from-env\"\ntls_cert = \"c.pem\"\n",
+        )
+        .unwrap();
+        let on_disk: Table = toml::from_str("[api]\nbind = \"127.0.0.1:2\"\n").unwrap();
+        let defaults = Table::try_from(Config::default()).unwrap();
+        let locked = [
+            "api.api_key",
+            "api.bind",
+            "api.tls_cert",
+            "users.0.password",
+            "users.0.username",
+        ]
+        .map(str::to_string);
+        restore_locked(&mut saved, Some(&on_disk), &defaults, &locked);
+        assert_eq!(saved["api"]["bind"].as_str(), Some("127.0.0.1:2"));
+        assert_eq!(saved["api"]["api_key"].as_str(), Some(""));
+        assert!(saved["api"].get("tls_cert").is_none());
+        assert!(saved.get("users").is_none());
+    }
+}
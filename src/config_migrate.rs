@@ -0,0 +1,100 @@
+use anyhow::{anyhow, Result};
+use toml::{Table, Value};
+
+/// Version written to newly saved configs. Bump it together with a new entry in
+/// `MIGRATIONS`.
+pub const CURRENT_CONFIG_VERSION: u32 = 1;
+
+/// Each step upgrades a raw config table by one version and describes what it
+/// changed; `MIGRATIONS[n]` turns version `n` into `n + 1`.
+type Migration = fn(&mut Table) -> Vec<String>;
+
+const MIGRATIONS: [Migration; CURRENT_CONFIG_VERSION as usize] = [move_legacy_user];
+
+/// Upgrade `table` to `CURRENT_CONFIG_VERSION`. Returns the changes made, empty
+/// only when the file was already current. Files without `config_version` are
+/// version 0.
+pub fn migrate(table: &mut Table) -> Result<Vec<String>> {
+    let version = match table.get("config_version") {
+        None => 0,
+        Some(Value::Integer(version)) if *version >= 0 => *version as u32,
+        Some(value) => return Err(anyhow!("config_version 必须是非负整数，当前为 {value}")),
+    };
+    if version > CURRENT_CONFIG_VERSION {
+        return Err(anyhow!(
+            "配置文件版本 {} 高于当前程序支持的版本 {}，请升级 DizzySync",
+            version,
+            CURRENT_CONFIG_VERSION
+        ));
+    }
+
+    let mut changes = Vec::new();
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        for change in migration(table) {
+            changes.push(format!("v{} → v{}: {}", from, from + 1, change));
+        }
+    }
+    if version < CURRENT_CONFIG_VERSION {
+        table.insert(
+            "config_version".to_string(),
+            Value::Integer(i64::from(CURRENT_CONFIG_VERSION)),
+        );
+        changes.push(format!(
+            "config_version: {version} → {CURRENT_CONFIG_VERSION}"
+        ));
+    }
+    Ok(changes)
+}
+
+/// v0 → v1: fold the single-account `[user]` block into `[[users]]`.
+fn move_legacy_user(table: &mut Table) -> Vec<String> {
+    let Some(Value::Table(user)) = table.remove("user") else {
+        return Vec::new();
+    };
+    let has_users = matches!(table.get("users"), Some(Value::Array(users)) if !users.is_empty());
+    if has_users {
+        return vec!["已移除旧版 [user]，账号以 [[users]] 为准".to_string()];
+    }
+
+    let username = user
+        .get("username")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let has_password = ["password", "password_file", "password_env"]
+        .iter()
+        .any(|key| {
+            user.get(*key)
+                .and_then(Value::as_str)
+                .is_some_and(|v| !v.is_empty())
+        });
+    if username.trim().is_empty() && !has_password {
+        return vec!["已移除空的旧版 [user]".to_string()];
+    }
+    table.insert("users".to_string(), Value::Array(vec![Value::Table(user)]));
+    vec![format!("已将旧版 [user] 账号 {username} 迁移到 [[users]]")]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_legacy_user() {
+        let mut table: Table = toml::from_str(
+            "[user]\nusername = \"alice\"\npassword = \"secret\"\n\n[download]\nformats = [\"320\"]\n",
+        )
+        .unwrap();
+        let changes = migrate(&mut table).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(table.get("user").is_none());
+        assert_eq!(table["users"][0]["username"].as_str(), Some("alice"));
+        assert_eq!(table["config_version"].as_integer(), Some(1));
+
+        // Already current: nothing to do.
+        assert!(migrate(&mut table).unwrap().is_empty());
+
+        table.insert("config_version".to_string(), Value::Integer(99));
+        assert!(migrate(&mut table).is_err());
+    }
+}
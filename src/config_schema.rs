@@ -0,0 +1,183 @@
+use crate::api_control::{validate_formats, validate_schedule};
+use crate::config::Config;
+use crate::config_migrate;
+use jsonschema::error::ValidationErrorKind;
+use jsonschema::paths::LocationSegment;
+use serde_json::Value;
+use std::fmt;
+use std::ops::Range;
+use std::sync::OnceLock;
+use toml::de::{DeTable, DeValue};
+
+/// JSON Schema of `config.toml`, generated from the `Config` structs.
+pub fn schema() -> &'static Value {
+    static SCHEMA: OnceLock<Value> = OnceLock::new();
+    SCHEMA.get_or_init(|| schemars::schema_for!(Config).to_value())
+}
+
+/// One problem found by `check`, located by dotted field path and, when the
+/// field exists in the file, its line number.
+#[derive(Debug)]
+pub struct ConfigIssue {
+    pub line: Option<usize>,
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "第 {} 行", line)?,
+            None => write!(f, "-")?,
+        }
+        if !self.field.is_empty() {
+            write!(f, " [{}]", self.field)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Validate the TOML text against the schema and then the semantic checks the
+/// API applies on save. Legacy files are migrated in memory first, so fields
+/// the migration moves may be reported without a line.
+pub fn check(content: &str) -> Vec<ConfigIssue> {
+    let spanned = match DeTable::parse(content) {
+        Ok(table) => table,
+        Err(e) => {
+            return vec![ConfigIssue {
+                line: e.span().map(|span| line_of(content, span.start)),
+                field: String::new(),
+                message: e.message().to_string(),
+            }]
+        }
+    };
+    let locate = |path: &[String]| {
+        let (found, span) = find_span(spanned.get_ref(), path);
+        (!path.is_empty() && found == path.len()).then(|| line_of(content, span.start))
+    };
+    let issue = |path: Vec<String>, message: String| ConfigIssue {
+        line: locate(&path).or_else(|| locate(&path[..path.len().saturating_sub(1)])),
+        field: path.join("."),
+        message,
+    };
+
+    let mut table: toml::Table = match toml::from_str(content) {
+        Ok(table) => table,
+        Err(e) => return vec![issue(Vec::new(), e.to_string())],
+    };
+    if let Err(e) = config_migrate::migrate(&mut table) {
+        return vec![issue(vec!["config_version".to_string()], e.to_string())];
+    }
+
+    let instance = match serde_json::to_value(&table) {
+        Ok(instance) => instance,
+        Err(e) => return vec![issue(Vec::new(), e.to_string())],
+    };
+    let validator = jsonschema::validator_for(schema()).expect("生成的配置 schema 无效");
+    let mut issues = Vec::new();
+    for error in validator.iter_errors(&instance) {
+        let path = error
+            .instance_path()
+            .iter()
+            .map(|segment| match segment {
+                LocationSegment::Property(name) => name.to_string(),
+                LocationSegment::Index(index) => index.to_string(),
+            })
+            .collect::<Vec<_>>();
+        match error.kind() {
+            ValidationErrorKind::AdditionalProperties { unexpected } => {
+                for key in unexpected {
+                    let mut field = path.clone();
+                    field.push(key.clone());
+                    issues.push(issue(field, "未知字段".to_string()));
+                }
+            }
+            _ => issues.push(issue(path, error.to_string())),
+        }
+    }
+    if !issues.is_empty() {
+        return issues;
+    }
+
+    let config: Config = match table.try_into() {
+        Ok(config) => config,
+        Err(e) => return vec![issue(Vec::new(), e.to_string())],
+    };
+    if let Err(e) = validate_formats(&config) {
+        issues.push(issue(field_path("download.formats"), e.to_string()));
+    }
+    if let Err(e) = validate_schedule(&config) {
+        issues.push(issue(field_path("schedule.cron"), e.to_string()));
+    }
+    issues
+}
+
+fn field_path(field: &str) -> Vec<String> {
+    field.split('.').map(str::to_string).collect()
+}
+
+/// Follow `path` through the parsed document and return how many segments were
+/// found plus the span of the deepest one (the key itself for table entries).
+fn find_span(table: &DeTable, path: &[String]) -> (usize, Range<usize>) {
+    let mut span = 0..0;
+    let mut current: Option<&DeValue> = None;
+    for (depth, segment) in path.iter().enumerate() {
+        let next = match current {
+            None => table
+                .iter()
+                .find(|(key, _)| key.get_ref() == segment)
+                .map(|(key, value)| (key.span(), value)),
+            Some(DeValue::Table(table)) => table
+                .iter()
+                .find(|(key, _)| key.get_ref() == segment)
+                .map(|(key, value)| (key.span(), value)),
+            Some(DeValue::Array(items)) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get(index))
+                .map(|value| (value.span(), value)),
+            Some(_) => None,
+        };
+        let Some((next_span, value)) = next else {
+            return (depth, span);
+        };
+        span = next_span;
+        current = Some(value.get_ref());
+    }
+    (path.len(), span)
+}
+
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())]
+        .bytes()
+        .filter(|&b| b == b'\n')
+        .count()
+        + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_reports_line_numbers() {
+        let content = "config_version = 1\n\n[download]\nformats = [\"FLAC\", \"mp4\"]\n\n\
+                       [paths]\noutput_dir = \"./out\"\ndirectory_template = \"{album}\"\n\n\
+                       [behavior]\nskip_existing = true\nsingle_threaded = true\nmax_concurent_albums = 2\n";
+        let issues = check(content);
+        assert_eq!(issues.len(), 2, "{issues:?}");
+        assert!(issues
+            .iter()
+            .any(|i| i.field == "download.formats.1" && i.line == Some(4)));
+        assert!(issues
+            .iter()
+            .any(|i| i.field == "behavior.max_concurent_albums" && i.line == Some(13)));
+
+        let content = content
+            .replace("\"mp4\"", "\"320\", \"128\"")
+            .replace("max_concurent", "max_concurrent");
+        let issues = check(&content);
+        assert_eq!(issues.len(), 1, "{issues:?}");
+        assert_eq!(issues[0].line, Some(4));
+    }
+}
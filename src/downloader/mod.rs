@@ -1,10 +1,16 @@
+mod plan;
 mod tracks;
 mod web;
 
+pub use plan::{AlbumPlan, FormatPlan, PlanAction, SyncPlan};
+
 use crate::archive;
 use crate::client::DizzylabClient;
 use crate::config::Config;
 use crate::metadata;
+use crate::metrics;
+use crate::progress::{self, SyncEvent};
+use crate::shutdown;
 use crate::types::{DiscInfo, DiscListItem};
 use anyhow::{anyhow, Result};
 use chrono::{self, Datelike};
@@ -12,7 +18,7 @@ use filetime::set_file_times;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
 #[derive(Clone)]
 pub struct Downloader {
@@ -49,11 +55,16 @@ impl Downloader {
         for (index, disc_item) in albums.into_iter().enumerate() {
             let sem = semaphore.clone();
             let downloader = this.clone();
-            join_set.spawn(async move {
+            let span = info_span!("album", album_id = %disc_item.id);
+            let task = async move {
                 let _permit = sem
                     .acquire()
                     .await
                     .map_err(|e| anyhow!("获取并发许可失败: {e}"))?;
+                // Albums still waiting for a permit are not started after a signal.
+                if shutdown::requested() {
+                    return Ok(());
+                }
                 info!(
                     "处理专辑 {}/{}: {} - {}",
                     index + 1,
@@ -62,11 +73,25 @@ impl Downloader {
                     disc_item.label
                 );
 
-                let disc_info = downloader
+                let disc_info = match downloader
                     .client
                     .get_disc_info(&disc_item.id, &downloader.token)
                     .await
-                    .map_err(|e| anyhow!("获取专辑 {} 详情失败: {e}", disc_item.id))?;
+                {
+                    Ok(disc_info) => disc_info,
+                    Err(e) => {
+                        let error = anyhow!("获取专辑 {} 详情失败: {e}", disc_item.id);
+                        // The album never started, but still counts as finished for progress.
+                        metrics::album_downloaded(false);
+                        progress::emit(SyncEvent::AlbumFinished {
+                            album_id: disc_item.id.clone(),
+                            title: disc_item.title.clone(),
+                            success: false,
+                            error: Some(error.to_string()),
+                        });
+                        return Err(error);
+                    }
+                };
 
                 downloader
                     .download_album(&disc_info)
@@ -78,7 +103,8 @@ impl Downloader {
                 }
 
                 Ok::<(), anyhow::Error>(())
-            });
+            };
+            join_set.spawn(task.instrument(span));
         }
 
         let mut failures = Vec::new();
@@ -95,6 +121,7 @@ impl Downloader {
                 }
             }
         }
+        shutdown::check()?;
 
         if failures.is_empty() {
             info!("同步完成！");
@@ -111,25 +138,54 @@ impl Downloader {
     /// Download a single album given its full disc info (already fetched).
     pub async fn download_album(&self, disc_info: &DiscInfo) -> Result<()> {
         let album_dir = self.get_album_directory(disc_info);
+        progress::emit(SyncEvent::AlbumStarted {
+            album_id: disc_info.id.clone(),
+            title: disc_info.title.clone(),
+            path: album_dir.display().to_string(),
+        });
+
+        let span = info_span!("album", album_id = %disc_info.id);
+        let result = self
+            .download_album_to(disc_info, &album_dir)
+            .instrument(span)
+            .await;
+        match result {
+            Ok(0) => metrics::album_up_to_date(),
+            Ok(_) => metrics::album_downloaded(true),
+            Err(_) => metrics::album_downloaded(false),
+        }
+        let result = result.map(|_| ());
+
+        progress::emit(SyncEvent::AlbumFinished {
+            album_id: disc_info.id.clone(),
+            title: disc_info.title.clone(),
+            success: result.is_ok(),
+            error: result.as_ref().err().map(ToString::to_string),
+        });
+        result
+    }
+
+    /// Returns how many files (tracks or archives) were downloaded.
+    async fn download_album_to(&self, disc_info: &DiscInfo, album_dir: &Path) -> Result<usize> {
         info!("album_dir: {}", album_dir.display());
 
-        fs::create_dir_all(&album_dir)?;
+        fs::create_dir_all(album_dir)?;
 
         if self.config.behavior.generate_readme {
             if let Err(e) =
-                metadata::generate_readme(disc_info, &album_dir, &self.config.download.formats)
+                metadata::generate_readme(disc_info, album_dir, &self.config.download.formats)
             {
                 warn!("生成README失败: {}", e);
             }
         }
 
         if self.config.behavior.generate_nfo {
-            if let Err(e) = metadata::generate_nfo(disc_info, &album_dir) {
+            if let Err(e) = metadata::generate_nfo(disc_info, album_dir) {
                 warn!("生成NFO失败: {}", e);
             }
         }
 
-        let cover_last_modified = match self.download_cover(disc_info, &album_dir).await {
+        let cover_last_modified = match self.download_cover(disc_info, album_dir).await {
             Ok(lm) => lm,
             Err(e) => {
                 warn!("下载封面失败: {}", e);
@@ -139,23 +195,51 @@ impl Downloader {
 
         if self.config.behavior.metadata_only {
             info!("仅下载元数据模式：跳过音频文件下载 - {}", disc_info.title);
-            self.set_metadata_timestamps(disc_info, &album_dir, cover_last_modified.as_deref());
-            return Ok(());
+            for format in &self.config.download.formats {
+                emit_format_skipped(disc_info, format, "metadata_only");
+            }
+            self.set_metadata_timestamps(disc_info, album_dir, cover_last_modified.as_deref());
+            return Ok(0);
         }
 
+        let mut downloaded = 0;
+        let mut failed = Vec::new();
         for format in &self.config.download.formats {
-            if let Err(e) = self.download_format(disc_info, format, &album_dir).await {
-                warn!("下载格式 {} 失败: {}", format, e);
+            shutdown::check()?;
+            let tracks = match format.as_str() {
+                "128" | "320" => disc_info.tracks.len(),
+                _ => 0,
+            };
+            progress::emit(SyncEvent::FormatStarted {
+                album_id: disc_info.id.clone(),
+                format: format.clone(),
+                tracks,
+            });
+            match self.download_format(disc_info, format, album_dir).await {
+                Ok(files) => downloaded += files,
+                Err(e) => {
+                    shutdown::check()?;
+                    warn!("下载格式 {} 失败: {}", format, e);
+                    failed.push(format!("{format}: {e}"));
+                }
             }
         }
 
-        self.match_lrc_files(disc_info, &album_dir);
+        self.match_lrc_files(disc_info, album_dir);
 
         // Set timestamps on metadata files and the album directory last,
         // after all file operations, so directory mtime is not overwritten.
-        self.set_metadata_timestamps(disc_info, &album_dir, cover_last_modified.as_deref());
-
-        Ok(())
+        self.set_metadata_timestamps(disc_info, album_dir, cover_last_modified.as_deref());
+
+        // The other formats were still tried, but the album is incomplete.
+        if !failed.is_empty() {
+            return Err(anyhow!(
+                "{} 个格式下载失败: {}",
+                failed.len(),
+                failed.join("; ")
+            ));
+        }
+        Ok(downloaded)
     }
 
     /// Set modification timestamps of cover, README, NFO, and the album directory.
@@ -215,7 +299,7 @@ impl Downloader {
         disc_info: &DiscInfo,
         format: &str,
         album_dir: &Path,
-    ) -> Result<()> {
+    ) -> Result<usize> {
         if format == "gift" {
             return self.download_gift(disc_info, album_dir).await;
         }
@@ -335,6 +419,14 @@ impl Downloader {
     }
 }
 
+pub(super) fn emit_format_skipped(disc_info: &DiscInfo, format: &str, reason: &str) {
+    progress::emit(SyncEvent::FormatSkipped {
+        album_id: disc_info.id.clone(),
+        format: format.to_string(),
+        reason: reason.to_string(),
+    });
+}
+
 /// Returns true if the local file's MD5 matches the ETag from the server.
 /// OSS ETags for single-part uploads are hex MD5 (with surrounding quotes).
 /// Multipart-upload ETags contain a hyphen ("MD5-N") and are skipped.
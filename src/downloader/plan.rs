@@ -0,0 +1,388 @@
+use super::tracks::{file_has_dizzylab_flac_tag, file_has_dizzylab_tag, format_to_extension};
+use super::Downloader;
+use crate::types::DiscInfo;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+/// What a sync would do with a file, format or album. Ordered so that the
+/// strongest action of the parts is the action of the whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanAction {
+    Skip,
+    Retag,
+    Download,
+}
+
+/// One file of a format: a track for MP3 formats, the archive for FLAC and
+/// gift, or an extracted FLAC file that would only be re-tagged.
+#[derive(Debug, Clone, Serialize)]
+pub struct FilePlan {
+    pub name: String,
+    pub action: PlanAction,
+    /// `Content-Length` from a HEAD request on the download URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FormatPlan {
+    pub format: String,
+    pub action: PlanAction,
+    /// Why nothing happens for this format, using the `format_skipped` event reasons.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub files: Vec<FilePlan>,
+}
+
+impl FormatPlan {
+    fn skipped(format: &str, reason: &str) -> Self {
+        Self {
+            format: format.to_string(),
+            action: PlanAction::Skip,
+            reason: Some(reason.to_string()),
+            files: Vec::new(),
+        }
+    }
+
+    /// A format whose files were all planned; skipping every file means the
+    /// local copy is already complete.
+    fn from_files(format: &str, files: Vec<FilePlan>) -> Self {
+        let action = files
+            .iter()
+            .map(|file| file.action)
+            .max()
+            .unwrap_or(PlanAction::Skip);
+        Self {
+            format: format.to_string(),
+            action,
+            reason: (action == PlanAction::Skip).then(|| "exists".to_string()),
+            files,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlbumPlan {
+    pub account: String,
+    pub id: String,
+    pub title: String,
+    pub label: String,
+    pub path: String,
+    pub action: PlanAction,
+    pub formats: Vec<FormatPlan>,
+    pub download_files: usize,
+    pub retag_files: usize,
+    /// Known bytes to download; files in `unknown_sizes` are not included.
+    pub bytes: u64,
+    pub unknown_sizes: usize,
+}
+
+impl AlbumPlan {
+    fn new(
+        account: &str,
+        disc_info: &DiscInfo,
+        album_dir: &Path,
+        formats: Vec<FormatPlan>,
+    ) -> Self {
+        let mut plan = Self {
+            account: account.to_string(),
+            id: disc_info.id.clone(),
+            title: disc_info.title.clone(),
+            label: disc_info.label.clone(),
+            path: album_dir.display().to_string(),
+            action: PlanAction::Skip,
+            formats: Vec::new(),
+            download_files: 0,
+            retag_files: 0,
+            bytes: 0,
+            unknown_sizes: 0,
+        };
+        for format in &formats {
+            plan.action = plan.action.max(format.action);
+            for file in &format.files {
+                match file.action {
+                    PlanAction::Download => {
+                        plan.download_files += 1;
+                        match file.bytes {
+                            Some(bytes) => plan.bytes += bytes,
+                            None => plan.unknown_sizes += 1,
+                        }
+                    }
+                    PlanAction::Retag => plan.retag_files += 1,
+                    PlanAction::Skip => {}
+                }
+            }
+        }
+        plan.formats = formats;
+        plan
+    }
+}
+
+/// The plan for a whole sync, with totals over every album.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncPlan {
+    pub albums: Vec<AlbumPlan>,
+    pub download_albums: usize,
+    pub retag_albums: usize,
+    pub skip_albums: usize,
+    pub download_files: usize,
+    pub retag_files: usize,
+    pub bytes: u64,
+    pub unknown_sizes: usize,
+    /// Albums that could not be planned, which the totals leave out.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<PlanError>,
+}
+
+/// An album whose details could not be fetched, or an account whose album
+/// list could not (without `id`).
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub error: String,
+}
+
+impl SyncPlan {
+    pub fn push(&mut self, album: AlbumPlan) {
+        match album.action {
+            PlanAction::Download => self.download_albums += 1,
+            PlanAction::Retag => self.retag_albums += 1,
+            PlanAction::Skip => self.skip_albums += 1,
+        }
+        self.download_files += album.download_files;
+        self.retag_files += album.retag_files;
+        self.bytes += album.bytes;
+        self.unknown_sizes += album.unknown_sizes;
+        self.albums.push(album);
+    }
+
+    pub fn fail(&mut self, id: Option<&str>, error: impl std::fmt::Display) {
+        self.errors.push(PlanError {
+            id: id.map(str::to_string),
+            error: error.to_string(),
+        });
+    }
+}
+
+impl Downloader {
+    /// Work out what `download_album` would do with the album given the files
+    /// already on disk, without writing anything. Download links are resolved
+    /// and sized with HEAD requests, so this still talks to Dizzylab.
+    pub async fn plan_album(&self, account: &str, disc_info: &DiscInfo) -> AlbumPlan {
+        let album_dir = self.get_album_directory(disc_info);
+        let mut formats = Vec::new();
+        for format in &self.config.download.formats {
+            let plan = if self.config.behavior.metadata_only {
+                FormatPlan::skipped(format, "metadata_only")
+            } else {
+                match format.as_str() {
+                    "gift" => self.plan_gift(disc_info, &album_dir).await,
+                    "FLAC" => self.plan_flac(disc_info, &album_dir).await,
+                    _ => self.plan_tracks(disc_info, format, &album_dir).await,
+                }
+            };
+            formats.push(plan);
+        }
+        AlbumPlan::new(account, disc_info, &album_dir, formats)
+    }
+
+    async fn plan_gift(&self, disc_info: &DiscInfo, album_dir: &Path) -> FormatPlan {
+        if !disc_info.hasgift {
+            return FormatPlan::skipped("gift", "unavailable");
+        }
+        let has_files = fs::read_dir(album_dir.join("gift"))
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(false);
+        if self.config.behavior.skip_existing && has_files {
+            return FormatPlan::skipped("gift", "exists");
+        }
+
+        let bytes = match self.client.get_gift_download_link(&disc_info.id).await {
+            Ok(links) => match links.get("gift") {
+                Some(url) => self.estimate_size(url, &disc_info.id).await,
+                None => return FormatPlan::skipped("gift", "unavailable"),
+            },
+            Err(e) => {
+                debug!("获取 gift 下载链接失败: {}", e);
+                None
+            }
+        };
+        FormatPlan::from_files("gift", vec![download("gift.zip", bytes)])
+    }
+
+    async fn plan_flac(&self, disc_info: &DiscInfo, album_dir: &Path) -> FormatPlan {
+        let mut flac_files: Vec<_> = fs::read_dir(album_dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|path| {
+                        path.extension()
+                            .and_then(|x| x.to_str())
+                            .is_some_and(|x| x.eq_ignore_ascii_case("flac"))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        flac_files.sort();
+
+        if self.config.behavior.skip_existing && !flac_files.is_empty() {
+            // Same pairing as `tag_flac_files`: sort order against the track list.
+            let files = flac_files
+                .iter()
+                .take(disc_info.tracks.len())
+                .map(|path| FilePlan {
+                    name: path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                    action: if file_has_dizzylab_flac_tag(path, &disc_info.id) {
+                        PlanAction::Skip
+                    } else {
+                        PlanAction::Retag
+                    },
+                    bytes: None,
+                })
+                .collect();
+            return FormatPlan::from_files("FLAC", files);
+        }
+
+        let bytes = match self
+            .client
+            .get_web_format_download_link(&disc_info.id, "FLAC")
+            .await
+        {
+            Ok(url) => self.estimate_size(&url, &disc_info.id).await,
+            Err(e) => {
+                debug!("获取 FLAC 下载链接失败: {}", e);
+                None
+            }
+        };
+        FormatPlan::from_files("FLAC", vec![download("flac.zip", bytes)])
+    }
+
+    async fn plan_tracks(
+        &self,
+        disc_info: &DiscInfo,
+        format: &str,
+        album_dir: &Path,
+    ) -> FormatPlan {
+        if disc_info.tracks.is_empty() {
+            return FormatPlan::skipped(format, "no_tracks");
+        }
+
+        let mut files = Vec::new();
+        for (idx, track) in disc_info.tracks.iter().enumerate() {
+            let name = format!(
+                "{} {}.{}",
+                idx + 1,
+                self.sanitize_filename(&track.title),
+                format_to_extension(format)
+            );
+            let file_path = album_dir.join(&name);
+
+            if self.config.behavior.skip_existing && file_path.exists() {
+                let action = if file_has_dizzylab_tag(&file_path, &disc_info.id, format) {
+                    PlanAction::Skip
+                } else {
+                    PlanAction::Retag
+                };
+                files.push(FilePlan {
+                    name,
+                    action,
+                    bytes: None,
+                });
+                continue;
+            }
+
+            let bytes = match self
+                .client
+                .get_track_download_url(&disc_info.id, &track.id, format, &self.token)
+                .await
+            {
+                Ok(url) => self.estimate_size(&url, &disc_info.id).await,
+                Err(e) => {
+                    debug!("获取曲目 {} 下载链接失败: {}", track.title, e);
+                    None
+                }
+            };
+            files.push(download(&name, bytes));
+        }
+        FormatPlan::from_files(format, files)
+    }
+
+    async fn estimate_size(&self, url: &str, album_id: &str) -> Option<u64> {
+        match self.client.head_size(url, album_id).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                debug!("获取文件大小失败: {}", e);
+                None
+            }
+        }
+    }
+}
+
+fn download(name: &str, bytes: Option<u64>) -> FilePlan {
+    FilePlan {
+        name: name.to_string(),
+        action: PlanAction::Download,
+        bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(action: PlanAction, bytes: Option<u64>) -> FilePlan {
+        FilePlan {
+            name: String::new(),
+            action,
+            bytes,
+        }
+    }
+
+    #[test]
+    fn test_plan_totals() {
+        let disc_info: DiscInfo =
+            serde_json::from_value(serde_json::json!({ "id": "dts" })).unwrap();
+        let tracks = FormatPlan::from_files(
+            "320",
+            vec![
+                file(PlanAction::Skip, None),
+                file(PlanAction::Retag, None),
+                file(PlanAction::Download, Some(100)),
+                file(PlanAction::Download, None),
+            ],
+        );
+        assert_eq!(tracks.action, PlanAction::Download);
+        assert_eq!(tracks.reason, None);
+        let complete = FormatPlan::from_files("FLAC", vec![file(PlanAction::Skip, None)]);
+        assert_eq!(complete.reason.as_deref(), Some("exists"));
+
+        let mut plan = SyncPlan::default();
+        plan.push(AlbumPlan::new(
+            "a",
+            &disc_info,
+            Path::new("out"),
+            vec![tracks, complete],
+        ));
+        plan.push(AlbumPlan::new(
+            "a",
+            &disc_info,
+            Path::new("out"),
+            vec![FormatPlan::skipped("gift", "unavailable")],
+        ));
+        assert_eq!(
+            (plan.download_albums, plan.retag_albums, plan.skip_albums),
+            (1, 0, 1)
+        );
+        assert_eq!((plan.download_files, plan.retag_files), (2, 1));
+        assert_eq!((plan.bytes, plan.unknown_sizes), (100, 1));
+    }
+}
@@ -1,8 +1,11 @@
-use super::Downloader;
+use super::{emit_format_skipped, Downloader};
 use crate::archive::filetime_from_http_date;
 use crate::metadata::{extract_year_from_date, normalize_date};
+use crate::metrics;
+use crate::progress::{self, SyncEvent};
+use crate::shutdown;
 use crate::types::{DiscInfo, Track};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use filetime::set_file_times;
 use id3::TagLike;
 use std::path::Path;
@@ -14,18 +17,20 @@ impl Downloader {
         disc_info: &DiscInfo,
         format: &str,
         album_dir: &Path,
-    ) -> Result<()> {
+    ) -> Result<usize> {
         if format == "FLAC" {
             warn!(
                 "FLAC 格式暂不支持通过 API 下载，请前往 https://www.dizzylab.net/d/{}/ 手动下载 - {}",
                 disc_info.id, disc_info.title
             );
-            return Ok(());
+            emit_format_skipped(disc_info, format, "unsupported");
+            return Ok(0);
         }
 
         if disc_info.tracks.is_empty() {
             warn!("专辑 {} 没有曲目信息，跳过格式 {}", disc_info.title, format);
-            return Ok(());
+            emit_format_skipped(disc_info, format, "no_tracks");
+            return Ok(0);
         }
 
         let target_dir = album_dir.to_path_buf();
@@ -39,8 +44,17 @@ impl Downloader {
             disc_info.title
         );
 
+        let mut downloaded = 0;
+        let mut failed = Vec::new();
         for (idx, track) in disc_info.tracks.iter().enumerate() {
+            shutdown::check()?;
             let track_num = idx + 1;
+            progress::emit(SyncEvent::TrackStarted {
+                album_id: disc_info.id.clone(),
+                format: format.to_string(),
+                track: track_num,
+                title: track.title.clone(),
+            });
             let file_name = format!(
                 "{} {}.{}",
                 track_num,
@@ -58,24 +72,7 @@ impl Downloader {
                 }
                 // File exists but lacks our tags (e.g. old download) — re-tag only.
                 debug!("文件已存在但缺少标签，补写标签: {}", file_name);
-                let saved_mtime = std::fs::metadata(&file_path)
-                    .ok()
-                    .map(|m| filetime::FileTime::from_last_modification_time(&m));
-                if let Err(e) = write_mp3_tags(
-                    &file_path,
-                    disc_info,
-                    track,
-                    track_num as u32,
-                    disc_info.tracks.len() as u32,
-                    &cover_path,
-                    format,
-                ) {
-                    warn!("写入ID3标签失败 {}: {}", file_name, e);
-                } else if let Some(ft) = saved_mtime {
-                    if let Err(e) = set_file_times(&file_path, ft, ft) {
-                        warn!("还原MP3时间戳失败 {}: {}", file_name, e);
-                    }
-                }
+                retag_mp3(&file_path, disc_info, track, track_num, &cover_path, format);
                 continue;
             }
 
@@ -88,14 +85,24 @@ impl Downloader {
                 Ok(url) => url,
                 Err(e) => {
                     warn!("获取曲目 {} 下载链接失败: {}", track.title, e);
+                    failed.push(track.title.as_str());
                     continue;
                 }
             };
 
-            let last_modified = match self.client.stream_to_file(&cdn_url, &file_path).await {
-                Ok(lm) => lm,
+            let last_modified = match self
+                .client
+                .stream_to_file(&cdn_url, &disc_info.id, &file_path)
+                .await
+            {
+                Ok(lm) => {
+                    metrics::tracks_downloaded(1);
+                    downloaded += 1;
+                    lm
+                }
                 Err(e) => {
                     warn!("下载曲目 {} 失败: {}", track.title, e);
+                    failed.push(track.title.as_str());
                     continue;
                 }
             };
@@ -125,11 +132,92 @@ impl Downloader {
             tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
         }
 
-        Ok(())
+        // Tracks that did arrive are kept; the format still counts as failed.
+        if !failed.is_empty() {
+            return Err(anyhow!(
+                "{}/{} 首曲目下载失败（已下载 {} 首）: {}",
+                failed.len(),
+                disc_info.tracks.len(),
+                downloaded,
+                failed.join("、")
+            ));
+        }
+        Ok(downloaded)
+    }
+}
+
+impl Downloader {
+    /// Rewrite the tags of the album's existing audio files from `disc_info`
+    /// without downloading anything. Returns how many MP3 files were tagged;
+    /// FLAC files go through `tag_flac_files`, which honours `skip_existing`.
+    pub fn retag_album(&self, disc_info: &DiscInfo) -> usize {
+        let album_dir = self.get_album_directory(disc_info);
+        if !album_dir.is_dir() {
+            return 0;
+        }
+        let cover_path = cover_path_for_disc(disc_info, &album_dir);
+        let mut tagged = 0;
+        for format in self
+            .config
+            .download
+            .formats
+            .iter()
+            .filter(|format| matches!(format.as_str(), "128" | "320"))
+        {
+            for (idx, track) in disc_info.tracks.iter().enumerate() {
+                let file_path = album_dir.join(format!(
+                    "{} {}.{}",
+                    idx + 1,
+                    self.sanitize_filename(&track.title),
+                    format_to_extension(format)
+                ));
+                if file_path.exists()
+                    && retag_mp3(&file_path, disc_info, track, idx + 1, &cover_path, format)
+                {
+                    tagged += 1;
+                }
+            }
+        }
+        if self.config.download.formats.iter().any(|f| f == "FLAC") {
+            self.tag_flac_files(disc_info, &album_dir);
+        }
+        tagged
+    }
+}
+
+/// Write ID3 tags to an already downloaded MP3, keeping its modification time.
+fn retag_mp3(
+    file_path: &Path,
+    disc_info: &DiscInfo,
+    track: &Track,
+    track_num: usize,
+    cover_path: &Path,
+    format: &str,
+) -> bool {
+    let saved_mtime = std::fs::metadata(file_path)
+        .ok()
+        .map(|m| filetime::FileTime::from_last_modification_time(&m));
+    if let Err(e) = write_mp3_tags(
+        file_path,
+        disc_info,
+        track,
+        track_num as u32,
+        disc_info.tracks.len() as u32,
+        cover_path,
+        format,
+    ) {
+        warn!("写入ID3标签失败 {}: {}", file_path.display(), e);
+        return false;
+    }
+    if let Some(ft) = saved_mtime {
+        if let Err(e) = set_file_times(file_path, ft, ft) {
+            warn!("还原MP3时间戳失败 {}: {}", file_path.display(), e);
+        }
     }
+    true
 }
 
-fn format_to_extension(format: &str) -> &str {
+pub(super) fn format_to_extension(format: &str) -> &str {
     match format {
         "128" | "320" => "mp3",
         "FLAC" => "flac",
@@ -155,7 +243,7 @@ fn parse_release_date(date_str: &str) -> Option<id3::Timestamp> {
     })
 }
 
-fn file_has_dizzylab_tag(file_path: &Path, disc_id: &str, format: &str) -> bool {
+pub(super) fn file_has_dizzylab_tag(file_path: &Path, disc_id: &str, format: &str) -> bool {
     let Ok(tag) = id3::Tag::read_from_path(file_path) else {
         return false;
     };
@@ -263,7 +351,7 @@ impl Downloader {
     }
 }
 
-fn file_has_dizzylab_flac_tag(file_path: &Path, disc_id: &str) -> bool {
+pub(super) fn file_has_dizzylab_flac_tag(file_path: &Path, disc_id: &str) -> bool {
     let Ok(tag) = metaflac::Tag::read_from_path(file_path) else {
         return false;
     };
@@ -1,5 +1,6 @@
-use super::Downloader;
-use crate::archive::{self, ArchiveFormat};
+use super::{emit_format_skipped, Downloader};
+use crate::archive;
+use crate::metrics;
 use crate::types::DiscInfo;
 use anyhow::{anyhow, Result};
 use std::fs;
@@ -12,30 +13,20 @@ impl Downloader {
         disc_info: &DiscInfo,
         format: &str,
         album_dir: &Path,
-    ) -> Result<()> {
+    ) -> Result<usize> {
         let target_dir = album_dir.to_path_buf();
+        let audio_ext = match format {
+            "FLAC" => "flac",
+            "128" | "320" => "mp3",
+            _ => "",
+        };
 
         let mut skip_download = false;
-        if self.config.behavior.skip_existing && target_dir.exists() {
-            let ext = match format {
-                "FLAC" => "flac",
-                "128" | "320" => "mp3",
-                _ => "",
-            };
-            if !ext.is_empty() {
-                if let Ok(entries) = fs::read_dir(&target_dir) {
-                    let has_audio = entries.filter_map(|e| e.ok()).any(|e| {
-                        e.file_name()
-                            .to_str()
-                            .map(|n| n.to_lowercase().ends_with(&format!(".{ext}")))
-                            .unwrap_or(false)
-                    });
-                    if has_audio {
-                        info!("格式 {} 已存在，跳过下载 - {}", format, disc_info.title);
-                        skip_download = true;
-                    }
-                }
-            }
+        let mut downloaded = 0;
+        if self.config.behavior.skip_existing && has_audio_files(&target_dir, audio_ext) {
+            info!("格式 {} 已存在，跳过下载 - {}", format, disc_info.title);
+            emit_format_skipped(disc_info, format, "exists");
+            skip_download = true;
         }
 
         if !skip_download {
@@ -57,52 +48,45 @@ impl Downloader {
                 .stream_file_to_path(&download_url, &disc_info.id, &archive_path)
                 .await?;
 
-            match archive::detect_archive_format_from_path(&archive_path) {
-                ArchiveFormat::Zip => {
-                    archive::extract_zip_from_path(&archive_path, format, album_dir)?;
-                    if let Err(e) = fs::remove_file(&archive_path) {
-                        warn!("删除归档文件失败 {}: {}", archive_path.display(), e);
-                    }
-                }
-                ArchiveFormat::Rar => {
-                    archive::extract_rar_from_path(&archive_path, format, album_dir)?;
-                    if let Err(e) = fs::remove_file(&archive_path) {
-                        warn!("删除归档文件失败 {}: {}", archive_path.display(), e);
-                    }
-                }
-                ArchiveFormat::Unknown => {
-                    // Leave the file as-is (unknown binary payload).
-                }
-            }
+            let extracted = extract_archive(&archive_path, format, album_dir).await?;
+            let tracks = extracted
+                .iter()
+                .filter(|path| has_extension(path, audio_ext))
+                .count();
+            metrics::tracks_downloaded(tracks as u64);
+            downloaded = 1;
         }
 
         if format == "FLAC" {
             self.tag_flac_files(disc_info, album_dir);
         }
 
-        Ok(())
+        Ok(downloaded)
     }
 
-    pub(super) async fn download_gift(&self, disc_info: &DiscInfo, album_dir: &Path) -> Result<()> {
+    pub(super) async fn download_gift(
+        &self,
+        disc_info: &DiscInfo,
+        album_dir: &Path,
+    ) -> Result<usize> {
         if !disc_info.hasgift {
             info!("专辑 {} 没有特典内容，跳过", disc_info.title);
-            return Ok(());
+            emit_format_skipped(disc_info, "gift", "unavailable");
+            return Ok(0);
         }
 
         let target_dir = album_dir.join("gift");
 
-        if self.config.behavior.skip_existing && target_dir.exists() {
-            if let Ok(entries) = fs::read_dir(&target_dir) {
-                if entries.count() > 0 {
-                    info!("gift 已存在，跳过下载 - {}", disc_info.title);
-                    return Ok(());
-                }
-            }
+        if self.config.behavior.skip_existing && has_gift(&target_dir) {
+            info!("gift 已存在，跳过下载 - {}", disc_info.title);
+            emit_format_skipped(disc_info, "gift", "exists");
+            return Ok(0);
         }
 
         let links = self.client.get_gift_download_link(&disc_info.id).await?;
         if links.is_empty() {
-            return Ok(());
+            emit_format_skipped(disc_info, "gift", "unavailable");
+            return Ok(0);
         }
 
         let download_url = links
@@ -121,25 +105,9 @@ impl Downloader {
             .stream_file_to_path(download_url, &disc_info.id, &archive_path)
             .await?;
 
-        match archive::detect_archive_format_from_path(&archive_path) {
-            ArchiveFormat::Zip => {
-                archive::extract_zip_from_path(&archive_path, "gift", album_dir)?;
-                if let Err(e) = fs::remove_file(&archive_path) {
-                    warn!("删除归档文件失败 {}: {}", archive_path.display(), e);
-                }
-            }
-            ArchiveFormat::Rar => {
-                archive::extract_rar_from_path(&archive_path, "gift", album_dir)?;
-                if let Err(e) = fs::remove_file(&archive_path) {
-                    warn!("删除归档文件失败 {}: {}", archive_path.display(), e);
-                }
-            }
-            ArchiveFormat::Unknown => {
-                // Leave the file as-is (unknown binary payload).
-            }
-        }
+        extract_archive(&archive_path, "gift", album_dir).await?;
 
-        Ok(())
+        Ok(1)
     }
 
     /// After gift extraction, find LRC files in the `gift/` subdirectory and copy them
@@ -199,6 +167,46 @@ impl Downloader {
     }
 }
 
+/// Extract a downloaded archive into `album_dir` and delete it, also when
+/// extraction fails so the next sync downloads it again. Runs on the blocking
+/// pool so the shutdown grace timer can still fire mid-extraction.
+/// Unknown payloads are left as-is.
+async fn extract_archive(
+    archive_path: &Path,
+    format: &str,
+    album_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let (path, format, dir) = (
+        archive_path.to_path_buf(),
+        format.to_string(),
+        album_dir.to_path_buf(),
+    );
+    let extracted =
+        tokio::task::spawn_blocking(move || archive::extract_staged(&path, &format, &dir))
+            .await
+            .map_err(|e| anyhow!("解压任务异常: {e}"))?;
+    if !matches!(extracted, Ok(None)) {
+        if let Err(e) = fs::remove_file(archive_path) {
+            warn!("删除归档文件失败 {}: {}", archive_path.display(), e);
+        }
+    }
+    Ok(extracted?.unwrap_or_default())
+}
+
+/// Whether `dir` holds `.ext` files, so `skip_existing` can skip the format.
+fn has_audio_files(dir: &Path, ext: &str) -> bool {
+    fs::read_dir(dir).is_ok_and(|entries| {
+        entries
+            .flatten()
+            .any(|entry| has_extension(&entry.path(), ext))
+    })
+}
+
+/// Whether the gift archive was extracted into `dir` before.
+fn has_gift(dir: &Path) -> bool {
+    fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some())
+}
+
 struct ParsedLrc {
     track_num: Option<usize>,
     title: String,
@@ -251,6 +259,15 @@ fn normalize_title(s: &str) -> String {
         .to_lowercase()
 }
 
+/// Whether `path` ends in `.ext`, ignoring case; never true for an empty `ext`.
+fn has_extension(path: &Path, ext: &str) -> bool {
+    !ext.is_empty()
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+}
+
 /// Recursively collect every `.lrc` file under `dir`.
 fn collect_lrc_files(dir: &Path) -> Vec<PathBuf> {
     let mut result = Vec::new();
@@ -272,3 +289,71 @@ fn collect_lrc_files(dir: &Path) -> Vec<PathBuf> {
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shutdown;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (name, data) in entries {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_interrupted_extraction_is_downloaded_again() {
+        let dir = std::env::temp_dir().join(format!("dizzysync-extract-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let archive = dir.join("flac.zip");
+        let entries: &[(&str, &[u8])] = &[
+            ("1 First.flac", b"first track"),
+            ("2 Second.flac", b"second track"),
+        ];
+
+        // The second entry fails its checksum after the first was written.
+        write_zip(&archive, entries);
+        let mut bytes = fs::read(&archive).unwrap();
+        let at = bytes
+            .windows(12)
+            .position(|window| window == b"second track")
+            .unwrap();
+        bytes[at] ^= 0xff;
+        fs::write(&archive, bytes).unwrap();
+        assert!(archive::extract_staged(&archive, "FLAC", &dir).is_err());
+        assert!(!has_audio_files(&dir, "flac"));
+
+        // Shutdown gave up on in-flight files.
+        write_zip(&archive, entries);
+        shutdown::set_abandoned(true);
+        let result = archive::extract_staged(&archive, "gift", &dir);
+        shutdown::set_abandoned(false);
+        assert!(result.is_err());
+        assert!(!has_gift(&dir.join("gift")));
+
+        // The rerun extracts everything into place.
+        let extracted = archive::extract_staged(&archive, "FLAC", &dir)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            extracted,
+            [dir.join("1 First.flac"), dir.join("2 Second.flac")]
+        );
+        assert_eq!(
+            fs::read(dir.join("2 Second.flac")).unwrap(),
+            b"second track"
+        );
+        assert!(has_audio_files(&dir, "flac"));
+        assert!(!dir.join(".dizzysync-extract-flac").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+/// Failure categories with their own process exit codes, so scripts can tell
+/// a broken config from a flaky download. Errors without a category exit
+/// with 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// The config file is missing, unreadable or invalid.
+    Config,
+    /// No account could log in.
+    Auth,
+    /// Some albums or accounts failed, others were synced.
+    Partial,
+    /// Nothing could be synced.
+    Total,
+    /// The sync succeeded without downloading anything new.
+    NothingToDo,
+}
+
+impl Failure {
+    pub fn code(self) -> u8 {
+        match self {
+            Failure::Config => 3,
+            Failure::Auth => 4,
+            Failure::Partial => 5,
+            Failure::NothingToDo => 6,
+            Failure::Total => 7,
+        }
+    }
+
+    /// The name used by `--fail-on`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Failure::Config => "config",
+            Failure::Auth => "auth",
+            Failure::Partial => "partial",
+            Failure::Total => "total",
+            Failure::NothingToDo => "nothing-to-do",
+        }
+    }
+
+    pub fn error(self, message: impl Into<String>) -> anyhow::Error {
+        anyhow::Error::new(CategorizedError {
+            failure: self,
+            message: message.into(),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct CategorizedError {
+    failure: Failure,
+    message: String,
+}
+
+impl fmt::Display for CategorizedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CategorizedError {}
+
+/// Categorize the end of a sync from its counts: `failed` albums, accounts
+/// or requested IDs failed, `synced` albums were downloaded or already
+/// complete and `downloaded_bytes` were written. `None` means a plain success.
+pub fn classify_sync(failed: u64, synced: u64, downloaded_bytes: u64) -> Option<Failure> {
+    match (failed, synced, downloaded_bytes) {
+        (0, _, 0) => Some(Failure::NothingToDo),
+        (0, _, _) => None,
+        (_, 0, _) => Some(Failure::Total),
+        _ => Some(Failure::Partial),
+    }
+}
+
+/// The category an error was created with, if any.
+pub fn failure(error: &anyhow::Error) -> Option<Failure> {
+    error
+        .downcast_ref::<CategorizedError>()
+        .map(|error| error.failure)
+}
+
+pub fn code(error: &anyhow::Error) -> u8 {
+    failure(error).map_or(1, Failure::code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let error = Failure::Auth.error("登录失败");
+        assert_eq!(error.to_string(), "登录失败");
+        assert_eq!(failure(&error), Some(Failure::Auth));
+        assert_eq!(code(&error), 4);
+        assert_eq!(code(&error.context("同步失败")), 4);
+        assert_eq!(code(&anyhow::anyhow!("其他错误")), 1);
+    }
+
+    #[test]
+    fn test_classify_sync() {
+        // Every download failed: bytes of the failed tracks do not make it partial.
+        assert_eq!(classify_sync(3, 0, 0), Some(Failure::Total));
+        assert_eq!(classify_sync(3, 0, 1024), Some(Failure::Total));
+        assert_eq!(classify_sync(1, 2, 1024), Some(Failure::Partial));
+        // An up-to-date library with one failing album downloads nothing.
+        assert_eq!(classify_sync(1, 40, 0), Some(Failure::Partial));
+        assert_eq!(classify_sync(0, 40, 0), Some(Failure::NothingToDo));
+        assert_eq!(classify_sync(0, 2, 1024), None);
+        assert_eq!(Failure::Total.code(), 7);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::album_filter::AlbumFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// What a sync job covers. Used both as the queue entry payload and as the
+/// deduplication key: two queued jobs with the same kind are the same work.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobKind {
+    All,
+    Account {
+        username: String,
+    },
+    Album {
+        id: String,
+    },
+    /// Albums of every account selected by a filter, e.g. one label's catalog.
+    Filter(AlbumFilter),
+}
+
+impl JobKind {
+    pub fn label(&self) -> String {
+        match self {
+            JobKind::All => "all".to_string(),
+            JobKind::Account { username } => format!("account:{username}"),
+            JobKind::Album { id } => format!("album:{id}"),
+            JobKind::Filter(filter) => format!("filter:{}", filter.describe()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobTrigger {
+    Manual,
+    Scheduled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedJob {
+    pub id: u64,
+    pub kind: JobKind,
+    pub trigger: JobTrigger,
+    pub enqueued_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunningJob {
+    pub id: u64,
+    pub kind: JobKind,
+    pub trigger: JobTrigger,
+    pub started_at: u64,
+}
+
+pub enum Enqueued {
+    Added { job: QueuedJob, position: usize },
+    Duplicate { job: QueuedJob, position: usize },
+}
+
+/// FIFO of pending sync jobs plus the single job currently being executed.
+/// Jobs run one at a time; only queued (not running) work is deduplicated so a
+/// job can be re-queued while an identical one is in progress.
+#[derive(Debug, Default)]
+pub struct JobQueue {
+    next_id: u64,
+    running: Option<RunningJob>,
+    queued: VecDeque<QueuedJob>,
+}
+
+impl JobQueue {
+    pub fn enqueue(&mut self, kind: JobKind, trigger: JobTrigger, now: u64) -> Enqueued {
+        if let Some(position) = self.queued.iter().position(|job| job.kind == kind) {
+            return Enqueued::Duplicate {
+                job: self.queued[position].clone(),
+                position,
+            };
+        }
+
+        self.next_id += 1;
+        let job = QueuedJob {
+            id: self.next_id,
+            kind,
+            trigger,
+            enqueued_at: now,
+        };
+        self.queued.push_back(job.clone());
+        Enqueued::Added {
+            job,
+            position: self.queued.len() - 1,
+        }
+    }
+
+    /// Pop the next queued job and mark it as running. Returns `None` while a
+    /// job is already running or the queue is empty.
+    pub fn start_next(&mut self, now: u64) -> Option<RunningJob> {
+        if self.running.is_some() {
+            return None;
+        }
+        let job = self.queued.pop_front()?;
+        let running = RunningJob {
+            id: job.id,
+            kind: job.kind,
+            trigger: job.trigger,
+            started_at: now,
+        };
+        self.running = Some(running.clone());
+        Some(running)
+    }
+
+    pub fn finish(&mut self) -> Option<RunningJob> {
+        self.running.take()
+    }
+
+    pub fn remove(&mut self, id: u64) -> Option<QueuedJob> {
+        let position = self.queued.iter().position(|job| job.id == id)?;
+        self.queued.remove(position)
+    }
+
+    /// Move a queued job to `position` (clamped to the queue length).
+    /// Returns the resulting position, or `None` if the job is not queued.
+    pub fn move_to(&mut self, id: u64, position: usize) -> Option<usize> {
+        let job = self.remove(id)?;
+        let position = position.min(self.queued.len());
+        self.queued.insert(position, job);
+        Some(position)
+    }
+
+    pub fn running(&self) -> Option<&RunningJob> {
+        self.running.as_ref()
+    }
+
+    pub fn queued(&self) -> Vec<QueuedJob> {
+        self.queued.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enqueue_deduplicates_queued_album() {
+        let mut queue = JobQueue::default();
+        let album = JobKind::Album {
+            id: "dts".to_string(),
+        };
+        assert!(matches!(
+            queue.enqueue(album.clone(), JobTrigger::Manual, 0),
+            Enqueued::Added { position: 0, .. }
+        ));
+        assert!(matches!(
+            queue.enqueue(album.clone(), JobTrigger::Manual, 1),
+            Enqueued::Duplicate { position: 0, .. }
+        ));
+
+        // Once running, the same album may be queued again.
+        queue.start_next(2);
+        assert!(matches!(
+            queue.enqueue(album, JobTrigger::Manual, 3),
+            Enqueued::Added { position: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn test_move_and_remove_queued_jobs() {
+        let mut queue = JobQueue::default();
+        for id in ["a", "b", "c"] {
+            queue.enqueue(JobKind::Album { id: id.to_string() }, JobTrigger::Manual, 0);
+        }
+        assert_eq!(queue.move_to(3, 0), Some(0));
+        assert_eq!(queue.move_to(1, 99), Some(2));
+        let order = queue.queued().iter().map(|job| job.id).collect::<Vec<_>>();
+        assert_eq!(order, vec![3, 2, 1]);
+
+        assert!(queue.remove(2).is_some());
+        assert!(queue.remove(2).is_none());
+        assert_eq!(queue.start_next(0).map(|job| job.id), Some(3));
+        assert!(queue.start_next(0).is_none());
+    }
+}
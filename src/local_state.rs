@@ -7,6 +7,16 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 pub fn annotate_album_list(config: &Config, albums: &mut [DiscListItem]) {
+    annotate_album_list_with_details(config, albums, |_| None);
+}
+
+/// Like [`annotate_album_list`], but albums whose details `details` returns are
+/// checked track by track instead of by counting files.
+pub fn annotate_album_list_with_details(
+    config: &Config,
+    albums: &mut [DiscListItem],
+    mut details: impl FnMut(&str) -> Option<DiscInfo>,
+) {
     let index = build_album_index(&config.paths.output_dir);
     for album in albums {
         let expected_dir = album_directory_for_list_item(config, album);
@@ -21,11 +31,12 @@ pub fn annotate_album_list(config: &Config, albums: &mut [DiscListItem]) {
         if album.release_date.is_none() {
             album.release_date = read_album_release_date_from_metadata(&album_dir);
         }
+        let disc = details(&album.id);
         album.local = Some(album_state_from_dir(
             config,
             &album_dir,
             album.track_count,
-            None,
+            disc.as_ref(),
         ));
     }
 }
@@ -55,6 +66,8 @@ fn album_state_from_dir(
     let mut audio_files = 0usize;
     let mut gift_exists = false;
     let mut formats = BTreeMap::new();
+    // Files of the least complete format, so one full format cannot hide gaps in another.
+    let mut fewest_format_files: Option<usize> = None;
 
     if directory_exists {
         for format in config
@@ -63,12 +76,10 @@ fn album_state_from_dir(
             .iter()
             .filter(|format| format.as_str() != "gift")
         {
-            let present = match format.as_str() {
-                "FLAC" => count_extension(album_dir, "flac") > 0,
-                "128" | "320" => count_extension(album_dir, "mp3") > 0,
-                other => count_extension(album_dir, extension_for_format(other)) > 0,
-            };
-            formats.insert(format.clone(), present);
+            let files = count_extension(album_dir, extension_for_format(format));
+            formats.insert(format.clone(), files > 0);
+            fewest_format_files =
+                Some(fewest_format_files.map_or(files, |fewest| fewest.min(files)));
         }
         audio_files = count_audio_files(album_dir);
         gift_exists = album_dir.join("gift").is_dir();
@@ -106,10 +117,11 @@ fn album_state_from_dir(
                 })
         })
         .unwrap_or_else(|| {
+            let files = fewest_format_files.unwrap_or(audio_files);
             let estimated = if expected_tracks > 0 {
-                audio_files.min(expected_tracks)
+                files.min(expected_tracks)
             } else {
-                audio_files
+                files
             };
             (estimated, 0)
         });
@@ -134,7 +146,7 @@ fn album_state_from_dir(
     let complete = if expected_tracks > 0 && album.is_some() {
         complete_tracks >= expected_tracks && audio_formats_complete
     } else if expected_tracks > 0 {
-        audio_formats_complete && audio_files >= expected_tracks
+        audio_formats_complete && downloaded_tracks >= expected_tracks
     } else {
         false
     };
@@ -207,6 +219,11 @@ fn track_state_from_dir(
     }
 }
 
+/// Number of album directories under `output_dir` identified by their metadata files.
+pub fn library_album_count(config: &Config) -> usize {
+    build_album_index(&config.paths.output_dir).len()
+}
+
 fn build_album_index(output_dir: &Path) -> BTreeMap<String, PathBuf> {
     let mut index = BTreeMap::new();
     collect_album_index(output_dir, 0, &mut index);
@@ -0,0 +1,560 @@
+use crate::config::LoggingConfig;
+use anyhow::Result;
+use chrono::{Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub timestamp: u64,
+    pub level: &'static str,
+    pub message: String,
+    #[serde(flatten)]
+    pub context: LogContext,
+}
+
+/// Which sync job, account and album a log record belongs to, taken from the
+/// enclosing tracing spans.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_id: Option<String>,
+}
+
+impl LogContext {
+    pub fn job(job_id: u64) -> Self {
+        Self {
+            job_id: Some(job_id),
+            ..Self::default()
+        }
+    }
+
+    /// Fill unset fields from `other`; fields already set win.
+    pub fn merge(&mut self, other: &LogContext) {
+        if self.job_id.is_none() {
+            self.job_id = other.job_id;
+        }
+        if self.account.is_none() {
+            self.account = other.account.clone();
+        }
+        if self.album_id.is_none() {
+            self.album_id = other.album_id.clone();
+        }
+    }
+}
+
+/// On-disk form of [`LogEntry`]; the level is interned back to a static string.
+#[derive(Deserialize)]
+struct StoredLogEntry {
+    timestamp: u64,
+    level: String,
+    message: String,
+    #[serde(flatten)]
+    context: LogContext,
+}
+
+impl From<StoredLogEntry> for LogEntry {
+    fn from(stored: StoredLogEntry) -> Self {
+        Self {
+            timestamp: stored.timestamp,
+            level: static_level(&stored.level),
+            message: stored.message,
+            context: stored.context,
+        }
+    }
+}
+
+const FILE_PREFIX: &str = "dizzysync-";
+const FILE_SUFFIX: &str = ".log";
+
+static SINK: OnceLock<Mutex<Option<LogFileSink>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rotation {
+    Daily,
+    Size,
+}
+
+struct LogFileSink {
+    dir: PathBuf,
+    rotation: Rotation,
+    max_bytes: u64,
+    retention: Option<Duration>,
+    max_files: usize,
+    current: Option<OpenSegment>,
+}
+
+struct OpenSegment {
+    file: File,
+    date: NaiveDate,
+    size: u64,
+}
+
+fn sink() -> &'static Mutex<Option<LogFileSink>> {
+    SINK.get_or_init(|| Mutex::new(None))
+}
+
+/// Resolve the log directory. An empty `dir` places logs next to the config file,
+/// like the album cache.
+pub fn log_dir(config: &LoggingConfig, config_path: &str) -> PathBuf {
+    if !config.dir.as_os_str().is_empty() {
+        return config.dir.clone();
+    }
+    Path::new(config_path)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+        .join(".dizzysync-logs")
+}
+
+/// Install (or replace) the file sink. Entries logged before this call only live in memory.
+pub fn configure(config: &LoggingConfig, config_path: &str) -> Result<()> {
+    let mut guard = sink()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if !config.file_enabled {
+        *guard = None;
+        return Ok(());
+    }
+
+    let dir = log_dir(config, config_path);
+    fs::create_dir_all(&dir)?;
+    let rotation = match config.rotation.trim().to_ascii_lowercase().as_str() {
+        "size" => Rotation::Size,
+        _ => Rotation::Daily,
+    };
+    let sink = LogFileSink {
+        dir,
+        rotation,
+        max_bytes: config.max_size_mb.saturating_mul(1024 * 1024),
+        retention: (config.retention_days > 0)
+            .then(|| Duration::from_secs(config.retention_days * 24 * 60 * 60)),
+        max_files: config.max_files as usize,
+        current: None,
+    };
+    sink.prune();
+    *guard = Some(sink);
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    sink()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .is_some()
+}
+
+/// Append one entry to the active log file. Failures are reported on stderr only:
+/// logging them through `tracing` would feed back into this sink.
+pub fn append(entry: &LogEntry) {
+    let mut guard = sink()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(sink) = guard.as_mut() else {
+        return;
+    };
+    if let Err(e) = sink.write(entry) {
+        eprintln!("写入日志文件失败: {e}");
+        sink.current = None;
+    }
+}
+
+/// Persisted entries accepted by `keep`, oldest first.
+pub struct LogScan {
+    pub entries: Vec<LogEntry>,
+    /// Every match in range was read; otherwise older matches exist.
+    pub complete: bool,
+}
+
+/// Read persisted entries whose timestamps may fall in `[since, until]` and
+/// that `keep` accepts. Files are pruned by name (segment start date) and
+/// modification time, then read newest first until `needed` entries are found.
+pub fn read_entries(
+    since: Option<u64>,
+    until: Option<u64>,
+    needed: Option<usize>,
+    keep: impl Fn(&LogEntry) -> bool,
+) -> Result<LogScan> {
+    let dir = {
+        let guard = sink()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match guard.as_ref() {
+            Some(sink) => sink.dir.clone(),
+            None => {
+                return Ok(LogScan {
+                    entries: Vec::new(),
+                    complete: true,
+                })
+            }
+        }
+    };
+    scan_segments(&dir, since, until, needed, keep)
+}
+
+fn scan_segments(
+    dir: &Path,
+    since: Option<u64>,
+    until: Option<u64>,
+    needed: Option<usize>,
+    keep: impl Fn(&LogEntry) -> bool,
+) -> Result<LogScan> {
+    let mut files = list_log_files(dir)?;
+    files.sort_by_key(|path| std::cmp::Reverse(segment_key(path)));
+
+    let since_date = since.and_then(local_date);
+    let until_date = until.and_then(local_date);
+    let mut files = files
+        .into_iter()
+        .filter(|path| segment_in_range(path, since_date, until_date));
+    let mut chunks: Vec<Vec<LogEntry>> = Vec::new();
+    let mut found = 0;
+    for path in files.by_ref() {
+        let chunk = read_segment(&path, &keep, None)?;
+        found += chunk.len();
+        chunks.push(chunk);
+        if needed.is_some_and(|needed| found >= needed) {
+            break;
+        }
+    }
+    // Older files only count as more entries when one of them has a match.
+    let mut complete = true;
+    for path in files {
+        if !read_segment(&path, &keep, Some(1))?.is_empty() {
+            complete = false;
+            break;
+        }
+    }
+
+    let mut entries: Vec<LogEntry> = chunks.into_iter().rev().flatten().collect();
+    entries.sort_by_key(|entry| entry.timestamp);
+    Ok(LogScan { entries, complete })
+}
+
+/// Whether a segment may hold entries between the dates of `since` and `until`,
+/// judged by its name (start date) and modification time.
+fn segment_in_range(path: &Path, since: Option<NaiveDate>, until: Option<NaiveDate>) -> bool {
+    let Some(segment_date) = segment_date(path) else {
+        return false;
+    };
+    if until.is_some_and(|until| segment_date > until) {
+        return false;
+    }
+    if let Some(since) = since {
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .and_then(|duration| local_date(duration.as_secs()));
+        if modified.is_some_and(|modified| modified < since) {
+            return false;
+        }
+    }
+    true
+}
+
+/// The entries of one segment that `keep` accepts, stopping after `limit`.
+fn read_segment(
+    path: &Path,
+    keep: &impl Fn(&LogEntry) -> bool,
+    limit: Option<usize>,
+) -> Result<Vec<LogEntry>> {
+    let mut entries = Vec::new();
+    let reader = BufReader::new(File::open(path)?);
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if let Ok(entry) = serde_json::from_str::<StoredLogEntry>(&line) {
+            let entry = LogEntry::from(entry);
+            if keep(&entry) {
+                entries.push(entry);
+                if limit.is_some_and(|limit| entries.len() >= limit) {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+impl LogFileSink {
+    fn write(&mut self, entry: &LogEntry) -> std::io::Result<()> {
+        let today = Local::now().date_naive();
+        let needs_rotation = match (&self.current, self.rotation) {
+            (None, _) => true,
+            (Some(segment), Rotation::Daily) => segment.date != today,
+            (Some(segment), Rotation::Size) => self.max_bytes > 0 && segment.size >= self.max_bytes,
+        };
+        if needs_rotation {
+            self.open_segment(today)?;
+        }
+
+        let mut line = serde_json::to_string(entry)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        line.push('\n');
+        if let Some(segment) = self.current.as_mut() {
+            segment.file.write_all(line.as_bytes())?;
+            segment.size += line.len() as u64;
+        }
+        Ok(())
+    }
+
+    fn open_segment(&mut self, today: NaiveDate) -> std::io::Result<()> {
+        let path = match self.rotation {
+            Rotation::Daily => self.dir.join(format!("{FILE_PREFIX}{today}{FILE_SUFFIX}")),
+            Rotation::Size => self.next_size_segment(today),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        self.current = Some(OpenSegment {
+            file,
+            date: today,
+            size,
+        });
+        self.prune();
+        Ok(())
+    }
+
+    /// Size-rotated segments are `dizzysync-<date>.<n>.log`; reuse the newest one
+    /// for today while it still has room.
+    fn next_size_segment(&self, today: NaiveDate) -> PathBuf {
+        let mut index = 0;
+        loop {
+            let path = self
+                .dir
+                .join(format!("{FILE_PREFIX}{today}.{index}{FILE_SUFFIX}"));
+            let size = fs::metadata(&path).map(|metadata| metadata.len()).ok();
+            match size {
+                Some(size) if self.max_bytes > 0 && size >= self.max_bytes => index += 1,
+                _ => return path,
+            }
+        }
+    }
+
+    /// Delete files older than the retention period, then the oldest files
+    /// beyond `max_files`.
+    fn prune(&self) {
+        let Ok(mut files) = list_log_files(&self.dir) else {
+            return;
+        };
+        if let Some(retention) = self.retention {
+            let cutoff = SystemTime::now()
+                .checked_sub(retention)
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            files.retain(|path| {
+                let expired = fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .map(|modified| modified < cutoff)
+                    .unwrap_or(false);
+                !(expired && fs::remove_file(path).is_ok())
+            });
+        }
+        if self.max_files > 0 && files.len() > self.max_files {
+            files.sort_by_key(|path| segment_key(path));
+            let excess = files.len() - self.max_files;
+            for path in &files[..excess] {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+fn list_log_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    Ok(fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| segment_date(path).is_some())
+        .collect())
+}
+
+fn segment_date(path: &Path) -> Option<NaiveDate> {
+    let name = path.file_name()?.to_str()?;
+    let stem = name.strip_prefix(FILE_PREFIX)?.strip_suffix(FILE_SUFFIX)?;
+    NaiveDate::parse_from_str(stem.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// Sort key of a segment: its date, then the size-rotation index, so that
+/// `.10.log` follows `.9.log`.
+fn segment_key(path: &Path) -> (Option<NaiveDate>, u64) {
+    let index = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_suffix(FILE_SUFFIX))
+        .and_then(|stem| stem.rsplit_once('.'))
+        .and_then(|(_, index)| index.parse().ok())
+        .unwrap_or(0);
+    (segment_date(path), index)
+}
+
+fn local_date(timestamp: u64) -> Option<NaiveDate> {
+    Local
+        .timestamp_opt(timestamp as i64, 0)
+        .single()
+        .map(|datetime| datetime.date_naive())
+}
+
+fn static_level(level: &str) -> &'static str {
+    match level.to_ascii_lowercase().as_str() {
+        "error" => "error",
+        "warn" => "warn",
+        "debug" => "debug",
+        "trace" => "trace",
+        _ => "info",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filetime::{set_file_mtime, FileTime};
+
+    fn test_sink(name: &str, rotation: Rotation) -> LogFileSink {
+        let dir =
+            std::env::temp_dir().join(format!("dizzysync-logs-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        LogFileSink {
+            dir,
+            rotation,
+            max_bytes: 64,
+            retention: None,
+            max_files: 0,
+            current: None,
+        }
+    }
+
+    fn entry(message: &str) -> LogEntry {
+        LogEntry {
+            timestamp: 0,
+            level: "info",
+            message: message.to_string(),
+            context: LogContext::default(),
+        }
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = list_log_files(dir)
+            .unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_rollover_naming() {
+        let today = Local::now().date_naive();
+
+        let mut daily = test_sink("daily", Rotation::Daily);
+        daily.write(&entry("a")).unwrap();
+        daily.write(&entry("b")).unwrap();
+        assert_eq!(file_names(&daily.dir), [format!("dizzysync-{today}.log")]);
+        daily.current.as_mut().unwrap().date = today.pred_opt().unwrap();
+        daily.write(&entry("c")).unwrap();
+        assert_eq!(file_names(&daily.dir), [format!("dizzysync-{today}.log")]);
+
+        let mut sized = test_sink("size", Rotation::Size);
+        for _ in 0..3 {
+            sized
+                .write(&entry("a message long enough to fill a segment"))
+                .unwrap();
+        }
+        assert_eq!(
+            file_names(&sized.dir),
+            [
+                format!("dizzysync-{today}.0.log"),
+                format!("dizzysync-{today}.1.log"),
+                format!("dizzysync-{today}.2.log"),
+            ]
+        );
+        // A restart keeps appending to the newest segment with room.
+        sized.current = None;
+        assert_eq!(
+            sized.next_size_segment(today),
+            sized.dir.join(format!("dizzysync-{today}.3.log"))
+        );
+
+        let ten = Path::new("dizzysync-2024-01-01.10.log");
+        let nine = Path::new("dizzysync-2024-01-01.9.log");
+        assert!(segment_key(ten) > segment_key(nine));
+        fs::remove_dir_all(&daily.dir).unwrap();
+        fs::remove_dir_all(&sized.dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune_by_age_and_count() {
+        let mut sink = test_sink("prune", Rotation::Daily);
+        let old = FileTime::from_unix_time(
+            (SystemTime::now() - Duration::from_secs(10 * 24 * 60 * 60))
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i64,
+            0,
+        );
+        for day in 1..=5 {
+            let path = sink.dir.join(format!("dizzysync-2024-01-0{day}.log"));
+            fs::write(&path, "{}\n").unwrap();
+            if day <= 2 {
+                set_file_mtime(&path, old).unwrap();
+            }
+        }
+        fs::write(sink.dir.join("other.log"), "").unwrap();
+
+        sink.retention = Some(Duration::from_secs(7 * 24 * 60 * 60));
+        sink.prune();
+        assert_eq!(
+            file_names(&sink.dir),
+            [
+                "dizzysync-2024-01-03.log",
+                "dizzysync-2024-01-04.log",
+                "dizzysync-2024-01-05.log"
+            ]
+        );
+
+        sink.max_files = 2;
+        sink.prune();
+        assert_eq!(
+            file_names(&sink.dir),
+            ["dizzysync-2024-01-04.log", "dizzysync-2024-01-05.log"]
+        );
+        assert!(sink.dir.join("other.log").exists());
+        fs::remove_dir_all(&sink.dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_reports_more_only_for_older_matches() {
+        let sink = test_sink("scan", Rotation::Daily);
+        let line = |message: &str| serde_json::to_string(&entry(message)).unwrap() + "\n";
+        fs::write(
+            sink.dir.join("dizzysync-2024-01-02.log"),
+            line("job a") + &line("job b"),
+        )
+        .unwrap();
+        let older = sink.dir.join("dizzysync-2024-01-01.log");
+        fs::write(&older, line("other")).unwrap();
+        let jobs = |entry: &LogEntry| entry.message.starts_with("job");
+
+        let scan = scan_segments(&sink.dir, None, None, Some(1), jobs).unwrap();
+        assert_eq!(scan.entries.len(), 2);
+        assert!(scan.complete);
+
+        fs::write(&older, line("other") + &line("job c")).unwrap();
+        let scan = scan_segments(&sink.dir, None, None, Some(1), jobs).unwrap();
+        assert_eq!(scan.entries.len(), 2);
+        assert!(!scan.complete);
+        let scan = scan_segments(&sink.dir, None, None, Some(3), jobs).unwrap();
+        assert_eq!(scan.entries.len(), 3);
+        assert!(scan.complete);
+        fs::remove_dir_all(&sink.dir).unwrap();
+    }
+}
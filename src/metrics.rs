@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// Process-wide counters, fed by the client and downloader and rendered at
+/// `GET /metrics` in the Prometheus text exposition format.
+#[derive(Default)]
+struct Metrics {
+    albums_downloaded: AtomicU64,
+    albums_up_to_date: AtomicU64,
+    album_failures: AtomicU64,
+    tracks_downloaded: AtomicU64,
+    bytes_downloaded: AtomicU64,
+    login_failures: AtomicU64,
+    http_requests: Mutex<BTreeMap<(&'static str, String), u64>>,
+}
+
+/// Point-in-time values owned by the API server, sampled on each scrape.
+pub struct Gauges {
+    pub job_running: bool,
+    pub queue_length: usize,
+    pub next_scheduled_run: Option<u64>,
+    pub library_albums: usize,
+}
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::default)
+}
+
+pub fn album_downloaded(success: bool) {
+    let counter = if success {
+        &metrics().albums_downloaded
+    } else {
+        &metrics().album_failures
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn tracks_downloaded(count: u64) {
+    metrics()
+        .tracks_downloaded
+        .fetch_add(count, Ordering::Relaxed);
+}
+
+pub fn bytes_downloaded(bytes: u64) {
+    metrics()
+        .bytes_downloaded
+        .fetch_add(bytes, Ordering::Relaxed);
+}
+
+pub fn login_failed() {
+    metrics().login_failures.fetch_add(1, Ordering::Relaxed);
+}
+
+/// An album that was already complete, or skipped, and needed no downloads.
+pub fn album_up_to_date() {
+    metrics().albums_up_to_date.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Albums synced without failing in this process so far, whether they
+/// downloaded new files or were already complete.
+pub fn albums_synced() -> u64 {
+    let metrics = metrics();
+    metrics.albums_downloaded.load(Ordering::Relaxed)
+        + metrics.albums_up_to_date.load(Ordering::Relaxed)
+}
+
+/// Albums whose sync failed in this process so far.
+pub fn albums_failed() -> u64 {
+    metrics().album_failures.load(Ordering::Relaxed)
+}
+
+/// Bytes of tracks and archives downloaded by this process so far.
+pub fn downloaded_bytes() -> u64 {
+    metrics().bytes_downloaded.load(Ordering::Relaxed)
+}
+
+/// Count one request to Dizzylab. `status` is `None` when no response arrived.
+pub fn http_request(endpoint: &'static str, status: Option<u16>) {
+    let status = status.map_or_else(|| "error".to_string(), |status| status.to_string());
+    let mut requests = metrics()
+        .http_requests
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *requests.entry((endpoint, status)).or_default() += 1;
+}
+
+pub fn render(gauges: &Gauges) -> String {
+    let metrics = metrics();
+    let mut out = String::new();
+
+    write_value(
+        &mut out,
+        "dizzysync_albums_downloaded_total",
+        "counter",
+        "Albums synced successfully with newly downloaded files.",
+        metrics.albums_downloaded.load(Ordering::Relaxed),
+    );
+    write_value(
+        &mut out,
+        "dizzysync_albums_up_to_date_total",
+        "counter",
+        "Albums synced without downloading anything, as they were already complete.",
+        metrics.albums_up_to_date.load(Ordering::Relaxed),
+    );
+    write_value(
+        &mut out,
+        "dizzysync_album_failures_total",
+        "counter",
+        "Albums whose sync failed.",
+        metrics.album_failures.load(Ordering::Relaxed),
+    );
+    write_value(
+        &mut out,
+        "dizzysync_tracks_downloaded_total",
+        "counter",
+        "Audio tracks downloaded or extracted from archives.",
+        metrics.tracks_downloaded.load(Ordering::Relaxed),
+    );
+    write_value(
+        &mut out,
+        "dizzysync_downloaded_bytes_total",
+        "counter",
+        "Bytes transferred by file downloads.",
+        metrics.bytes_downloaded.load(Ordering::Relaxed),
+    );
+    write_value(
+        &mut out,
+        "dizzysync_login_failures_total",
+        "counter",
+        "Failed Dizzylab logins.",
+        metrics.login_failures.load(Ordering::Relaxed),
+    );
+
+    let requests = metrics
+        .http_requests
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .map(|((endpoint, status), count)| {
+            (
+                format!(
+                    "endpoint=\"{}\",status=\"{}\"",
+                    escape_label(endpoint),
+                    escape_label(status)
+                ),
+                *count,
+            )
+        })
+        .collect::<Vec<_>>();
+    write_metric(
+        &mut out,
+        "dizzysync_http_requests_total",
+        "counter",
+        "HTTP requests sent to Dizzylab, by endpoint and response status.",
+        &requests,
+    );
+
+    write_value(
+        &mut out,
+        "dizzysync_job_running",
+        "gauge",
+        "Whether a sync job is currently running.",
+        u64::from(gauges.job_running),
+    );
+    write_value(
+        &mut out,
+        "dizzysync_queue_length",
+        "gauge",
+        "Sync jobs waiting in the queue.",
+        gauges.queue_length as u64,
+    );
+    write_value(
+        &mut out,
+        "dizzysync_next_scheduled_run_timestamp_seconds",
+        "gauge",
+        "Unix time of the next scheduled sync, 0 when none is scheduled.",
+        gauges.next_scheduled_run.unwrap_or(0),
+    );
+    write_value(
+        &mut out,
+        "dizzysync_library_albums",
+        "gauge",
+        "Albums found in the local output directory.",
+        gauges.library_albums as u64,
+    );
+    out
+}
+
+fn write_value(out: &mut String, name: &str, kind: &str, help: &str, value: u64) {
+    write_metric(out, name, kind, help, &[(String::new(), value)]);
+}
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, samples: &[(String, u64)]) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    for (labels, value) in samples {
+        if labels.is_empty() {
+            let _ = writeln!(out, "{name} {value}");
+        } else {
+            let _ = writeln!(out, "{name}{{{labels}}} {value}");
+        }
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_labels_and_gauges() {
+        http_request("getmydisc", Some(200));
+        http_request("getmydisc", None);
+        let text = render(&Gauges {
+            job_running: true,
+            queue_length: 2,
+            next_scheduled_run: None,
+            library_albums: 5,
+        });
+        assert!(text.contains("# TYPE dizzysync_http_requests_total counter\n"));
+        assert!(
+            text.contains("dizzysync_http_requests_total{endpoint=\"getmydisc\",status=\"200\"} ")
+        );
+        assert!(text
+            .contains("dizzysync_http_requests_total{endpoint=\"getmydisc\",status=\"error\"} "));
+        assert!(text.contains("dizzysync_job_running 1\n"));
+        assert!(text.contains("dizzysync_queue_length 2\n"));
+        assert!(text.contains("dizzysync_next_scheduled_run_timestamp_seconds 0\n"));
+        assert_eq!(escape_label("a\"b\\"), "a\\\"b\\\\");
+    }
+}
@@ -0,0 +1,379 @@
+use crate::config::{Config, WebhookConfig};
+use crate::progress::{self, ProgressEvent, SyncEvent};
+use crate::report::{ReportCollector, ReportUpdate};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, RwLock};
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// How long [`flush`] waits at shutdown; retries still pending after that are dropped.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Flush requests for the dispatcher started by [`start`].
+static FLUSH: OnceLock<mpsc::Sender<oneshot::Sender<()>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyEvent {
+    AlbumDownloaded,
+    JobFailed,
+    JobFinished,
+    LoginFailed,
+}
+
+impl NotifyEvent {
+    const ALL: [NotifyEvent; 4] = [
+        NotifyEvent::AlbumDownloaded,
+        NotifyEvent::JobFailed,
+        NotifyEvent::JobFinished,
+        NotifyEvent::LoginFailed,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotifyEvent::AlbumDownloaded => "album_downloaded",
+            NotifyEvent::JobFailed => "job_failed",
+            NotifyEvent::JobFinished => "job_finished",
+            NotifyEvent::LoginFailed => "login_failed",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|event| event.as_str() == value.trim())
+    }
+}
+
+struct Notification {
+    event: NotifyEvent,
+    payload: Value,
+}
+
+pub fn validate_webhooks(config: &Config) -> Result<()> {
+    for webhook in &config.notify.webhooks {
+        let url = webhook.url.trim();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(anyhow!(
+                "webhook url 必须以 http:// 或 https:// 开头: \"{}\"",
+                url
+            ));
+        }
+        for event in &webhook.events {
+            if NotifyEvent::parse(event).is_none() {
+                return Err(anyhow!(
+                    "不支持的 webhook 事件 \"{}\"；可选值为 album_downloaded、job_failed、job_finished、login_failed",
+                    event
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn wants(webhook: &WebhookConfig, event: NotifyEvent) -> bool {
+    webhook.events.is_empty()
+        || webhook
+            .events
+            .iter()
+            .any(|name| NotifyEvent::parse(name) == Some(event))
+}
+
+/// Follow the lossless report stream and POST matching notifications to the
+/// configured webhooks. The webhook list is re-read for every notification so
+/// config edits apply without a restart.
+pub fn start(config: Arc<RwLock<Config>>) {
+    let Some(mut events) = progress::subscribe_reports() else {
+        warn!("webhook 通知已在运行");
+        return;
+    };
+    let client = match reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("无法创建 webhook HTTP 客户端: {}", e);
+            return;
+        }
+    };
+
+    let (flush_requests, mut flushes) = mpsc::channel::<oneshot::Sender<()>>(1);
+    let _ = FLUSH.set(flush_requests);
+    tokio::spawn(async move {
+        let mut dispatcher = Dispatcher {
+            config,
+            client,
+            collector: ReportCollector::default(),
+            deliveries: JoinSet::new(),
+        };
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(event) => dispatcher.dispatch(&event).await,
+                    None => break,
+                },
+                Some(done) = flushes.recv() => {
+                    dispatcher.flush(&mut events).await;
+                    let _ = done.send(());
+                }
+                Some(_) = dispatcher.deliveries.join_next(), if !dispatcher.deliveries.is_empty() => {}
+            }
+        }
+    });
+}
+
+/// Wait, at most [`FLUSH_TIMEOUT`], until the notifications for every event
+/// emitted so far are delivered, so that exiting does not lose the report of
+/// the job that was just interrupted.
+pub async fn flush() {
+    let Some(flush_requests) = FLUSH.get() else {
+        return;
+    };
+    let (done, delivered) = oneshot::channel();
+    if flush_requests.send(done).await.is_err() {
+        return;
+    }
+    if tokio::time::timeout(FLUSH_TIMEOUT, delivered)
+        .await
+        .is_err()
+    {
+        warn!(
+            "等待 webhook 通知发送超过 {} 秒，剩余通知可能未送达",
+            FLUSH_TIMEOUT.as_secs()
+        );
+    }
+}
+
+struct Dispatcher {
+    config: Arc<RwLock<Config>>,
+    client: reqwest::Client,
+    collector: ReportCollector,
+    deliveries: JoinSet<()>,
+}
+
+impl Dispatcher {
+    async fn dispatch(&mut self, event: &ProgressEvent) {
+        let notifications = notifications_for(&mut self.collector, event);
+        if notifications.is_empty() {
+            return;
+        }
+        let webhooks = self.config.read().await.notify.webhooks.clone();
+        for notification in notifications {
+            for webhook in webhooks
+                .iter()
+                .filter(|webhook| wants(webhook, notification.event))
+            {
+                self.deliveries.spawn(deliver(
+                    self.client.clone(),
+                    webhook.clone(),
+                    notification.payload.clone(),
+                ));
+            }
+        }
+    }
+
+    /// Dispatch the events already queued, then wait for every delivery.
+    async fn flush(&mut self, events: &mut mpsc::UnboundedReceiver<ProgressEvent>) {
+        while let Ok(event) = events.try_recv() {
+            self.dispatch(&event).await;
+        }
+        while self.deliveries.join_next().await.is_some() {}
+    }
+}
+
+fn notifications_for(collector: &mut ReportCollector, event: &ProgressEvent) -> Vec<Notification> {
+    let timestamp = event.timestamp;
+    if let SyncEvent::LoginFailed { account, error } = &event.event {
+        return vec![Notification {
+            event: NotifyEvent::LoginFailed,
+            payload: json!({
+                "event": NotifyEvent::LoginFailed.as_str(),
+                "timestamp": timestamp,
+                "message": format!("账号 {account} 登录失败：{error}"),
+                "account": account,
+                "error": error,
+            }),
+        }];
+    }
+
+    match collector.observe(event) {
+        Some(ReportUpdate::AlbumFinished(album)) if album.is_new() => vec![Notification {
+            event: NotifyEvent::AlbumDownloaded,
+            payload: json!({
+                "event": NotifyEvent::AlbumDownloaded.as_str(),
+                "timestamp": timestamp,
+                "message": format!("已下载新专辑：{}", album.title),
+                "album": album,
+            }),
+        }],
+        Some(ReportUpdate::JobFinished(report)) => {
+            let mut notifications = Vec::new();
+            if !report.success {
+                notifications.push(Notification {
+                    event: NotifyEvent::JobFailed,
+                    payload: json!({
+                        "event": NotifyEvent::JobFailed.as_str(),
+                        "timestamp": timestamp,
+                        "message": format!(
+                            "同步任务 #{} {}：{}",
+                            report.job_id,
+                            if report.interrupted { "已中断" } else { "失败" },
+                            report.error.as_deref().unwrap_or_default()
+                        ),
+                        "report": report,
+                    }),
+                });
+            }
+            let message = format!(
+                "同步任务 #{} 已结束：新下载 {} 个专辑，{} 个专辑失败",
+                report.job_id,
+                report.new_albums().count(),
+                report.failed_albums().count()
+            );
+            notifications.push(Notification {
+                event: NotifyEvent::JobFinished,
+                payload: json!({
+                    "event": NotifyEvent::JobFinished.as_str(),
+                    "timestamp": timestamp,
+                    "message": message,
+                    "report": report,
+                }),
+            });
+            notifications
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// POST one payload, retrying with exponential backoff on network errors, 5xx
+/// and 429. Other 4xx responses mean a misconfigured endpoint and are final.
+async fn deliver(client: reqwest::Client, webhook: WebhookConfig, payload: Value) {
+    let url = webhook.url.trim();
+    let mut delay = Duration::from_secs(1);
+    let mut attempt = 0;
+    loop {
+        let mut request = client.post(url).json(&payload);
+        for (name, value) in &webhook.headers {
+            request = request.header(name, value);
+        }
+        let result = match request.send().await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) if !retryable(response.status()) => {
+                warn!(
+                    "webhook 发送失败，不再重试: {} -> {}: HTTP {}",
+                    payload["event"],
+                    url,
+                    response.status()
+                );
+                return;
+            }
+            Ok(response) => Err(format!("HTTP {}", response.status())),
+            Err(e) => Err(e.to_string()),
+        };
+
+        match result {
+            Ok(()) => {
+                debug!("webhook 已发送: {} -> {}", payload["event"], url);
+                return;
+            }
+            Err(e) if attempt >= webhook.retries => {
+                warn!(
+                    "webhook 发送失败（已重试 {} 次）: {} -> {}: {}",
+                    attempt, payload["event"], url, e
+                );
+                return;
+            }
+            Err(e) => {
+                debug!(
+                    "webhook 发送失败，{} 秒后重试: {}: {}",
+                    delay.as_secs(),
+                    url,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+                attempt += 1;
+            }
+        }
+    }
+}
+
+fn retryable(status: reqwest::StatusCode) -> bool {
+    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_reports_survive_a_flood_of_file_progress() {
+        let mut events = progress::subscribe_reports().unwrap();
+        let _lagging = progress::subscribe();
+        let file_progress = |done| SyncEvent::FileProgress {
+            job_id: Some(3),
+            album_id: "flood".to_string(),
+            path: "/music/flood/01.mp3".to_string(),
+            downloaded: 10,
+            total: Some(10),
+            bytes_per_sec: 0,
+            done,
+        };
+        progress::emit(SyncEvent::JobStarted {
+            job_id: 3,
+            kind: "all".to_string(),
+        });
+        progress::emit(SyncEvent::AlbumStarted {
+            album_id: "flood".to_string(),
+            title: "Flood".to_string(),
+            path: "/music/flood".to_string(),
+        });
+        for _ in 0..5000 {
+            progress::emit(file_progress(false));
+        }
+        progress::emit(file_progress(true));
+        progress::emit(SyncEvent::AlbumFinished {
+            album_id: "flood".to_string(),
+            title: "Flood".to_string(),
+            success: true,
+            error: None,
+        });
+        progress::emit(SyncEvent::JobDone {
+            job_id: 3,
+            success: false,
+            interrupted: false,
+            error: Some("账号失败".to_string()),
+        });
+
+        let mut collector = ReportCollector::default();
+        let mut sent = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            sent.extend(notifications_for(&mut collector, &event));
+        }
+        let sent = sent
+            .iter()
+            .map(|notification| notification.event)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sent,
+            [
+                NotifyEvent::AlbumDownloaded,
+                NotifyEvent::JobFailed,
+                NotifyEvent::JobFinished
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retryable_status() {
+        assert!(retryable(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(retryable(StatusCode::BAD_GATEWAY));
+        assert!(retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!retryable(StatusCode::BAD_REQUEST));
+        assert!(!retryable(StatusCode::UNAUTHORIZED));
+        assert!(!retryable(StatusCode::NOT_FOUND));
+    }
+}
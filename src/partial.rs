@@ -0,0 +1,70 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A file written as `<name>.part` next to its destination and renamed into
+/// place once complete. Dropped without [`PartialFile::commit`] (an error, or
+/// the task being cancelled on shutdown), the `.part` file is deleted, so a
+/// truncated download never looks like a finished one to `skip_existing`.
+pub struct PartialFile {
+    part: PathBuf,
+    dest: PathBuf,
+    committed: bool,
+}
+
+impl PartialFile {
+    pub fn new(dest: &Path) -> Self {
+        let mut name = dest.file_name().unwrap_or_default().to_os_string();
+        name.push(".part");
+        Self {
+            part: dest.with_file_name(name),
+            dest: dest.to_path_buf(),
+            committed: false,
+        }
+    }
+
+    /// Where the data is written until [`PartialFile::commit`].
+    pub fn path(&self) -> &Path {
+        &self.part
+    }
+
+    pub fn commit(mut self) -> io::Result<()> {
+        fs::rename(&self.part, &self.dest)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.part);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_file_only_appears_on_commit() {
+        let dir = std::env::temp_dir().join(format!("dizzysync-partial-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("1 Track.mp3");
+
+        let partial = PartialFile::new(&dest);
+        assert_eq!(partial.path(), dir.join("1 Track.mp3.part"));
+        fs::write(partial.path(), b"trunc").unwrap();
+        drop(partial);
+        assert!(!dir.join("1 Track.mp3.part").exists());
+        assert!(!dest.exists());
+
+        let partial = PartialFile::new(&dest);
+        fs::write(partial.path(), b"full").unwrap();
+        partial.commit().unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"full");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,223 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, mpsc};
+
+/// Structured sync progress, broadcast to `/api/events` subscribers.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncEvent {
+    JobStarted {
+        job_id: u64,
+        kind: String,
+    },
+    AlbumStarted {
+        album_id: String,
+        title: String,
+        path: String,
+    },
+    AlbumFinished {
+        album_id: String,
+        title: String,
+        success: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// A format of the album is being fetched. `tracks` is the number of
+    /// per-track downloads, or 0 for a single archive (FLAC, gift).
+    FormatStarted {
+        album_id: String,
+        format: String,
+        tracks: usize,
+    },
+    /// Track `track` (1-based) of a per-track format is being processed,
+    /// whether it is downloaded, re-tagged or skipped.
+    TrackStarted {
+        album_id: String,
+        format: String,
+        track: usize,
+        title: String,
+    },
+    FileProgress {
+        /// The running API job, if any; CLI syncs have none.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        job_id: Option<u64>,
+        album_id: String,
+        path: String,
+        downloaded: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        total: Option<u64>,
+        bytes_per_sec: u64,
+        done: bool,
+    },
+    FormatSkipped {
+        album_id: String,
+        format: String,
+        reason: String,
+    },
+    JobDone {
+        job_id: u64,
+        success: bool,
+        /// The job was cut short by a shutdown signal.
+        interrupted: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    LoginFailed {
+        account: String,
+        error: String,
+    },
+}
+
+impl SyncEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncEvent::JobStarted { .. } => "job_started",
+            SyncEvent::AlbumStarted { .. } => "album_started",
+            SyncEvent::AlbumFinished { .. } => "album_finished",
+            SyncEvent::FormatStarted { .. } => "format_started",
+            SyncEvent::TrackStarted { .. } => "track_started",
+            SyncEvent::FileProgress { .. } => "file_progress",
+            SyncEvent::FormatSkipped { .. } => "format_skipped",
+            SyncEvent::JobDone { .. } => "job_done",
+            SyncEvent::LoginFailed { .. } => "login_failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressEvent {
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: SyncEvent,
+}
+
+static EVENTS: OnceLock<broadcast::Sender<ProgressEvent>> = OnceLock::new();
+const EVENT_CAPACITY: usize = 1024;
+const FILE_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+fn sender() -> &'static broadcast::Sender<ProgressEvent> {
+    EVENTS.get_or_init(|| broadcast::channel(EVENT_CAPACITY).0)
+}
+
+pub fn subscribe() -> broadcast::Receiver<ProgressEvent> {
+    sender().subscribe()
+}
+
+/// Lossless copy of the events job reports are built from, for the webhook
+/// dispatcher: the broadcast channel drops events when a busy sync floods it
+/// with file progress, which would lose `JobDone` and its webhooks.
+static REPORT_EVENTS: OnceLock<mpsc::UnboundedSender<ProgressEvent>> = OnceLock::new();
+
+/// Receive every event except in-flight file progress and per-track steps.
+/// Only one subscriber is supported; `None` when it was already taken.
+pub fn subscribe_reports() -> Option<mpsc::UnboundedReceiver<ProgressEvent>> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    REPORT_EVENTS.set(sender).ok()?;
+    Some(receiver)
+}
+
+/// Albums finished in this process, so a subscriber that lagged behind the
+/// channel can still show an exact count.
+static ALBUMS_FINISHED: AtomicU64 = AtomicU64::new(0);
+
+pub fn albums_finished() -> u64 {
+    ALBUMS_FINISHED.load(Ordering::Relaxed)
+}
+
+/// The API job between its `JobStarted` and `JobDone`, 0 when none. Jobs
+/// run one at a time, so file transfers in between belong to it.
+static CURRENT_JOB: AtomicU64 = AtomicU64::new(0);
+
+fn current_job() -> Option<u64> {
+    Some(CURRENT_JOB.load(Ordering::Relaxed)).filter(|job_id| *job_id != 0)
+}
+
+/// Publish an event. Dropped silently when nobody is listening.
+pub fn emit(event: SyncEvent) {
+    match &event {
+        SyncEvent::AlbumFinished { .. } => {
+            ALBUMS_FINISHED.fetch_add(1, Ordering::Relaxed);
+        }
+        SyncEvent::JobStarted { job_id, .. } => CURRENT_JOB.store(*job_id, Ordering::Relaxed),
+        SyncEvent::JobDone { job_id, .. } => {
+            let _ = CURRENT_JOB.compare_exchange(*job_id, 0, Ordering::Relaxed, Ordering::Relaxed);
+        }
+        _ => {}
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let event = ProgressEvent { timestamp, event };
+    if let Some(reports) = REPORT_EVENTS.get() {
+        let reported = !matches!(
+            event.event,
+            SyncEvent::FileProgress { done: false, .. }
+                | SyncEvent::FormatStarted { .. }
+                | SyncEvent::TrackStarted { .. }
+        );
+        if reported {
+            let _ = reports.send(event.clone());
+        }
+    }
+    let _ = sender().send(event);
+}
+
+/// Throttled byte counter for a single file transfer.
+pub struct FileProgress {
+    album_id: String,
+    path: String,
+    total: Option<u64>,
+    downloaded: u64,
+    started: Instant,
+    last_emit: Option<Instant>,
+}
+
+impl FileProgress {
+    pub fn new(album_id: &str, path: &std::path::Path, total: Option<u64>) -> Self {
+        Self {
+            album_id: album_id.to_string(),
+            path: path.display().to_string(),
+            total,
+            downloaded: 0,
+            started: Instant::now(),
+            last_emit: None,
+        }
+    }
+
+    pub fn advance(&mut self, bytes: usize) {
+        self.downloaded += bytes as u64;
+        let due = self
+            .last_emit
+            .map(|last| last.elapsed() >= FILE_PROGRESS_INTERVAL)
+            .unwrap_or(true);
+        if due {
+            self.last_emit = Some(Instant::now());
+            self.emit(false);
+        }
+    }
+
+    pub fn finish(&self) {
+        self.emit(true);
+    }
+
+    fn emit(&self, done: bool) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let bytes_per_sec = if elapsed > 0.0 {
+            (self.downloaded as f64 / elapsed) as u64
+        } else {
+            0
+        };
+        emit(SyncEvent::FileProgress {
+            job_id: current_job(),
+            album_id: self.album_id.clone(),
+            path: self.path.clone(),
+            downloaded: self.downloaded,
+            total: self.total,
+            bytes_per_sec,
+            done,
+        });
+    }
+}
@@ -0,0 +1,470 @@
+use crate::cli::{output_format, OutputFormat};
+use crate::config::Config;
+use crate::jobs::JobKind;
+use anyhow::{anyhow, Result};
+use clap::ArgMatches;
+use reqwest::{Client, Method, RequestBuilder, Response, Url};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+use tracing::warn;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `dizzysync remote <command>`: drive a running `serve` instance over its API.
+pub async fn run(config_path: &str, matches: &ArgMatches) -> Result<()> {
+    let remote = Remote::new(config_path, matches)?;
+    match matches.subcommand() {
+        Some(("status", sub)) => remote.status(sub).await,
+        Some(("sync", sub)) => remote.sync(sub).await,
+        Some(("logs", sub)) => remote.logs(sub).await,
+        Some(("albums", sub)) => remote.albums(sub).await,
+        _ => Ok(()),
+    }
+}
+
+struct Remote {
+    client: Client,
+    base_url: String,
+    base: Url,
+    api_key: Option<String>,
+}
+
+impl Remote {
+    /// `--url` / `--api-key` win, then `DIZZYSYNC_URL`, then the `[api]`
+    /// section of the local config (with the same env fallbacks `serve` uses).
+    fn new(config_path: &str, matches: &ArgMatches) -> Result<Self> {
+        let mut config = if Path::new(config_path).exists() {
+            Config::load_from_file(config_path)?
+        } else {
+            Config::default()
+        };
+        config.apply_env_overrides(true)?;
+
+        let base_url = matches
+            .get_one::<String>("url")
+            .cloned()
+            .or_else(|| std::env::var("DIZZYSYNC_URL").ok())
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or_else(|| base_url_from_config(&config));
+        let api_key = matches
+            .get_one::<String>("api-key")
+            .cloned()
+            .unwrap_or_else(|| config.api.api_key.clone());
+        let client = Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .danger_accept_invalid_certs(matches.get_flag("insecure"))
+            .build()?;
+
+        let base_url = base_url.trim().trim_end_matches('/').to_string();
+        let base = Url::parse(&base_url)
+            .ok()
+            .filter(|url| !url.cannot_be_a_base())
+            .ok_or_else(|| anyhow!("服务地址无效: {}", base_url))?;
+        Ok(Self {
+            client,
+            base_url,
+            base,
+            api_key: (!api_key.is_empty()).then_some(api_key),
+        })
+    }
+
+    /// A request to `/api/<segments>`; each segment is percent-encoded.
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let request = self.client.request(method, api_url(&self.base, segments));
+        match &self.api_key {
+            Some(key) => request.header("X-API-Key", key),
+            None => request,
+        }
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| anyhow!("无法连接 DizzySync 服务 {}: {}", self.base_url, e))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let message = response
+            .json::<Value>()
+            .await
+            .ok()
+            .and_then(|body| body.get("message")?.as_str().map(str::to_string))
+            .unwrap_or_else(|| status.to_string());
+        Err(anyhow!("服务返回 {}: {}", status.as_u16(), message))
+    }
+
+    async fn get(&self, segments: &[&str], query: &[(&str, String)]) -> Result<Value> {
+        let response = self
+            .send(self.request(Method::GET, segments).query(query))
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    async fn status(&self, matches: &ArgMatches) -> Result<()> {
+        let status = self.get(&["status"], &[]).await?;
+        if output_format(matches) != OutputFormat::Text {
+            return print_json(output_format(matches), &status);
+        }
+
+        let ready = status["ready"].as_bool().unwrap_or(false);
+        println!(
+            "服务: {} ({})",
+            self.base_url,
+            if ready { "已登录" } else { "未登录" }
+        );
+        let authenticated = !status["requires_auth"].as_bool().unwrap_or(false)
+            || status["scopes"].as_array().is_some_and(|s| !s.is_empty());
+        if !authenticated {
+            println!("未通过认证，仅显示公开信息；请用 --api-key 或 DIZZYSYNC_API_KEY 提供密钥");
+            return Ok(());
+        }
+
+        let users = strings(&status["users"], "username");
+        if !users.is_empty() {
+            println!("账号: {}", users.join(", "));
+        }
+        let job = &status["job"];
+        if job["state"] == "running" {
+            println!(
+                "当前任务: #{} {}，开始于 {}",
+                job["id"],
+                job["kind"].as_str().unwrap_or_default(),
+                format_time(&job["started_at"])
+            );
+        } else {
+            println!("当前任务: 空闲");
+        }
+        for (index, queued) in status["queue"].as_array().into_iter().flatten().enumerate() {
+            println!(
+                "排队 {}: #{} {}",
+                index + 1,
+                queued["id"],
+                job_kind_label(&queued["kind"])
+            );
+        }
+
+        let schedule = &status["schedule"];
+        if schedule["enabled"].as_bool().unwrap_or(false) {
+            println!(
+                "自动同步: 已启用 ({})，下次 {}，上次 {}",
+                schedule["cron"].as_str().unwrap_or_default(),
+                format_time(&schedule["next_run"]),
+                format_time(&schedule["last_run"])
+            );
+            if let Some(error) = schedule["last_error"].as_str() {
+                println!("自动同步上次失败: {}", error);
+            }
+        } else {
+            println!("自动同步: 未启用");
+        }
+        if let Some(error) = status["last_error"].as_str() {
+            println!("最近错误: {}", error);
+        }
+        if let Some(error) = status["config_error"].as_str() {
+            println!("配置文件错误: {}", error);
+        }
+        Ok(())
+    }
+
+    async fn sync(&self, matches: &ArgMatches) -> Result<()> {
+        let request = match (
+            matches.get_one::<String>("id"),
+            matches.get_one::<String>("account"),
+        ) {
+            (Some(id), _) => self.request(Method::POST, &["sync", id]),
+            (None, Some(username)) => self
+                .request(Method::POST, &["jobs"])
+                .json(&json!({ "type": "account", "username": username })),
+            (None, None) => self.request(Method::POST, &["sync"]),
+        };
+        let response: Value = self.send(request).await?.json().await?;
+        println!(
+            "{}：任务 #{} {}，队列位置 {}",
+            response["message"].as_str().unwrap_or_default(),
+            response["job"]["id"],
+            job_kind_label(&response["job"]["kind"]),
+            response["position"].as_u64().unwrap_or_default() + 1
+        );
+        Ok(())
+    }
+
+    async fn logs(&self, matches: &ArgMatches) -> Result<()> {
+        let limit = matches.get_one::<usize>("limit").copied().unwrap_or(100);
+        let mut query = vec![("limit", limit.to_string())];
+        if let Some(job) = matches.get_one::<u64>("job") {
+            query.push(("job", job.to_string()));
+        }
+        for param in ["level", "account", "album"] {
+            if let Some(value) = matches.get_one::<String>(param) {
+                query.push((param, value.clone()));
+            }
+        }
+        let follow = matches.get_flag("follow");
+        let format = match output_format(matches) {
+            // A followed stream never ends, so it cannot be one JSON array.
+            OutputFormat::Json if follow => OutputFormat::Ndjson,
+            format => format,
+        };
+
+        if follow {
+            let request = self.request(Method::GET, &["logs", "stream"]).query(&query);
+            return follow_logs(self.send(request).await?, format).await;
+        }
+
+        let entries = self.get(&["logs"], &query).await?;
+        let entries = entries.as_array().cloned().unwrap_or_default();
+        if format == OutputFormat::Json {
+            return print_json(format, &Value::Array(entries));
+        }
+        for entry in &entries {
+            print_log_entry(format, entry)?;
+        }
+        Ok(())
+    }
+
+    async fn albums(&self, matches: &ArgMatches) -> Result<()> {
+        let refresh = matches.get_flag("refresh").to_string();
+        let albums = self.get(&["albums"], &[("refresh", refresh)]).await?;
+        let mut albums = albums.as_array().cloned().unwrap_or_default();
+        if matches.get_flag("incomplete") {
+            albums.retain(|album| album["local"]["complete"] != true);
+        }
+        let format = output_format(matches);
+        if format != OutputFormat::Text {
+            return match format {
+                OutputFormat::Ndjson => albums
+                    .iter()
+                    .try_for_each(|album| print_json(format, album)),
+                _ => print_json(format, &Value::Array(albums)),
+            };
+        }
+
+        let complete = albums
+            .iter()
+            .filter(|album| album["local"]["complete"] == true)
+            .count();
+        for album in &albums {
+            let local = &album["local"];
+            let state = if local["complete"] == true {
+                "完整  "
+            } else if local["directory_exists"] == true {
+                "不完整"
+            } else {
+                "未下载"
+            };
+            println!(
+                "{}  {} - {} ({})  {}/{} 首",
+                state,
+                album["title"].as_str().unwrap_or_default(),
+                album["label"].as_str().unwrap_or_default(),
+                album["id"].as_str().unwrap_or_default(),
+                local["downloaded_tracks"].as_u64().unwrap_or_default(),
+                local["expected_tracks"].as_u64().unwrap_or_default()
+            );
+        }
+        println!(
+            "共 {} 张专辑：{} 张完整，{} 张不完整",
+            albums.len(),
+            complete,
+            albums.len() - complete
+        );
+        Ok(())
+    }
+}
+
+fn api_url(base: &Url, segments: &[&str]) -> Url {
+    let mut url = base.clone();
+    if let Ok(mut path) = url.path_segments_mut() {
+        path.pop_if_empty().push("api").extend(segments);
+    }
+    url
+}
+
+/// Print the entries of an `/api/logs/stream` response until the server
+/// closes it.
+async fn follow_logs(mut response: Response, format: OutputFormat) -> Result<()> {
+    let mut parser = SseParser::default();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| anyhow!("日志流中断: {}", e))?
+    {
+        for (event, data) in parser.feed(&chunk) {
+            match event.as_str() {
+                "log" => print_log_entry(format, &serde_json::from_str(&data)?)?,
+                "lagged" => warn!("输出过慢，跳过了 {} 条日志", data),
+                _ => {}
+            }
+        }
+    }
+    Err(anyhow!("服务关闭了日志流"))
+}
+
+/// Splits a Server-Sent Events body into `(event, data)` pairs.
+#[derive(Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    event: String,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<(String, String)> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line = self.buffer.drain(..=end).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    let event = std::mem::take(&mut self.event);
+                    let event = if event.is_empty() {
+                        "message".to_string()
+                    } else {
+                        event
+                    };
+                    events.push((event, self.data.join("\n")));
+                    self.data.clear();
+                }
+                self.event.clear();
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = value.to_string(),
+                "data" => self.data.push(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+fn print_log_entry(format: OutputFormat, entry: &Value) -> Result<()> {
+    if format != OutputFormat::Text {
+        return print_json(format, entry);
+    }
+    let mut context = Vec::new();
+    if let Some(job) = entry["job_id"].as_u64() {
+        context.push(format!("#{job}"));
+    }
+    for field in ["account", "album_id"] {
+        if let Some(value) = entry[field].as_str() {
+            context.push(value.to_string());
+        }
+    }
+    let context = if context.is_empty() {
+        String::new()
+    } else {
+        format!("[{}] ", context.join(" "))
+    };
+    println!(
+        "{} {:>5} {}{}",
+        format_time(&entry["timestamp"]),
+        entry["level"].as_str().unwrap_or_default().to_uppercase(),
+        context,
+        entry["message"].as_str().unwrap_or_default()
+    );
+    Ok(())
+}
+
+fn print_json(format: OutputFormat, value: &Value) -> Result<()> {
+    match format {
+        OutputFormat::Ndjson => println!("{}", serde_json::to_string(value)?),
+        _ => println!("{}", serde_json::to_string_pretty(value)?),
+    }
+    Ok(())
+}
+
+fn strings(values: &Value, field: &str) -> Vec<String> {
+    values
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|value| value[field].as_str().map(str::to_string))
+        .collect()
+}
+
+fn job_kind_label(kind: &Value) -> String {
+    serde_json::from_value::<JobKind>(kind.clone())
+        .map(|kind| kind.label())
+        .unwrap_or_default()
+}
+
+fn format_time(timestamp: &Value) -> String {
+    timestamp
+        .as_u64()
+        .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp as i64, 0))
+        .map(|time| {
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Where a local `serve` listens according to `[api]`; wildcard binds are
+/// reached through loopback.
+fn base_url_from_config(config: &Config) -> String {
+    let scheme = if config.api.tls_cert.as_os_str().is_empty() {
+        "http"
+    } else {
+        "https"
+    };
+    let bind = config.api.bind.trim();
+    let bind = match bind.rsplit_once(':') {
+        Some(("0.0.0.0", port)) => format!("127.0.0.1:{port}"),
+        Some(("[::]", port)) => format!("[::1]:{port}"),
+        _ => bind.to_string(),
+    };
+    format!("{scheme}://{bind}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url_from_config() {
+        let mut config = Config::default();
+        config.api.bind = "0.0.0.0:8787".to_string();
+        assert_eq!(base_url_from_config(&config), "http://127.0.0.1:8787");
+        config.api.bind = "[::]:443".to_string();
+        config.api.tls_cert = "cert.pem".into();
+        assert_eq!(base_url_from_config(&config), "https://[::1]:443");
+        config.api.bind = "nas.local:8787".to_string();
+        assert_eq!(base_url_from_config(&config), "https://nas.local:8787");
+    }
+
+    #[test]
+    fn test_api_url_encodes_segments() {
+        let base = Url::parse("https://nas.local/dizzysync").unwrap();
+        assert_eq!(
+            api_url(&base, &["sync", "a/b ?c"]).as_str(),
+            "https://nas.local/dizzysync/api/sync/a%2Fb%20%3Fc"
+        );
+        let base = Url::parse("http://127.0.0.1:8787").unwrap();
+        assert_eq!(
+            api_url(&base, &["logs", "stream"]).as_str(),
+            "http://127.0.0.1:8787/api/logs/stream"
+        );
+    }
+
+    #[test]
+    fn test_sse_parser_joins_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b": keep-alive\n\nevent: log\nda").is_empty());
+        assert_eq!(
+            parser.feed(b"ta: {\"a\":1}\r\n\r\nevent: lagged\ndata: 3\n\n"),
+            vec![
+                ("log".to_string(), "{\"a\":1}".to_string()),
+                ("lagged".to_string(), "3".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use crate::progress::{ProgressEvent, SyncEvent};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Summary of one sync job, assembled from the progress event stream.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncReport {
+    pub job_id: u64,
+    pub kind: String,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub success: bool,
+    /// Cut short by a shutdown signal; `success` is false.
+    pub interrupted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub albums: Vec<AlbumReport>,
+    pub downloaded_files: u64,
+    pub downloaded_bytes: u64,
+}
+
+impl SyncReport {
+    pub fn new_albums(&self) -> impl Iterator<Item = &AlbumReport> {
+        self.albums.iter().filter(|album| album.is_new())
+    }
+
+    pub fn failed_albums(&self) -> impl Iterator<Item = &AlbumReport> {
+        self.albums.iter().filter(|album| !album.success)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlbumReport {
+    pub album_id: String,
+    pub title: String,
+    pub path: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Files fully transferred into the album directory during this run.
+    pub files: u64,
+    pub bytes: u64,
+    pub skipped_formats: Vec<String>,
+}
+
+impl AlbumReport {
+    /// An album counts as newly downloaded when something was actually transferred;
+    /// albums whose formats all already existed locally are not.
+    pub fn is_new(&self) -> bool {
+        self.success && self.files > 0
+    }
+}
+
+pub enum ReportUpdate {
+    AlbumFinished(AlbumReport),
+    JobFinished(SyncReport),
+}
+
+struct PendingJob {
+    job_id: u64,
+    kind: String,
+    started_at: u64,
+    albums: Vec<AlbumReport>,
+}
+
+/// Folds progress events into album and job reports. Jobs run one at a time,
+/// so album events in between `JobStarted` and `JobDone` belong to that job.
+#[derive(Default)]
+pub struct ReportCollector {
+    job: Option<PendingJob>,
+    albums: HashMap<String, AlbumReport>,
+}
+
+impl ReportCollector {
+    pub fn observe(&mut self, event: &ProgressEvent) -> Option<ReportUpdate> {
+        match &event.event {
+            SyncEvent::JobStarted { job_id, kind } => {
+                self.job = Some(PendingJob {
+                    job_id: *job_id,
+                    kind: kind.clone(),
+                    started_at: event.timestamp,
+                    albums: Vec::new(),
+                });
+                None
+            }
+            SyncEvent::AlbumStarted {
+                album_id,
+                title,
+                path,
+            } => {
+                self.albums.insert(
+                    album_id.clone(),
+                    AlbumReport {
+                        album_id: album_id.clone(),
+                        title: title.clone(),
+                        path: path.clone(),
+                        success: false,
+                        error: None,
+                        files: 0,
+                        bytes: 0,
+                        skipped_formats: Vec::new(),
+                    },
+                );
+                None
+            }
+            SyncEvent::FileProgress {
+                album_id,
+                downloaded,
+                done: true,
+                ..
+            } => {
+                if let Some(album) = self.albums.get_mut(album_id) {
+                    album.files += 1;
+                    album.bytes += downloaded;
+                }
+                None
+            }
+            SyncEvent::FileProgress { .. } => None,
+            SyncEvent::FormatSkipped {
+                album_id, format, ..
+            } => {
+                if let Some(album) = self.albums.get_mut(album_id) {
+                    album.skipped_formats.push(format.clone());
+                }
+                None
+            }
+            SyncEvent::AlbumFinished {
+                album_id,
+                title,
+                success,
+                error,
+            } => {
+                let mut album = self.albums.remove(album_id).unwrap_or_else(|| AlbumReport {
+                    album_id: album_id.clone(),
+                    title: title.clone(),
+                    path: String::new(),
+                    success: false,
+                    error: None,
+                    files: 0,
+                    bytes: 0,
+                    skipped_formats: Vec::new(),
+                });
+                album.success = *success;
+                album.error = error.clone();
+                if let Some(job) = self.job.as_mut() {
+                    job.albums.push(album.clone());
+                }
+                Some(ReportUpdate::AlbumFinished(album))
+            }
+            SyncEvent::JobDone {
+                job_id,
+                success,
+                interrupted,
+                error,
+            } => {
+                let job = self.job.take().filter(|job| job.job_id == *job_id);
+                let (kind, started_at, albums) = match job {
+                    Some(job) => (job.kind, job.started_at, job.albums),
+                    None => (String::new(), event.timestamp, Vec::new()),
+                };
+                Some(ReportUpdate::JobFinished(SyncReport {
+                    job_id: *job_id,
+                    kind,
+                    started_at,
+                    finished_at: event.timestamp,
+                    success: *success,
+                    interrupted: *interrupted,
+                    error: error.clone(),
+                    downloaded_files: albums.iter().map(|album| album.files).sum(),
+                    downloaded_bytes: albums.iter().map(|album| album.bytes).sum(),
+                    albums,
+                }))
+            }
+            SyncEvent::FormatStarted { .. }
+            | SyncEvent::TrackStarted { .. }
+            | SyncEvent::LoginFailed { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(timestamp: u64, event: SyncEvent) -> ProgressEvent {
+        ProgressEvent { timestamp, event }
+    }
+
+    fn album_started(id: &str) -> SyncEvent {
+        SyncEvent::AlbumStarted {
+            album_id: id.to_string(),
+            title: id.to_uppercase(),
+            path: format!("/music/{id}"),
+        }
+    }
+
+    fn album_finished(id: &str) -> SyncEvent {
+        SyncEvent::AlbumFinished {
+            album_id: id.to_string(),
+            title: id.to_uppercase(),
+            success: true,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_report_counts_only_transferred_albums_as_new() {
+        let mut collector = ReportCollector::default();
+        let events = [
+            SyncEvent::JobStarted {
+                job_id: 7,
+                kind: "all".to_string(),
+            },
+            album_started("new"),
+            album_started("old"),
+            SyncEvent::FileProgress {
+                job_id: Some(7),
+                album_id: "new".to_string(),
+                path: "/music/new/FLAC/01.flac".to_string(),
+                downloaded: 100,
+                total: Some(100),
+                bytes_per_sec: 0,
+                done: true,
+            },
+            SyncEvent::FormatSkipped {
+                album_id: "old".to_string(),
+                format: "FLAC".to_string(),
+                reason: "exists".to_string(),
+            },
+            album_finished("old"),
+            album_finished("new"),
+        ];
+        for (index, sync_event) in events.into_iter().enumerate() {
+            collector.observe(&event(index as u64, sync_event));
+        }
+
+        let Some(ReportUpdate::JobFinished(report)) = collector.observe(&event(
+            10,
+            SyncEvent::JobDone {
+                job_id: 7,
+                success: true,
+                interrupted: false,
+                error: None,
+            },
+        )) else {
+            panic!("expected a job report");
+        };
+        assert_eq!(report.kind, "all");
+        assert_eq!(report.started_at, 0);
+        assert_eq!(report.albums.len(), 2);
+        assert_eq!(report.downloaded_bytes, 100);
+        let new_albums = report
+            .new_albums()
+            .map(|album| album.album_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(new_albums, vec!["new"]);
+    }
+}
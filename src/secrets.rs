@@ -0,0 +1,183 @@
+use anyhow::{anyhow, Result};
+use aws_lc_rs::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use aws_lc_rs::pbkdf2;
+use aws_lc_rs::rand::{SecureRandom, SystemRandom};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::sync::Mutex;
+
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+pub const MASTER_KEY_ENV: &str = "DIZZYSYNC_MASTER_KEY";
+pub const MASTER_KEY_FILE_ENV: &str = "DIZZYSYNC_MASTER_KEY_FILE";
+
+const SALT_LEN: usize = 16;
+const KDF_ITERATIONS: u32 = 600_000;
+/// Refuse iteration counts from the file that would stall loading.
+const MAX_KDF_ITERATIONS: u32 = 10_000_000;
+
+/// Master key for config values. Each value is encrypted with AES-256-GCM
+/// under a key derived by PBKDF2-HMAC-SHA256 from the master key and a random
+/// salt, stored as `enc:v1:<iterations>:<base64 salt>:<base64(nonce || ciphertext)>`.
+/// Values saved together share one salt, so a save derives a single key.
+pub struct MasterKey {
+    secret: String,
+    iterations: u32,
+    salt: [u8; SALT_LEN],
+    derived: Mutex<HashMap<KdfParams, [u8; 32]>>,
+}
+
+/// Iterations and salt of a derived key.
+type KdfParams = (u32, [u8; SALT_LEN]);
+
+impl MasterKey {
+    fn from_secret(secret: &str) -> Result<Self> {
+        Self::with_iterations(secret, KDF_ITERATIONS)
+    }
+
+    fn with_iterations(secret: &str, iterations: u32) -> Result<Self> {
+        let mut salt = [0_u8; SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| anyhow!("无法生成加密随机数"))?;
+        Ok(Self {
+            secret: secret.trim().to_string(),
+            iterations,
+            salt,
+            derived: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The master key from `DIZZYSYNC_MASTER_KEY`, else the file named by
+    /// `DIZZYSYNC_MASTER_KEY_FILE`; `None` when neither is set.
+    pub fn from_env() -> Result<Option<Self>> {
+        if let Some(secret) = std::env::var(MASTER_KEY_ENV).ok().filter(|s| !s.is_empty()) {
+            return Self::from_secret(&secret).map(Some);
+        }
+        let Some(path) = std::env::var(MASTER_KEY_FILE_ENV)
+            .ok()
+            .filter(|s| !s.is_empty())
+        else {
+            return Ok(None);
+        };
+        let secret = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("无法读取主密钥文件 {}: {}", path, e))?;
+        if secret.trim().is_empty() {
+            return Err(anyhow!("主密钥文件 {} 为空", path));
+        }
+        Self::from_secret(&secret).map(Some)
+    }
+
+    fn derive(&self, iterations: NonZeroU32, salt: [u8; SALT_LEN]) -> Result<LessSafeKey> {
+        let mut derived = self
+            .derived
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let bytes = *derived.entry((iterations.get(), salt)).or_insert_with(|| {
+            let mut out = [0_u8; 32];
+            pbkdf2::derive(
+                pbkdf2::PBKDF2_HMAC_SHA256,
+                iterations,
+                &salt,
+                self.secret.as_bytes(),
+                &mut out,
+            );
+            out
+        });
+        aes_key(&bytes)
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let iterations =
+            NonZeroU32::new(self.iterations).ok_or_else(|| anyhow!("迭代次数不能为 0"))?;
+        let mut nonce = [0_u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("无法生成加密随机数"))?;
+        let mut data = plaintext.as_bytes().to_vec();
+        self.derive(iterations, self.salt)?
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| anyhow!("加密配置值失败"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&data);
+        Ok(format!(
+            "{ENCRYPTED_PREFIX}{}:{}:{}",
+            iterations,
+            STANDARD.encode(self.salt),
+            STANDARD.encode(payload)
+        ))
+    }
+
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let invalid = || anyhow!("加密的配置值格式无效");
+        let rest = value
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .ok_or_else(|| anyhow!("不是加密的配置值"))?;
+        let mut parts = rest.splitn(3, ':');
+        let (Some(iterations), Some(salt), Some(encoded)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let iterations = iterations
+            .parse::<NonZeroU32>()
+            .ok()
+            .filter(|iterations| iterations.get() <= MAX_KDF_ITERATIONS)
+            .ok_or_else(invalid)?;
+        let salt: [u8; SALT_LEN] = STANDARD
+            .decode(salt)
+            .ok()
+            .and_then(|salt| salt.try_into().ok())
+            .ok_or_else(invalid)?;
+        let key = self.derive(iterations, salt)?;
+
+        let mut payload = STANDARD.decode(encoded).map_err(|_| invalid())?;
+        if payload.len() < NONCE_LEN {
+            return Err(invalid());
+        }
+        let mut data = payload.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&payload).map_err(|_| invalid())?;
+        let plaintext = key
+            .open_in_place(nonce, Aad::empty(), &mut data)
+            .map_err(|_| anyhow!("无法解密配置值：主密钥错误或数据已损坏"))?;
+        String::from_utf8(plaintext.to_vec()).map_err(|_| anyhow!("解密后的配置值不是有效的 UTF-8"))
+    }
+}
+
+fn aes_key(bytes: &[u8; 32]) -> Result<LessSafeKey> {
+    UnboundKey::new(&AES_256_GCM, bytes)
+        .map(LessSafeKey::new)
+        .map_err(|_| anyhow!("无效的 AES-256 密钥"))
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let key = MasterKey::with_iterations("correct horse battery staple", 1000).unwrap();
+        let encrypted = key.encrypt("hunter2").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(encrypted.starts_with("enc:v1:1000:"));
+        assert!(!encrypted.contains("hunter2"));
+        assert_ne!(encrypted, key.encrypt("hunter2").unwrap());
+        assert_eq!(key.decrypt(&encrypted).unwrap(), "hunter2");
+
+        // Another process, with its own salt, reads the stored salt and parameters.
+        let reloaded = MasterKey::with_iterations("correct horse battery staple", 5).unwrap();
+        assert_eq!(reloaded.decrypt(&encrypted).unwrap(), "hunter2");
+
+        let wrong = MasterKey::with_iterations("wrong", 1000).unwrap();
+        assert!(wrong.decrypt(&encrypted).is_err());
+        assert!(key.decrypt("enc:v1:0:AAAA:AAAA").is_err());
+        assert!(key.decrypt("enc:v1:AAAA").is_err());
+        assert!(key.decrypt("hunter2").is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{anyhow, Result};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::warn;
+
+/// How long in-flight files may keep downloading after a signal. Below the
+/// 10 seconds `docker stop` waits before it sends SIGKILL.
+pub const GRACE_PERIOD: Duration = Duration::from_secs(8);
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static ABANDONED: AtomicBool = AtomicBool::new(false);
+
+/// Resolves on Ctrl+C, or on SIGTERM on Unix (what `docker stop` and systemd
+/// send). Can be awaited again to catch a second signal.
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("无法监听 Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("无法监听 SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Stop starting new albums, formats and tracks; files already downloading
+/// are finished.
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Fails once shutdown was requested, so sync loops can bail out with `?`
+/// between files.
+pub fn check() -> Result<()> {
+    if requested() {
+        Err(anyhow!("收到退出信号，同步已中断"))
+    } else {
+        Ok(())
+    }
+}
+
+/// True once [`drain`] gave up. Blocking work that dropping a future cannot
+/// cancel, such as archive extraction, polls this to stop early.
+pub fn abandoned() -> bool {
+    ABANDONED.load(Ordering::SeqCst)
+}
+
+/// Simulate [`drain`] giving up, for tests of blocking work.
+#[cfg(test)]
+pub fn set_abandoned(abandoned: bool) {
+    ABANDONED.store(abandoned, Ordering::SeqCst);
+}
+
+/// Give `work` [`GRACE_PERIOD`] to wind down after shutdown was requested.
+/// `None` when it is still running at the deadline or a second signal arrives.
+pub async fn drain<F: Future>(work: F) -> Option<F::Output> {
+    let output = tokio::select! {
+        output = work => Some(output),
+        _ = tokio::time::sleep(GRACE_PERIOD) => {
+            warn!("等待超过 {} 秒，放弃未完成的文件", GRACE_PERIOD.as_secs());
+            None
+        }
+        _ = signal() => {
+            warn!("再次收到退出信号，立即退出");
+            None
+        }
+    };
+    if output.is_none() {
+        ABANDONED.store(true, Ordering::SeqCst);
+    }
+    output
+}
+
+/// Run `work` until it finishes or a signal arrives. On a signal, shutdown is
+/// requested and `work` is drained; dropping it removes any partial files.
+/// `None` means the work was interrupted.
+pub async fn interruptible<F: Future>(work: F) -> Option<F::Output> {
+    tokio::pin!(work);
+    tokio::select! {
+        output = &mut work => return Some(output),
+        _ = signal() => {}
+    }
+    request();
+    warn!(
+        "收到退出信号，不再开始新的下载，等待进行中的文件完成（最多 {} 秒，再次发送信号立即退出）",
+        GRACE_PERIOD.as_secs()
+    );
+    drain(work).await;
+    None
+}
@@ -0,0 +1,271 @@
+use crate::progress::{self, ProgressEvent, SyncEvent};
+use indicatif::{HumanBytes, HumanDuration, MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Instant;
+use tokio::sync::{broadcast, oneshot};
+use tokio::task::JoinHandle;
+
+static BARS: OnceLock<MultiProgress> = OnceLock::new();
+
+/// Turn the progress display on when stdout is a terminal. Call before logging
+/// is set up so that log lines go through [`LogWriter`].
+pub fn enable_if_terminal() -> bool {
+    if !io::stdout().is_terminal() {
+        return false;
+    }
+    BARS.get_or_init(MultiProgress::new);
+    true
+}
+
+/// Log writer for stdout that hides the bars while a line is printed.
+pub struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match BARS.get() {
+            Some(bars) => bars.suspend(|| io::stdout().write(buf)),
+            None => io::stdout().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+/// Multi-line view of a CLI sync: an overall album counter plus a track bar
+/// and a file transfer bar for every album in progress. Driven by the same
+/// `SyncEvent`s that `/api/events` streams.
+pub struct SyncProgress {
+    overall: ProgressBar,
+    started: Instant,
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl SyncProgress {
+    /// `None` unless [`enable_if_terminal`] turned the display on.
+    pub fn start() -> Option<Self> {
+        let bars = BARS.get()?.clone();
+        let overall = bars.add(
+            ProgressBar::new(0)
+                .with_style(style("专辑 {pos}/{len} [{wide_bar}] {elapsed_precise}")),
+        );
+        let events = progress::subscribe();
+        let (stop, stopped) = oneshot::channel();
+        let view = View {
+            bars,
+            overall: overall.clone(),
+            albums: HashMap::new(),
+            finished_before: progress::albums_finished(),
+        };
+        let task = tokio::spawn(view.run(events, stopped));
+        Some(Self {
+            overall,
+            started: Instant::now(),
+            stop,
+            task,
+        })
+    }
+
+    /// Grow the overall counter once an account's album list is known.
+    pub fn add_albums(&self, count: usize) {
+        self.overall.inc_length(count as u64);
+    }
+
+    /// Render the events still queued, then replace the bars with a summary line.
+    pub async fn finish(self) {
+        let _ = self.stop.send(());
+        let _ = self.task.await;
+        self.overall.finish_and_clear();
+        let total = self.overall.length().unwrap_or_default();
+        if let (Some(bars), true) = (BARS.get(), total > 0) {
+            let _ = bars.println(format!(
+                "已处理 {}/{} 个专辑，用时 {}",
+                self.overall.position(),
+                total,
+                HumanDuration(self.started.elapsed())
+            ));
+        }
+    }
+}
+
+struct AlbumBars {
+    title: String,
+    tracks: ProgressBar,
+    file: Option<ProgressBar>,
+}
+
+struct View {
+    bars: MultiProgress,
+    overall: ProgressBar,
+    albums: HashMap<String, AlbumBars>,
+    /// Albums finished before this view started, for counting from the
+    /// process-wide total instead of from events that may have been dropped.
+    finished_before: u64,
+}
+
+impl View {
+    async fn run(
+        mut self,
+        mut events: broadcast::Receiver<ProgressEvent>,
+        mut stopped: oneshot::Receiver<()>,
+    ) {
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => self.apply(event.event),
+                    Err(broadcast::error::RecvError::Lagged(_)) => self.resync(),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = &mut stopped => break,
+            }
+        }
+        loop {
+            match events.try_recv() {
+                Ok(event) => self.apply(event.event),
+                Err(broadcast::error::TryRecvError::Lagged(_)) => self.resync(),
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Events were dropped: any album bar may belong to an album that has
+    /// finished since, so clear them all and recount from the process total.
+    fn resync(&mut self) {
+        for (_, album) in self.albums.drain() {
+            for bar in album.file.iter().chain([&album.tracks]) {
+                bar.finish_and_clear();
+                self.bars.remove(bar);
+            }
+        }
+        self.sync_overall();
+    }
+
+    fn sync_overall(&self) {
+        let finished = progress::albums_finished().saturating_sub(self.finished_before);
+        if finished > self.overall.length().unwrap_or_default() {
+            self.overall.set_length(finished);
+        }
+        self.overall.set_position(finished);
+    }
+
+    fn apply(&mut self, event: SyncEvent) {
+        match event {
+            SyncEvent::AlbumStarted {
+                album_id, title, ..
+            } => {
+                let tracks = self.bars.insert_before(
+                    &self.overall,
+                    ProgressBar::new(0).with_style(style("  {prefix}")),
+                );
+                tracks.set_prefix(title.clone());
+                self.albums.insert(
+                    album_id,
+                    AlbumBars {
+                        title,
+                        tracks,
+                        file: None,
+                    },
+                );
+            }
+            SyncEvent::FormatStarted {
+                album_id,
+                format,
+                tracks,
+            } => {
+                let Some(album) = self.albums.get_mut(&album_id) else {
+                    return;
+                };
+                album
+                    .tracks
+                    .set_prefix(format!("{} [{}]", album.title, format));
+                album.tracks.set_message("");
+                if tracks > 0 {
+                    album
+                        .tracks
+                        .set_style(style("  {prefix} [{bar:20}] {pos}/{len} {wide_msg}"));
+                    album.tracks.set_length(tracks as u64);
+                    album.tracks.set_position(0);
+                } else {
+                    album.tracks.set_style(style("  {prefix}"));
+                }
+            }
+            SyncEvent::TrackStarted {
+                album_id,
+                track,
+                title,
+                ..
+            } => {
+                if let Some(album) = self.albums.get(&album_id) {
+                    album.tracks.set_position(track.saturating_sub(1) as u64);
+                    album.tracks.set_message(title);
+                }
+            }
+            SyncEvent::FileProgress {
+                album_id,
+                path,
+                downloaded,
+                total,
+                bytes_per_sec,
+                done,
+                ..
+            } => {
+                let Some(album) = self.albums.get_mut(&album_id) else {
+                    return;
+                };
+                if done {
+                    if let Some(file) = album.file.take() {
+                        file.finish_and_clear();
+                        self.bars.remove(&file);
+                    }
+                    return;
+                }
+                let file = album.file.get_or_insert_with(|| {
+                    self.bars.insert_after(
+                        &album.tracks,
+                        ProgressBar::new(0)
+                            .with_style(style("    [{bar:20}] {bytes}/{total_bytes} {wide_msg}")),
+                    )
+                });
+                file.set_length(total.unwrap_or(downloaded));
+                file.set_position(downloaded);
+                let name = Path::new(&path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                file.set_message(format!("{}/s  {}", HumanBytes(bytes_per_sec), name));
+            }
+            SyncEvent::AlbumFinished {
+                album_id,
+                title,
+                success,
+                error,
+            } => {
+                if let Some(album) = self.albums.remove(&album_id) {
+                    for bar in album.file.iter().chain([&album.tracks]) {
+                        bar.finish_and_clear();
+                        self.bars.remove(bar);
+                    }
+                }
+                self.sync_overall();
+                let line = match (success, error) {
+                    (true, _) => format!("完成 {}", title),
+                    (false, Some(error)) => format!("失败 {}: {}", title, error),
+                    (false, None) => format!("失败 {}", title),
+                };
+                let _ = self.bars.println(line);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn style(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template)
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> ")
+}
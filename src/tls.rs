@@ -0,0 +1,59 @@
+use crate::config::ApiConfig;
+use anyhow::{anyhow, Context, Result};
+use axum_server::tls_rustls::RustlsConfig;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The configured certificate and key paths, or `None` to serve plain HTTP.
+pub fn tls_paths(api: &ApiConfig) -> Result<Option<(PathBuf, PathBuf)>> {
+    let cert = &api.tls_cert;
+    let key = &api.tls_key;
+    match (cert.as_os_str().is_empty(), key.as_os_str().is_empty()) {
+        (true, true) => Ok(None),
+        (false, false) => Ok(Some((cert.clone(), key.clone()))),
+        _ => Err(anyhow!("[api] tls_cert 和 tls_key 需要同时设置")),
+    }
+}
+
+pub async fn load(cert: &Path, key: &Path) -> Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(cert, key)
+        .await
+        .with_context(|| {
+            format!(
+                "无法加载 TLS 证书 {} / 私钥 {}",
+                cert.display(),
+                key.display()
+            )
+        })
+}
+
+/// Poll the PEM files and swap in the new certificate when either changes, so
+/// renewed certificates apply without a restart. A broken pair is logged and
+/// the previous certificate stays in use.
+pub fn watch(config: RustlsConfig, cert: PathBuf, key: PathBuf) {
+    tokio::spawn(async move {
+        let mut loaded = modified(&cert, &key);
+        let mut interval = tokio::time::interval(RELOAD_CHECK_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let current = modified(&cert, &key);
+            if current == loaded {
+                continue;
+            }
+            loaded = current;
+            match config.reload_from_pem_file(&cert, &key).await {
+                Ok(()) => info!("TLS 证书已重新加载: {}", cert.display()),
+                Err(e) => warn!("TLS 证书重新加载失败，继续使用旧证书: {}", e),
+            }
+        }
+    });
+}
+
+fn modified(cert: &Path, key: &Path) -> (Option<SystemTime>, Option<SystemTime>) {
+    let mtime = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    (mtime(cert), mtime(key))
+}
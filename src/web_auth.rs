@@ -0,0 +1,199 @@
+use crate::api_keys::{self, ApiGrant};
+use crate::config::ApiConfig;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+pub const SESSION_COOKIE: &str = "dizzysync_session";
+
+const MAX_FAILED_LOGINS: u32 = 5;
+const FAILURE_WINDOW_SECS: u64 = 15 * 60;
+const LOCKOUT_SECS: u64 = 15 * 60;
+
+struct WebSession {
+    grant: ApiGrant,
+    /// Hash of the key used to log in, to re-resolve the grant when keys change.
+    key_hash: String,
+    expires_at: u64,
+}
+
+#[derive(Default)]
+struct LoginFailures {
+    count: u32,
+    window_start: u64,
+    locked_until: u64,
+}
+
+/// Web UI login sessions plus per-IP failed login tracking. Everything lives in
+/// memory: a restart logs everyone out and resets lockouts.
+#[derive(Default)]
+pub struct WebSessions {
+    sessions: HashMap<String, WebSession>,
+    failures: HashMap<IpAddr, LoginFailures>,
+}
+
+impl WebSessions {
+    /// Seconds until `ip` may try again, or `None` when it is not locked out.
+    pub fn locked_for(&self, ip: IpAddr, now: u64) -> Option<u64> {
+        self.failures
+            .get(&ip)
+            .filter(|failures| failures.locked_until > now)
+            .map(|failures| failures.locked_until - now)
+    }
+
+    /// Count a failed attempt. Returns the lockout length once the limit is reached.
+    pub fn record_failure(&mut self, ip: IpAddr, now: u64) -> Option<u64> {
+        // Forget addresses whose window and lockout are both over, so clients
+        // spraying from many addresses cannot grow the map without bound.
+        self.failures.retain(|_, failures| {
+            failures.locked_until > now
+                || now.saturating_sub(failures.window_start) <= FAILURE_WINDOW_SECS
+        });
+        let failures = self.failures.entry(ip).or_default();
+        if now.saturating_sub(failures.window_start) > FAILURE_WINDOW_SECS {
+            failures.count = 0;
+            failures.window_start = now;
+        }
+        failures.count += 1;
+        if failures.count >= MAX_FAILED_LOGINS {
+            failures.count = 0;
+            failures.locked_until = now + LOCKOUT_SECS;
+            return Some(LOCKOUT_SECS);
+        }
+        None
+    }
+
+    pub fn record_success(&mut self, ip: IpAddr) {
+        self.failures.remove(&ip);
+    }
+
+    pub fn create(
+        &mut self,
+        grant: ApiGrant,
+        key: &str,
+        ttl_secs: u64,
+        now: u64,
+    ) -> Result<String> {
+        self.sessions.retain(|_, session| session.expires_at > now);
+        let token = api_keys::random_token()?;
+        self.sessions.insert(
+            token.clone(),
+            WebSession {
+                grant,
+                key_hash: api_keys::hash_key(key),
+                expires_at: now + ttl_secs,
+            },
+        );
+        Ok(token)
+    }
+
+    pub fn get(&self, token: &str, now: u64) -> Option<ApiGrant> {
+        self.sessions
+            .get(token)
+            .filter(|session| session.expires_at > now)
+            .map(|session| session.grant.clone())
+    }
+
+    pub fn remove(&mut self, token: &str) {
+        self.sessions.remove(token);
+    }
+
+    /// Re-resolve every session against the current keys: sessions whose key
+    /// was removed are dropped, the others take the key's current scopes.
+    pub fn refresh(&mut self, api: &ApiConfig) {
+        self.sessions.retain(|_, session| {
+            match api_keys::resolve_hash(api, Some(&session.key_hash)) {
+                Some(grant) => {
+                    session.grant = grant;
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+}
+
+/// Extract the session token from a `Cookie` header value.
+pub fn session_token(cookie_header: &str) -> Option<&str> {
+    cookie_header.split(';').find_map(|pair| {
+        let (name, value) = pair.trim().split_once('=')?;
+        (name == SESSION_COOKIE && !value.is_empty()).then_some(value)
+    })
+}
+
+/// `secure` adds the `Secure` attribute; set it when the server speaks HTTPS.
+pub fn session_cookie(token: &str, max_age_secs: u64, secure: bool) -> String {
+    let secure = if secure { "; Secure" } else { "" };
+    format!(
+        "{SESSION_COOKIE}={token}; Path=/; HttpOnly; SameSite=Strict; Max-Age={max_age_secs}{secure}"
+    )
+}
+
+pub fn expired_session_cookie(secure: bool) -> String {
+    session_cookie("", 0, secure)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_keys::ApiScope;
+
+    #[test]
+    fn test_failed_logins_lock_out_ip() {
+        let mut sessions = WebSessions::default();
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let other: IpAddr = "192.0.2.2".parse().unwrap();
+
+        for attempt in 1..MAX_FAILED_LOGINS {
+            assert_eq!(sessions.record_failure(ip, attempt as u64), None);
+        }
+        assert_eq!(sessions.record_failure(ip, 10), Some(LOCKOUT_SECS));
+        assert_eq!(sessions.locked_for(ip, 10), Some(LOCKOUT_SECS));
+        assert_eq!(sessions.locked_for(other, 10), None);
+        assert_eq!(sessions.locked_for(ip, 10 + LOCKOUT_SECS), None);
+
+        // Failures spread beyond the window never accumulate to a lockout.
+        for attempt in 0..MAX_FAILED_LOGINS as u64 {
+            let now = 10_000 + attempt * (FAILURE_WINDOW_SECS + 1);
+            assert_eq!(sessions.record_failure(other, now), None);
+        }
+
+        // Expired entries are dropped on the next failure.
+        let later = 10_000 + 10 * FAILURE_WINDOW_SECS;
+        sessions.record_failure("192.0.2.3".parse().unwrap(), later);
+        assert_eq!(sessions.failures.len(), 1);
+    }
+
+    #[test]
+    fn test_sessions_follow_key_changes() {
+        let mut api = ApiConfig::default();
+        api.keys.push(crate::config::ApiKeyConfig {
+            name: "family".to_string(),
+            key: "secret".to_string(),
+            key_hash: String::new(),
+            scopes: vec!["admin".to_string()],
+        });
+        let mut sessions = WebSessions::default();
+        let grant = api_keys::resolve(&api, Some("secret")).unwrap();
+        let token = sessions.create(grant, "secret", 60, 0).unwrap();
+
+        api.keys[0].scopes = vec!["read".to_string()];
+        sessions.refresh(&api);
+        let grant = sessions.get(&token, 1).unwrap();
+        assert!(grant.allows(ApiScope::Read) && !grant.allows(ApiScope::Admin));
+
+        api.keys.clear();
+        api.api_key = "other".to_string();
+        sessions.refresh(&api);
+        assert!(sessions.get(&token, 1).is_none());
+    }
+
+    #[test]
+    fn test_session_cookie_round_trip() {
+        let header = format!("theme=dark; {SESSION_COOKIE}=abc123; other=1");
+        assert_eq!(session_token(&header), Some("abc123"));
+        assert_eq!(session_token("theme=dark"), None);
+        assert!(session_cookie("abc", 60, false).ends_with("HttpOnly; SameSite=Strict; Max-Age=60"));
+        assert!(session_cookie("abc", 60, true).ends_with("; Secure"));
+    }
+}
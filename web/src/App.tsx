@@ -1,4 +1,4 @@
-import { LoginOutlined, TranslationOutlined } from "@ant-design/icons";
+import { LoginOutlined, LogoutOutlined, TranslationOutlined } from "@ant-design/icons";
 import {
   Alert,
   App as AntApp,
@@ -13,7 +13,7 @@ import {
   Typography,
 } from "antd";
 import { useCallback, useEffect, useMemo, useState } from "react";
-import { ApiError, api, apiKeyStorageKey } from "./api.ts";
+import { ApiError, api, eventsUrl, syncEventTypes } from "./api.ts";
 import { AlbumDetailDrawer } from "./components/AlbumDetailDrawer.tsx";
 import { AlbumTable } from "./components/AlbumTable.tsx";
 import { ConfigForm } from "./components/ConfigForm.tsx";
@@ -27,7 +27,15 @@ import { LogViewer } from "./components/LogViewer.tsx";
 import { StatusCard } from "./components/StatusCard.tsx";
 import { SyncControls } from "./components/SyncControls.tsx";
 import { type Language, useI18n } from "./i18n.tsx";
-import type { ConfigResponse, DiscInfo, DiscListItem, StatusResponse, Track } from "./types.ts";
+import type {
+  ConfigResponse,
+  DiscInfo,
+  DiscListItem,
+  FileProgressEvent,
+  ProgressEvent,
+  StatusResponse,
+  Track,
+} from "./types.ts";
 
 const { Footer, Header } = Layout;
 const { Title, Text } = Typography;
@@ -38,15 +46,15 @@ const languageOptions: Array<{ label: string; value: Language }> = [
 ];
 
 interface LoginValues {
-  apiKey: string;
+  password: string;
 }
 
+// Older builds kept the key in localStorage; sessions now live in an HttpOnly cookie.
+globalThis.localStorage.removeItem("dizzysync.apiKey");
+
 export function App() {
   const { message } = AntApp.useApp();
   const { language, setLanguage, t } = useI18n();
-  const [apiKey, setApiKey] = useState(
-    () => globalThis.localStorage.getItem(apiKeyStorageKey) ?? "",
-  );
   const [status, setStatus] = useState<StatusResponse | null>(null);
   const [config, setConfig] = useState<ConfigResponse | null>(null);
   const [albums, setAlbums] = useState<DiscListItem[]>([]);
@@ -57,19 +65,11 @@ export function App() {
   const [error, setError] = useState<string | null>(null);
   const [authRequired, setAuthRequired] = useState(false);
   const [activeGuideKey, setActiveGuideKey] = useState<ConfigGuideSection>("user");
+  const [fileProgress, setFileProgress] = useState<FileProgressEvent | null>(null);
 
-  const isRunning = status?.job.state === "running";
+  const isRunning = status?.job.state === "running" || (status?.queue?.length ?? 0) > 0;
   const needsOnboarding = status && !authRequired ? !(status.configured && status.ready) : false;
 
-  const saveApiKey = useCallback((value: string) => {
-    setApiKey(value);
-    if (value.trim()) {
-      globalThis.localStorage.setItem(apiKeyStorageKey, value.trim());
-    } else {
-      globalThis.localStorage.removeItem(apiKeyStorageKey);
-    }
-  }, []);
-
   const refreshAll = useCallback(async () => {
     setLoading(true);
     setError(null);
@@ -137,6 +137,30 @@ export function App() {
     return () => globalThis.clearInterval(timer);
   }, [isRunning, loadStatus]);
 
+  useEffect(() => {
+    if (authRequired || !status?.ready) {
+      return;
+    }
+
+    const source = new EventSource(eventsUrl);
+    const handleEvent = (event: MessageEvent<string>) => {
+      const payload = JSON.parse(event.data) as ProgressEvent;
+      if (payload.type === "file_progress") {
+        setFileProgress(payload.done ? null : payload);
+      } else if (payload.type === "job_started" || payload.type === "job_done") {
+        setFileProgress(null);
+        loadStatus().catch((caught: unknown) => {
+          setError(caught instanceof Error ? caught.message : String(caught));
+        });
+      }
+    };
+    for (const type of syncEventTypes) {
+      source.addEventListener(type, handleEvent);
+    }
+
+    return () => source.close();
+  }, [authRequired, loadStatus, status?.ready]);
+
   const showAlbum = useCallback(
     async (id: string, refresh = false) => {
       setLoading(true);
@@ -253,25 +277,40 @@ export function App() {
 
   const handleConfigSaved = useCallback(
     (nextConfig: ConfigResponse, nextApiKey?: string) => {
-      if (nextApiKey !== undefined) {
-        saveApiKey(nextApiKey);
-      }
       setConfig(nextConfig);
-      refreshAll().catch((caught: unknown) => {
-        message.error(caught instanceof Error ? caught.message : String(caught));
-      });
+      // Changing the Web UI password ends every session, so sign in again with the new one.
+      const relogin = nextApiKey ? api.login(nextApiKey).then(() => undefined) : Promise.resolve();
+      relogin
+        .then(() => refreshAll())
+        .catch((caught: unknown) => {
+          message.error(caught instanceof Error ? caught.message : String(caught));
+        });
     },
-    [message, refreshAll, saveApiKey],
+    [message, refreshAll],
   );
 
   const login = useCallback(
     async (values: LoginValues) => {
-      saveApiKey(values.apiKey);
+      setError(null);
+      try {
+        await api.login(values.password.trim());
+      } catch (caught) {
+        setError(caught instanceof Error ? caught.message : String(caught));
+        return;
+      }
       await refreshAll();
     },
-    [refreshAll, saveApiKey],
+    [refreshAll],
   );
 
+  const logout = useCallback(async () => {
+    try {
+      await api.logout();
+    } finally {
+      await refreshAll();
+    }
+  }, [refreshAll]);
+
   const closeAlbumDetail = useCallback(() => {
     setDetail(null);
   }, []);
@@ -336,8 +375,8 @@ export function App() {
         label: t("tabs.dashboard"),
         children: (
           <Space direction="vertical" size="large" style={{ width: "100%" }}>
-            <StatusCard status={status} />
-            <SyncControls disabled={!status?.ready || isRunning} onSyncAll={syncAll} />
+            <StatusCard fileProgress={fileProgress} status={status} />
+            <SyncControls disabled={!status?.ready} onSyncAll={syncAll} />
           </Space>
         ),
       },
@@ -352,7 +391,7 @@ export function App() {
             onRefresh={refreshAll}
             onShow={(id) => showAlbum(id, true)}
             onSync={syncAlbum}
-            syncDisabled={Boolean(isRunning)}
+            syncDisabled={!status?.ready}
           />
         ),
       },
@@ -380,8 +419,8 @@ export function App() {
       activeGuideKey,
       albums,
       config,
+      fileProgress,
       handleConfigSaved,
-      isRunning,
       loading,
       needsOnboarding,
       onboarding,
@@ -406,10 +445,10 @@ export function App() {
               <Text type="secondary">{t("auth.requiredDescription")}</Text>
             </div>
             {error ? <Alert showIcon={true} type="error" message={error} /> : null}
-            <Form<LoginValues> initialValues={{ apiKey }} layout="vertical" onFinish={login}>
+            <Form<LoginValues> layout="vertical" onFinish={login}>
               <Form.Item
                 label={t("app.apiKey.placeholder")}
-                name="apiKey"
+                name="password"
                 rules={[{ required: true, message: t("config.webPasswordRequired") }]}
               >
                 <Input.Password autoFocus={true} autoComplete="current-password" />
@@ -434,7 +473,14 @@ export function App() {
     <Layout>
       <Header className="app-header">
         <div className="app-logo">{t("app.title")}</div>
-        <Space>{languageButton()}</Space>
+        <Space>
+          {languageButton()}
+          {status?.requires_auth ? (
+            <Button icon={<LogoutOutlined />} onClick={() => void logout()}>
+              {t("auth.logout")}
+            </Button>
+          ) : null}
+        </Space>
       </Header>
       <main className="page-content">
         <Space direction="vertical" size="large" style={{ width: "100%" }}>
@@ -1,26 +1,37 @@
 import type {
   ApiMessage,
   ConfigResponse,
+  ConfigSchema,
   DiscInfo,
   DiscListItem,
+  EnqueueResponse,
   LogEntry,
   StatusResponse,
   TestLoginRequest,
   TestLoginResponse,
   UpdateConfigRequest,
+  WebLoginResponse,
 } from "./types.ts";
 
-export const apiKeyStorageKey = "dizzysync.apiKey";
-
 export function localFileUrl(path: string): string {
   const params = new URLSearchParams({ path });
-  const apiKey = localStorage.getItem(apiKeyStorageKey)?.trim();
-  if (apiKey) {
-    params.set("api_key", apiKey);
-  }
   return `/api/local-file?${params.toString()}`;
 }
 
+export const syncEventTypes = [
+  "job_started",
+  "album_started",
+  "album_finished",
+  "format_started",
+  "track_started",
+  "file_progress",
+  "format_skipped",
+  "job_done",
+  "login_failed",
+] as const;
+
+export const eventsUrl = "/api/events";
+
 export class ApiError extends Error {
   public readonly status: number;
 
@@ -33,11 +44,7 @@ export class ApiError extends Error {
 
 async function request<T>(path: string, init: RequestInit = {}): Promise<T> {
   const headers = new Headers(init.headers);
-  const apiKey = localStorage.getItem(apiKeyStorageKey)?.trim();
 
-  if (apiKey) {
-    headers.set("X-API-Key", apiKey);
-  }
   if (init.body && !headers.has("Content-Type")) {
     headers.set("Content-Type", "application/json");
   }
@@ -71,6 +78,11 @@ export interface LogFilters {
   level?: string;
   start?: string;
   end?: string;
+  offset?: string;
+  limit?: string;
+  job?: string;
+  account?: string;
+  album?: string;
 }
 
 export interface AlbumRequestOptions {
@@ -90,7 +102,14 @@ function queryString<T extends object>(filters: T): string {
 
 export const api = {
   status: () => request<StatusResponse>("/api/status"),
+  login: (password: string) =>
+    request<WebLoginResponse>("/api/auth/login", {
+      method: "POST",
+      body: JSON.stringify({ password }),
+    }),
+  logout: () => request<ApiMessage>("/api/auth/logout", { method: "POST" }),
   config: () => request<ConfigResponse>("/api/config"),
+  configSchema: () => request<ConfigSchema>("/api/config/schema"),
   logs: (filters: LogFilters = {}) => request<LogEntry[]>(`/api/logs${queryString(filters)}`),
   updateConfig: (body: UpdateConfigRequest) =>
     request<ConfigResponse>("/api/config", {
@@ -107,12 +126,16 @@ export const api = {
   album: (id: string, options: AlbumRequestOptions = {}) =>
     request<DiscInfo>(`/api/albums/${encodeURIComponent(id)}${queryString(options)}`),
   syncAll: () =>
-    request<ApiMessage>("/api/sync", {
+    request<EnqueueResponse>("/api/sync", {
       method: "POST",
       body: JSON.stringify({}),
     }),
   syncAlbum: (id: string) =>
-    request<ApiMessage>(`/api/sync/${encodeURIComponent(id)}`, {
+    request<EnqueueResponse>(`/api/sync/${encodeURIComponent(id)}`, {
       method: "POST",
     }),
+  removeJob: (id: number) =>
+    request<ApiMessage>(`/api/jobs/${id}`, {
+      method: "DELETE",
+    }),
 };
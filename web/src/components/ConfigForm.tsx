@@ -21,7 +21,12 @@ import {
 import { useCallback, useEffect, useMemo, useState } from "react";
 import { api } from "../api.ts";
 import { useI18n } from "../i18n.tsx";
-import type { ConfigResponse, TestLoginResponse, UpdateConfigRequest } from "../types.ts";
+import type {
+  ConfigResponse,
+  ConfigSchema,
+  TestLoginResponse,
+  UpdateConfigRequest,
+} from "../types.ts";
 import type { ConfigGuideSection } from "./ConfigGuide.tsx";
 
 interface ConfigFormProps {
@@ -59,6 +64,11 @@ interface AccountTestState {
   error?: string;
 }
 
+const formatLabels: Record<string, string> = {
+  "128": "128kbps MP3",
+  "320": "320kbps MP3",
+};
+
 export function ConfigForm({ config, mode = "settings", onSaved, onFocusGuide }: ConfigFormProps) {
   const { message } = App.useApp();
   const { t } = useI18n();
@@ -67,16 +77,28 @@ export function ConfigForm({ config, mode = "settings", onSaved, onFocusGuide }:
   const [accountTests, setAccountTests] = useState<Record<number, AccountTestState>>({});
   const isOnboarding = mode === "onboarding";
   const outputDirLocked = Boolean(config?.config.paths.output_dir_locked);
+  const envLocked = useMemo(() => new Set(config?.config.env_locked ?? []), [config]);
+  const isLocked = (field: string) => envLocked.has(field);
+  const lockedTooltip = (field: string) => (isLocked(field) ? t("config.envLocked") : undefined);
 
-  const formatOptions = useMemo(
-    () => [
-      { label: "128kbps MP3", value: "128" },
-      { label: "320kbps MP3", value: "320" },
-      { label: "FLAC", value: "FLAC" },
-      { label: "gift", value: "gift" },
-    ],
-    [],
-  );
+  const [schema, setSchema] = useState<ConfigSchema>();
+
+  useEffect(() => {
+    api
+      .configSchema()
+      .then(setSchema)
+      .catch(() => undefined);
+  }, []);
+
+  const formatOptions = useMemo(() => {
+    const formats = schema?.$defs?.DownloadConfig?.properties?.formats?.items?.enum ?? [
+      "128",
+      "320",
+      "FLAC",
+      "gift",
+    ];
+    return formats.map((value) => ({ label: formatLabels[value] ?? value, value }));
+  }, [schema]);
 
   const templateOptions = useMemo(
     () => [
@@ -284,9 +306,11 @@ export function ConfigForm({ config, mode = "settings", onSaved, onFocusGuide }:
                         label={t("config.username")}
                         name={[field.name, "username"]}
                         rules={[{ required: true, message: t("config.usernameRequired") }]}
+                        tooltip={lockedTooltip(`users.${field.name}.username`)}
                       >
                         <Input
                           autoComplete="username"
+                          disabled={isLocked(`users.${field.name}.username`)}
                           style={{ width: 280 }}
                           onFocus={focusGuide("user")}
                         />
@@ -304,7 +328,15 @@ export function ConfigForm({ config, mode = "settings", onSaved, onFocusGuide }:
                       >
                         <Input.Password
                           autoComplete="current-password"
-                          placeholder={t("config.passwordPlaceholder")}
+                          disabled={
+                            Boolean(account?.password_source) ||
+                            isLocked(`users.${field.name}.password`)
+                          }
+                          placeholder={
+                            account?.password_source
+                              ? t(`config.passwordFrom.${account.password_source}`)
+                              : t("config.passwordPlaceholder")
+                          }
                           style={{ width: 280 }}
                           onFocus={focusGuide("user")}
                         />
@@ -354,8 +386,13 @@ export function ConfigForm({ config, mode = "settings", onSaved, onFocusGuide }:
         </Form.List>
 
         <Space align="start" size="large" style={{ width: "100%" }} wrap={true}>
-          <Form.Item label={t("config.webPassword")} name="apiKey">
+          <Form.Item
+            label={t("config.webPassword")}
+            name="apiKey"
+            tooltip={lockedTooltip("api.api_key")}
+          >
             <Input.Password
+              disabled={isLocked("api.api_key")}
               placeholder={t("config.webPasswordPlaceholder")}
               style={{ width: 280 }}
               onFocus={focusGuide("api")}
@@ -367,8 +404,14 @@ export function ConfigForm({ config, mode = "settings", onSaved, onFocusGuide }:
           label={t("config.formats")}
           name="formats"
           rules={[{ required: true, message: t("config.formatsRequired") }]}
+          tooltip={lockedTooltip("download.formats")}
         >
-          <Select mode="multiple" options={formatOptions} onFocus={focusGuide("download")} />
+          <Select
+            disabled={isLocked("download.formats")}
+            mode="multiple"
+            options={formatOptions}
+            onFocus={focusGuide("download")}
+          />
         </Form.Item>
 
         <Space align="start" size="large" style={{ width: "100%" }} wrap={true}>
@@ -388,8 +431,10 @@ export function ConfigForm({ config, mode = "settings", onSaved, onFocusGuide }:
             label={t("config.directoryTemplate")}
             name="directoryTemplate"
             rules={[{ required: true, message: t("config.directoryTemplateRequired") }]}
+            tooltip={lockedTooltip("paths.directory_template")}
           >
             <AutoComplete
+              disabled={isLocked("paths.directory_template")}
               options={templateOptions}
               filterOption={(inputValue, option) =>
                 String(option?.label ?? "")
@@ -414,43 +459,50 @@ export function ConfigForm({ config, mode = "settings", onSaved, onFocusGuide }:
                 message: t("config.maxConcurrentAlbumsRequired"),
               },
             ]}
+            tooltip={lockedTooltip("behavior.max_concurrent_albums")}
           >
-            <InputNumber min={1} style={{ width: 180 }} onFocus={focusGuide("behavior")} />
+            <InputNumber
+              disabled={isLocked("behavior.max_concurrent_albums")}
+              min={1}
+              style={{ width: 180 }}
+              onFocus={focusGuide("behavior")}
+            />
           </Form.Item>
         </Space>
 
         <Space size="large" wrap={true}>
           <Form.Item name="skipExisting" valuePropName="checked">
-            <Checkbox onFocus={focusGuide("behavior")}>{t("config.skipExisting")}</Checkbox>
+            <Checkbox disabled={isLocked("behavior.skip_existing")} onFocus={focusGuide("behavior")}>{t("config.skipExisting")}</Checkbox>
           </Form.Item>
           <Form.Item name="singleThreaded" valuePropName="checked">
-            <Checkbox onFocus={focusGuide("behavior")}>{t("config.singleThreaded")}</Checkbox>
+            <Checkbox disabled={isLocked("behavior.single_threaded")} onFocus={focusGuide("behavior")}>{t("config.singleThreaded")}</Checkbox>
           </Form.Item>
           <Form.Item name="generateReadme" valuePropName="checked">
-            <Checkbox onFocus={focusGuide("behavior")}>{t("config.generateReadme")}</Checkbox>
+            <Checkbox disabled={isLocked("behavior.generate_readme")} onFocus={focusGuide("behavior")}>{t("config.generateReadme")}</Checkbox>
           </Form.Item>
           <Form.Item name="generateNfo" valuePropName="checked">
-            <Checkbox onFocus={focusGuide("behavior")}>{t("config.generateNfo")}</Checkbox>
+            <Checkbox disabled={isLocked("behavior.generate_nfo")} onFocus={focusGuide("behavior")}>{t("config.generateNfo")}</Checkbox>
           </Form.Item>
           <Form.Item name="metadataOnly" valuePropName="checked">
-            <Checkbox onFocus={focusGuide("behavior")}>{t("config.metadataOnly")}</Checkbox>
+            <Checkbox disabled={isLocked("behavior.metadata_only")} onFocus={focusGuide("behavior")}>{t("config.metadataOnly")}</Checkbox>
           </Form.Item>
           <Form.Item name="debug" valuePropName="checked">
-            <Checkbox onFocus={focusGuide("behavior")}>{t("config.debug")}</Checkbox>
+            <Checkbox disabled={isLocked("behavior.debug")} onFocus={focusGuide("behavior")}>{t("config.debug")}</Checkbox>
           </Form.Item>
         </Space>
 
         <Space align="start" size="large" style={{ width: "100%" }} wrap={true}>
           <Form.Item name="scheduleEnabled" valuePropName="checked">
-            <Checkbox onFocus={focusGuide("schedule")}>{t("config.scheduleEnabled")}</Checkbox>
+            <Checkbox disabled={isLocked("schedule.enabled")} onFocus={focusGuide("schedule")}>{t("config.scheduleEnabled")}</Checkbox>
           </Form.Item>
           <Form.Item
             label={t("config.scheduleCron")}
             name="scheduleCron"
             rules={[{ required: true, message: t("config.scheduleCronRequired") }]}
-            tooltip={t("config.scheduleCronHelp")}
+            tooltip={lockedTooltip("schedule.cron") ?? t("config.scheduleCronHelp")}
           >
             <Input
+              disabled={isLocked("schedule.cron")}
               placeholder="0 0 3 * * * *"
               style={{ width: 320 }}
               onFocus={focusGuide("schedule")}
@@ -1,5 +1,5 @@
 import { ReloadOutlined } from "@ant-design/icons";
-import { Alert, Button, Card, DatePicker, Empty, List, Select, Space, Tag, Typography } from "antd";
+import { Alert, Button, Card, DatePicker, Empty, Input, List, Select, Space, Tag, Typography } from "antd";
 import { useCallback, useEffect, useMemo, useState } from "react";
 import { api } from "../api.ts";
 import { useI18n } from "../i18n.tsx";
@@ -33,6 +33,7 @@ export function LogViewer() {
   const [date, setDate] = useState<PickerValue | null>(null);
   const [level, setLevel] = useState<string>();
   const [range, setRange] = useState<[PickerValue | null, PickerValue | null] | null>(null);
+  const [job, setJob] = useState("");
 
   const filters = useMemo(
     () => ({
@@ -40,8 +41,9 @@ export function LogViewer() {
       level,
       start: range?.[0]?.toISOString(),
       end: range?.[1]?.toISOString(),
+      job: job.trim() || undefined,
     }),
-    [date, level, range],
+    [date, level, range, job],
   );
 
   const loadLogs = useCallback(async () => {
@@ -93,6 +95,12 @@ export function LogViewer() {
             placeholder={[t("logs.filterStart"), t("logs.filterEnd")]}
             showTime={true}
           />
+          <Input.Search
+            allowClear={true}
+            onSearch={setJob}
+            placeholder={t("logs.filterJob")}
+            style={{ width: 160 }}
+          />
         </Space>
         {error ? <Alert showIcon={true} type="error" message={error} /> : null}
         {logs.length === 0 ? (
@@ -106,7 +114,12 @@ export function LogViewer() {
                 <Space align="start" size="middle">
                   <Tag color={levelColor[item.level]}>{item.level.toUpperCase()}</Tag>
                   <div>
-                    <Text type="secondary">{formatTime(item.timestamp)}</Text>
+                    <Space size="small" wrap={true}>
+                      <Text type="secondary">{formatTime(item.timestamp)}</Text>
+                      {item.job_id !== undefined ? <Tag>#{item.job_id}</Tag> : null}
+                      {item.account ? <Tag>{item.account}</Tag> : null}
+                      {item.album_id ? <Tag>{item.album_id}</Tag> : null}
+                    </Space>
                     <div>{item.message}</div>
                   </div>
                 </Space>
@@ -7,13 +7,14 @@ import {
   LoginOutlined,
   UserOutlined,
 } from "@ant-design/icons";
-import { Alert, Card, Tag, Typography } from "antd";
+import { Alert, Card, Progress, Tag, Typography } from "antd";
 import type { ReactNode } from "react";
 import { useI18n } from "../i18n.tsx";
-import type { StatusResponse, UserInfo } from "../types.ts";
+import type { FileProgressEvent, StatusResponse, UserInfo } from "../types.ts";
 
 interface StatusCardProps {
   status: StatusResponse | null;
+  fileProgress?: FileProgressEvent | null;
 }
 
 interface StatusMetricProps {
@@ -66,7 +67,39 @@ function StatusChip({ icon, label, value, tone = "default" }: StatusChipProps) {
   );
 }
 
-export function StatusCard({ status }: StatusCardProps) {
+function formatBytes(value: number): string {
+  const units = ["B", "KB", "MB", "GB"];
+  let size = value;
+  let unit = 0;
+  while (size >= 1024 && unit < units.length - 1) {
+    size /= 1024;
+    unit += 1;
+  }
+  return `${size.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`;
+}
+
+function FileProgressMeta({ progress }: { progress: FileProgressEvent }) {
+  const { t } = useI18n();
+  const fileName = progress.path.split(/[\\/]/).pop() ?? progress.path;
+  const percent = progress.total ? Math.round((progress.downloaded / progress.total) * 100) : 0;
+  const eta =
+    progress.total && progress.bytes_per_sec > 0
+      ? Math.ceil((progress.total - progress.downloaded) / progress.bytes_per_sec)
+      : null;
+
+  return (
+    <div className="status-file-progress">
+      <Typography.Text ellipsis={{ tooltip: progress.path }}>{fileName}</Typography.Text>
+      {progress.total ? <Progress percent={percent} size="small" /> : null}
+      <Typography.Text type="secondary">
+        {`${formatBytes(progress.downloaded)} · ${formatBytes(progress.bytes_per_sec)}/s`}
+        {eta === null ? "" : ` · ${t("status.eta", { seconds: eta })}`}
+      </Typography.Text>
+    </div>
+  );
+}
+
+export function StatusCard({ status, fileProgress }: StatusCardProps) {
   const { t } = useI18n();
 
   if (!status) {
@@ -118,10 +151,14 @@ export function StatusCard({ status }: StatusCardProps) {
   const heroDescription = status.ready
     ? t("status.heroReadyDescription")
     : t(status.requires_auth ? "status.heroAuthDescription" : "status.heroSetupDescription");
-  const jobMeta =
-    status.job.state === "running"
-      ? `${t("status.startedAt")}: ${formatTime(status.job.started_at)}`
-      : t("status.noActiveJob");
+  let jobMeta: ReactNode = t("status.noActiveJob");
+  if (status.job.state === "running") {
+    jobMeta = fileProgress ? (
+      <FileProgressMeta progress={fileProgress} />
+    ) : (
+      `${t("status.startedAt")}: ${formatTime(status.job.started_at)}`
+    );
+  }
 
   return (
     <Card className="status-card" title={t("status.title")}>
@@ -224,6 +261,14 @@ export function StatusCard({ status }: StatusCardProps) {
           message={t("status.lastError", { message: status.last_error })}
         />
       ) : null}
+      {status.config_error ? (
+        <Alert
+          className="status-alert"
+          showIcon={true}
+          type="warning"
+          message={t("status.configError", { message: status.config_error })}
+        />
+      ) : null}
     </Card>
   );
 }
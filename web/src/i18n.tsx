@@ -25,6 +25,7 @@ const translations: Record<Language, TranslationMap> = {
       "请输入 Docker 环境变量 DIZZYSYNC_WEB_PASSWORD 中设置的 Web UI 密码。",
     "auth.loginTitle": "登录 DizzySync",
     "auth.login": "登录",
+    "auth.logout": "退出登录",
     "tabs.dashboard": "控制台",
     "tabs.albums": "已购专辑",
     "tabs.logs": "日志",
@@ -53,6 +54,7 @@ const translations: Record<Language, TranslationMap> = {
     "status.lastRun": "上次运行",
     "status.scheduleLastError": "自动同步错误：{message}",
     "status.lastError": "最近错误：{message}",
+    "status.configError": "配置文件重新加载失败：{message}",
     "status.accountCount": ({ count }) => `${count} 个账号`,
     "status.heroReadyTitle": "服务运行正常",
     "status.heroNotReadyTitle": "服务需要完成设置",
@@ -63,6 +65,7 @@ const translations: Record<Language, TranslationMap> = {
       "请完成首次设置并验证 Dizzylab 账号；敏感凭据仅以已保存/未保存状态展示。",
     "status.startedAt": "开始时间",
     "status.noActiveJob": "当前没有正在运行的同步任务",
+    "status.eta": "预计剩余 {seconds} 秒",
     "status.accountSecurity": "账号与凭据",
     "status.accounts": "账号数",
     "status.credentials": "凭据",
@@ -130,6 +133,8 @@ const translations: Record<Language, TranslationMap> = {
     "config.password": "Dizzylab 密码",
     "config.passwordRequired": "请输入 Dizzylab 密码",
     "config.passwordPlaceholder": "留空保持不变",
+    "config.passwordFrom.file": "由 password_file 提供",
+    "config.passwordFrom.env": "由 password_env 提供",
     "config.addAccount": "添加 Dizzylab 账号",
     "config.removeAccount": "移除账号",
     "config.testLogin": "测试登录",
@@ -144,6 +149,7 @@ const translations: Record<Language, TranslationMap> = {
     "config.formatConflict": "128 和 320 不能同时选择，因为都会输出 .mp3 文件",
     "config.outputDir": "输出目录",
     "config.outputDirRequired": "请输入输出目录",
+    "config.envLocked": "该项由 DIZZYSYNC__ 环境变量设置，Web UI 中不允许修改。",
     "config.outputDirLocked": "输出目录由 DIZZYSYNC_OUTPUT_DIR 自动写入，Web UI 中不允许修改。",
     "config.directoryTemplate": "目录模板",
     "config.directoryTemplateRequired": "请输入目录模板",
@@ -177,6 +183,7 @@ const translations: Record<Language, TranslationMap> = {
     "logs.filterLevel": "按级别筛选",
     "logs.filterStart": "开始时间",
     "logs.filterEnd": "结束时间",
+    "logs.filterJob": "任务 ID",
     "footer.disclaimer":
       "DizzySync 是非官方项目，与 Dizzylab 官方无隶属、背书或合作关系。请遵守 Dizzylab 服务条款与当地法律，仅同步你有权访问的内容。",
     "footer.source": "开源地址",
@@ -196,7 +203,7 @@ const translations: Record<Language, TranslationMap> = {
     "guide.title": "配置指南",
     "guide.user.label": "登录凭据 [[users]]",
     "guide.user.body":
-      "可配置一个或多个 Dizzylab 账号。username 和 password 是登录凭据；首次设置和后续修改均在 Web UI 中完成。保存时会逐个登录 Dizzylab 验证，全部成功后才写入配置。旧版 [user] 配置仍会被兼容读取。",
+      "可配置一个或多个 Dizzylab 账号。username 和 password 是登录凭据；首次设置和后续修改均在 Web UI 中完成。保存时会逐个登录 Dizzylab 验证，全部成功后才写入配置。旧版 [user] 配置会在加载时自动迁移为 [[users]]。",
     "guide.download.label": "下载格式 [download]",
     "guide.download.conflict": "128 和 320 都会输出 .mp3 文件，不能同时选择，否则文件名会冲突。",
     "guide.paths.label": "路径与目录模板 [paths]",
@@ -231,6 +238,7 @@ const translations: Record<Language, TranslationMap> = {
       "Enter the Web UI password configured with the DIZZYSYNC_WEB_PASSWORD Docker environment variable.",
     "auth.loginTitle": "Log in to DizzySync",
     "auth.login": "Log in",
+    "auth.logout": "Log out",
     "tabs.dashboard": "Dashboard",
     "tabs.albums": "Purchased albums",
     "tabs.logs": "Logs",
@@ -259,6 +267,7 @@ const translations: Record<Language, TranslationMap> = {
     "status.lastRun": "Last run",
     "status.scheduleLastError": "Auto sync error: {message}",
     "status.lastError": "Last error: {message}",
+    "status.configError": "Config file reload failed: {message}",
     "status.accountCount": ({ count }) => `${count} account${count === 1 ? "" : "s"}`,
     "status.heroReadyTitle": "Service is healthy",
     "status.heroNotReadyTitle": "Service needs setup",
@@ -270,6 +279,7 @@ const translations: Record<Language, TranslationMap> = {
       "Complete first-time setup and verify Dizzylab accounts. Sensitive credentials are shown only as saved or missing.",
     "status.startedAt": "Started at",
     "status.noActiveJob": "No sync job is currently running",
+    "status.eta": "About {seconds}s left",
     "status.accountSecurity": "Accounts and credentials",
     "status.accounts": "Accounts",
     "status.credentials": "Credentials",
@@ -338,6 +348,8 @@ const translations: Record<Language, TranslationMap> = {
     "config.password": "Dizzylab password",
     "config.passwordRequired": "Enter your Dizzylab password",
     "config.passwordPlaceholder": "Leave blank to keep current value",
+    "config.passwordFrom.file": "Provided by password_file",
+    "config.passwordFrom.env": "Provided by password_env",
     "config.addAccount": "Add Dizzylab account",
     "config.removeAccount": "Remove account",
     "config.testLogin": "Test login",
@@ -352,6 +364,7 @@ const translations: Record<Language, TranslationMap> = {
     "config.formatConflict": "128 and 320 cannot both be selected because both write .mp3 files",
     "config.outputDir": "Output directory",
     "config.outputDirRequired": "Enter the output directory",
+    "config.envLocked": "Set by a DIZZYSYNC__ environment variable and cannot be changed in the Web UI.",
     "config.outputDirLocked":
       "The output directory is written from DIZZYSYNC_OUTPUT_DIR and cannot be changed in the Web UI.",
     "config.directoryTemplate": "Directory template",
@@ -387,6 +400,7 @@ const translations: Record<Language, TranslationMap> = {
     "logs.filterLevel": "Filter by level",
     "logs.filterStart": "Start time",
     "logs.filterEnd": "End time",
+    "logs.filterJob": "Job ID",
     "footer.disclaimer":
       "DizzySync is an unofficial project and is not affiliated with, endorsed by, or partnered with Dizzylab. Please follow Dizzylab terms and local laws, and only sync content you are allowed to access.",
     "footer.source": "Source code",
@@ -406,7 +420,7 @@ const translations: Record<Language, TranslationMap> = {
     "guide.title": "Configuration guide",
     "guide.user.label": "Login credentials [[users]]",
     "guide.user.body":
-      "Configure one or more Dizzylab accounts. username and password are the login credentials. Initial setup and future changes happen in the Web UI. Saving validates every account against Dizzylab before writing config. Legacy [user] config is still read for compatibility.",
+      "Configure one or more Dizzylab accounts. username and password are the login credentials. Initial setup and future changes happen in the Web UI. Saving validates every account against Dizzylab before writing config. Legacy [user] config is migrated to [[users]] on load.",
     "guide.download.label": "Download formats [download]",
     "guide.download.conflict":
       "128 and 320 both output .mp3 files and cannot be selected together.",
@@ -8,11 +8,14 @@ export interface StatusResponse {
   ready: boolean;
   configured: boolean;
   requires_auth: boolean;
+  scopes: ApiScope[];
   user: UserInfo | null;
   users: UserInfo[];
   job: JobState;
+  queue: QueuedJob[];
   schedule: ScheduleState;
   last_error: string | null;
+  config_error: string | null;
 }
 
 export interface ScheduleState {
@@ -27,9 +30,43 @@ export interface LogEntry {
   timestamp: number;
   level: "trace" | "debug" | "info" | "warn" | "error";
   message: string;
+  job_id?: number;
+  account?: string;
+  album_id?: string;
 }
 
-export type JobState = { state: "idle" } | { state: "running"; kind: string; started_at: number };
+export type JobState =
+  | { state: "idle" }
+  | { state: "running"; id: number; kind: string; started_at: number };
+
+export type JobKind =
+  | { type: "all" }
+  | { type: "account"; username: string }
+  | { type: "album"; id: string }
+  | ({ type: "filter" } & AlbumFilter);
+
+/** Album selection; every given criterion must match. Dates are YYYY-MM-DD. */
+export interface AlbumFilter {
+  ids?: string[];
+  labels?: string[];
+  tags?: string[];
+  released_after?: string;
+  released_before?: string;
+  title?: string;
+}
+
+export interface QueuedJob {
+  id: number;
+  kind: JobKind;
+  trigger: "manual" | "scheduled";
+  enqueued_at: number;
+}
+
+export interface EnqueueResponse {
+  message: string;
+  job: QueuedJob;
+  position: number;
+}
 
 export interface LocalAlbumState {
   downloaded: boolean;
@@ -117,6 +154,11 @@ export interface ConfigResponse {
   config: PublicConfig;
 }
 
+/** JSON Schema of config.toml, served by GET /api/config/schema. */
+export interface ConfigSchema {
+  $defs?: Record<string, { properties?: Record<string, { items?: { enum?: string[] } }> }>;
+}
+
 export interface PublicConfig {
   user: PublicUserConfig;
   users: PublicUserConfig[];
@@ -125,11 +167,13 @@ export interface PublicConfig {
   behavior: PublicBehaviorConfig;
   schedule: PublicScheduleConfig;
   api: PublicApiConfig;
+  env_locked: string[];
 }
 
 export interface PublicUserConfig {
   username: string;
   has_password: boolean;
+  password_source?: "file" | "env";
 }
 
 export interface PublicDownloadConfig {
@@ -158,10 +202,23 @@ export interface PublicScheduleConfig {
   cron: string;
 }
 
+export type ApiScope = "read" | "sync" | "admin";
+
+export interface WebLoginResponse {
+  message: string;
+  scopes: ApiScope[];
+}
+
+export interface PublicApiKey {
+  name: string;
+  scopes: ApiScope[];
+}
+
 export interface PublicApiConfig {
   bind: string;
   has_api_key: boolean;
   web_root: string;
+  keys: PublicApiKey[];
 }
 
 export interface UpdateUserConfig {
@@ -198,6 +255,30 @@ export interface UpdateConfigRequest {
   };
 }
 
+export type SyncEvent =
+  | { type: "job_started"; job_id: number; kind: string }
+  | { type: "album_started"; album_id: string; title: string; path: string }
+  | { type: "album_finished"; album_id: string; title: string; success: boolean; error?: string }
+  | { type: "format_started"; album_id: string; format: string; tracks: number }
+  | { type: "track_started"; album_id: string; format: string; track: number; title: string }
+  | {
+      type: "file_progress";
+      job_id?: number;
+      album_id: string;
+      path: string;
+      downloaded: number;
+      total?: number;
+      bytes_per_sec: number;
+      done: boolean;
+    }
+  | { type: "format_skipped"; album_id: string; format: string; reason: string }
+  | { type: "job_done"; job_id: number; success: boolean; interrupted: boolean; error?: string }
+  | { type: "login_failed"; account: string; error: string };
+
+export type ProgressEvent = SyncEvent & { timestamp: number };
+
+export type FileProgressEvent = Extract<ProgressEvent, { type: "file_progress" }>;
+
 export interface ApiMessage {
   message: string;
 }